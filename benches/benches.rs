@@ -0,0 +1,267 @@
+// `cargo bench` runs each script of `benches_oris/` and times lexing and
+// parsing a large file made of all the scripts
+//
+// pass `--save-baseline=<file>` to save the median time of each benchmark as
+// JSON, then `--baseline=<file>` to compare a later run against it, a change
+// slower than the baseline by more than `--threshold=<percent>`, 10 by default,
+// fails the run, e.g.
+//
+//     cargo bench --bench benches -- --save-baseline=base.json
+//     cargo bench --bench benches -- --baseline=base.json
+//
+// any other argument filters the benchmarks by name
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+// run each benchmark for at least that long, and at least `MIN_SAMPLES` times
+const MIN_TIME: Duration = Duration::from_secs(2);
+const MIN_SAMPLES: usize = 5;
+const MAX_SAMPLES: usize = 100;
+
+// size of the large file lexed and parsed
+const LARGE_FILE_SIZE: usize = 1 << 20;
+
+struct Options {
+    // `cargo test --benches` runs the benchmarks without `--bench`, once each,
+    // only to check they still work
+    bench: bool,
+    save_baseline: Option<String>,
+    baseline: Option<String>,
+    threshold: f64,
+    filters: Vec<String>,
+}
+
+struct Bench {
+    name: String,
+    run: Box<dyn Fn()>,
+}
+
+fn main() {
+    let mut options = Options {
+        bench: false,
+        save_baseline: None,
+        baseline: None,
+        threshold: 10.0,
+        filters: Vec::new(),
+    };
+
+    for arg in std::env::args().skip(1) {
+        if arg == "--bench" {
+            options.bench = true;
+        } else if let Some(value) = arg.strip_prefix("--save-baseline=") {
+            options.save_baseline = Some(value.to_owned());
+        } else if let Some(value) = arg.strip_prefix("--baseline=") {
+            options.baseline = Some(value.to_owned());
+        } else if let Some(value) = arg.strip_prefix("--threshold=") {
+            options.threshold = value
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid value for --threshold: '{}'", value));
+        } else if !arg.starts_with("--") {
+            options.filters.push(arg);
+        }
+    }
+
+    // deep recursion needs a larger stack than the main thread has
+    let ok = std::thread::Builder::new()
+        .stack_size(1 << 28)
+        .spawn(move || run(&options))
+        .unwrap()
+        .join()
+        .unwrap();
+
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> bool {
+    let benches = benches()
+        .into_iter()
+        .filter(|bench| {
+            options.filters.is_empty()
+                || options
+                    .filters
+                    .iter()
+                    .any(|filter| bench.name.contains(filter.as_str()))
+        })
+        .collect::<Vec<_>>();
+
+    if !options.bench {
+        for bench in &benches {
+            eprintln!("bench {}", bench.name);
+            (bench.run)();
+        }
+        return true;
+    }
+
+    let baseline = options.baseline.as_ref().map(|path| {
+        let json = std::fs::read_to_string(path)
+            .unwrap_or_else(|error| panic!("failed to read baseline {}: {}", path, error));
+        parse_baseline(&json).unwrap_or_else(|| {
+            panic!(
+                "invalid baseline {}, expected {{\"name\": nanos, ...}}",
+                path
+            )
+        })
+    });
+
+    let mut results = BTreeMap::new();
+    let mut regressed = Vec::new();
+
+    for bench in &benches {
+        let median = measure(&bench.run);
+
+        let mut line = format!("{:<20} {:>12}", bench.name, format_duration(median));
+        if let Some(&old) = baseline
+            .as_ref()
+            .and_then(|baseline| baseline.get(&bench.name))
+        {
+            let change = (median.as_nanos() as f64 / old as f64 - 1.0) * 100.0;
+            line += &format!("  {:+7.2}%", change);
+            if change > options.threshold {
+                line += "  regressed";
+                regressed.push(bench.name.as_str());
+            } else if change < -options.threshold {
+                line += "  improved";
+            }
+        }
+        println!("{}", line);
+
+        results.insert(bench.name.clone(), median.as_nanos());
+    }
+
+    if let Some(path) = &options.save_baseline {
+        std::fs::write(path, to_json(&results))
+            .unwrap_or_else(|error| panic!("failed to write baseline {}: {}", path, error));
+        eprintln!("saved baseline to {}", path);
+    }
+
+    if !regressed.is_empty() {
+        eprintln!(
+            "regressed by more than {}% x {}: {}",
+            options.threshold,
+            regressed.len(),
+            regressed.join(", ")
+        );
+    }
+
+    regressed.is_empty()
+}
+
+fn benches() -> Vec<Bench> {
+    let mut scripts = Vec::new();
+    for entry in std::fs::read_dir("benches_oris/").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "oris") {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            scripts.push((name, std::fs::read(&path).unwrap()));
+        }
+    }
+    scripts.sort();
+
+    let mut large = Vec::new();
+    while large.len() < LARGE_FILE_SIZE {
+        for (_, code) in &scripts {
+            large.extend_from_slice(code);
+            large.push(b'\n');
+        }
+    }
+    let large = std::rc::Rc::new(large);
+
+    let mut benches = vec![
+        Bench {
+            name: "lex".to_owned(),
+            run: Box::new({
+                let large = large.clone();
+                move || {
+                    for token in oris::syntax::tokenize(&large) {
+                        token.unwrap();
+                    }
+                }
+            }),
+        },
+        Bench {
+            name: "parse".to_owned(),
+            run: Box::new(move || {
+                oris::syntax::parse(&large).unwrap();
+            }),
+        },
+    ];
+
+    // only the evaluation is timed, with the output of `print()` discarded
+    for (name, code) in scripts {
+        let program = oris::Program::compile(&code)
+            .unwrap_or_else(|error| panic!("failed to compile {}: {}", name, error));
+        benches.push(Bench {
+            name,
+            run: Box::new(move || {
+                let mut env = oris::Env::builder()
+                    .with_builtin()
+                    .with_output(std::io::sink())
+                    .build();
+                program.run(&mut env).unwrap();
+            }),
+        });
+    }
+
+    benches
+}
+
+// median time of a run
+fn measure(run: &dyn Fn()) -> Duration {
+    // warm up
+    run();
+
+    let start = Instant::now();
+    let mut samples = Vec::new();
+    while samples.len() < MIN_SAMPLES || (samples.len() < MAX_SAMPLES && start.elapsed() < MIN_TIME)
+    {
+        let sample = Instant::now();
+        run();
+        samples.push(sample.elapsed());
+    }
+
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos() as f64;
+    if nanos >= 1e9 {
+        format!("{:.3} s", nanos / 1e9)
+    } else if nanos >= 1e6 {
+        format!("{:.3} ms", nanos / 1e6)
+    } else {
+        format!("{:.3} us", nanos / 1e3)
+    }
+}
+
+fn to_json(results: &BTreeMap<String, u128>) -> String {
+    let fields = results
+        .iter()
+        .map(|(name, nanos)| format!("  \"{}\": {}", name, nanos))
+        .collect::<Vec<_>>();
+    format!("{{\n{}\n}}\n", fields.join(",\n"))
+}
+
+// the flat object written by `to_json()`, the names of the benchmarks need no
+// escaping
+fn parse_baseline(json: &str) -> Option<BTreeMap<String, u128>> {
+    let fields = json.trim().strip_prefix('{')?.strip_suffix('}')?.trim();
+
+    let mut baseline = BTreeMap::new();
+    if fields.is_empty() {
+        return Some(baseline);
+    }
+
+    for field in fields.split(',') {
+        let (name, nanos) = field.split_once(':')?;
+        let name = name.trim().strip_prefix('"')?.strip_suffix('"')?;
+        baseline.insert(name.to_owned(), nanos.trim().parse().ok()?);
+    }
+
+    Some(baseline)
+}
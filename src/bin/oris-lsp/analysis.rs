@@ -0,0 +1,196 @@
+use oris::syntax::{self, Closure, Ident, Let, Node, Visitor};
+
+/// where the identifiers of a document are defined, resolved statically with
+/// the same scoping as the evaluator: only a closure opens a scope, a `let` in
+/// a block binds in the scope of the closure or the top level around it
+pub(crate) struct Analysis<'ast> {
+    scopes: Vec<Scope<'ast>>,
+    current: usize,
+    // identifiers used as expressions, with the scope they appear in
+    uses: Vec<(&'ast Ident, usize)>,
+}
+
+struct Scope<'ast> {
+    parent: Option<usize>,
+    bindings: Vec<Binding<'ast>>,
+}
+
+struct Binding<'ast> {
+    ident: &'ast Ident,
+    // `let x = x;` refers to the previous `x` until the statement ends
+    visible: usize,
+}
+
+impl<'ast> Analysis<'ast> {
+    pub(crate) fn new(nodes: &'ast [Node]) -> Self {
+        let mut analysis = Self {
+            scopes: vec![Scope {
+                parent: None,
+                bindings: Vec::new(),
+            }],
+            current: 0,
+            uses: Vec::new(),
+        };
+
+        for node in nodes {
+            analysis.visit_node(node);
+        }
+
+        analysis
+    }
+
+    /// the identifier at `offset`, and the binding it refers to, which is
+    /// itself for an identifier being bound and `None` for a builtin or an
+    /// undefined one
+    pub(crate) fn lookup(&self, offset: usize) -> Option<(&'ast Ident, Option<&'ast Ident>)> {
+        let contains =
+            |ident: &Ident| ident.span().start() <= offset && offset <= ident.span().end();
+
+        if let Some(&(ident, scope)) = self.uses.iter().find(|(ident, _)| contains(ident)) {
+            return Some((ident, self.resolve(ident, scope)));
+        }
+
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.bindings.iter())
+            .find(|binding| contains(binding.ident))
+            .map(|binding| (binding.ident, Some(binding.ident)))
+    }
+
+    /// the top level bindings, the last one of each name
+    pub(crate) fn globals(&self) -> Vec<&'ast Ident> {
+        let mut globals = Vec::<&Ident>::new();
+
+        for binding in self.scopes[0].bindings.iter() {
+            match globals
+                .iter_mut()
+                .find(|ident| ident.sym() == binding.ident.sym())
+            {
+                Some(ident) => *ident = binding.ident,
+                None => globals.push(binding.ident),
+            }
+        }
+
+        globals
+    }
+
+    // the innermost scope binding the name wins, in which the last binding
+    // before the use, or else the first one after it, e.g. a closure calling
+    // itself or a function defined later
+    fn resolve(&self, ident: &Ident, mut scope: usize) -> Option<&'ast Ident> {
+        let position = ident.span().start();
+
+        loop {
+            let scope_ = &self.scopes[scope];
+            let mut bindings = scope_
+                .bindings
+                .iter()
+                .filter(|binding| binding.ident.sym() == ident.sym());
+
+            let first = bindings.next();
+            if let Some(first) = first {
+                let found = std::iter::once(first)
+                    .chain(bindings)
+                    .take_while(|binding| binding.visible <= position)
+                    .last()
+                    .unwrap_or(first);
+                return Some(found.ident);
+            }
+
+            scope = scope_.parent?;
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        let parent = self.current;
+
+        self.scopes.push(Scope {
+            parent: Some(parent),
+            bindings: Vec::new(),
+        });
+        self.current = self.scopes.len() - 1;
+
+        f(self);
+
+        self.current = parent;
+    }
+
+    fn bind(&mut self, ident: &'ast Ident, visible: usize) {
+        self.scopes[self.current]
+            .bindings
+            .push(Binding { ident, visible });
+    }
+}
+
+impl<'ast> Visitor<'ast> for Analysis<'ast> {
+    fn visit_let(&mut self, let_: &'ast Let) {
+        syntax::walk_let(self, let_);
+        self.bind(let_.ident(), let_.span().end());
+    }
+
+    fn visit_ident(&mut self, ident: &'ast Ident) {
+        self.uses.push((ident, self.current));
+    }
+
+    fn visit_closure(&mut self, closure: &'ast Closure) {
+        self.scoped(|this| {
+            for parameter in closure.parameters() {
+                this.bind(parameter, closure.span().start());
+            }
+            syntax::walk_closure(this, closure);
+        });
+    }
+}
+
+#[test]
+fn lookup() {
+    let code = "let x = 1; let f = fn(x) { x + y }; let x = x + f(2); let y = 3;";
+    let nodes = syntax::parse(code.as_bytes()).unwrap();
+    let analysis = Analysis::new(&nodes);
+
+    let definition = |offset| {
+        analysis
+            .lookup(offset)
+            .and_then(|(_, binding)| binding)
+            .map(|ident| ident.span().start())
+    };
+
+    // the parameter shadows the global
+    assert_eq!(definition(code.find("x + y").unwrap()), Some(22));
+    // a global defined later
+    assert_eq!(definition(code.find("y }").unwrap()), Some(58));
+    // the previous `x` in its own `let`
+    assert_eq!(definition(code.find("x + f").unwrap()), Some(4));
+    assert_eq!(definition(code.find("f(2)").unwrap()), Some(15));
+    // a binding is its own definition
+    assert_eq!(definition(40), Some(40));
+
+    let globals = analysis.globals();
+    let globals: Vec<_> = globals.iter().map(|ident| ident.span().start()).collect();
+    assert_eq!(globals, [40, 15, 58]);
+}
+
+#[test]
+fn block() {
+    let code = "if true { let x = 1; } x; let f = fn(c) { if c { let y = 2; } y };";
+    let nodes = syntax::parse(code.as_bytes()).unwrap();
+    let analysis = Analysis::new(&nodes);
+
+    let definition = |offset| {
+        analysis
+            .lookup(offset)
+            .and_then(|(_, binding)| binding)
+            .map(|ident| ident.span().start())
+    };
+
+    // a block doesn't open a scope, in a closure or at the top level
+    assert_eq!(definition(code.find("x;").unwrap()), Some(14));
+    assert_eq!(
+        definition(code.find("y }").unwrap()),
+        Some(code.find("y =").unwrap())
+    );
+
+    let globals = analysis.globals();
+    let globals: Vec<_> = globals.iter().map(|ident| ident.span().start()).collect();
+    assert_eq!(globals, [14, code.find("f =").unwrap()]);
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+
+/// just enough JSON for the Language Server Protocol
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    // fields in the order of insertion
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        Self::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // `json.path(&["a", "b"])` is `json.get("a")?.get("b")`
+    pub(crate) fn path(&self, keys: &[&str]) -> Option<&Json> {
+        keys.iter().try_fold(self, |json, key| json.get(key))
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Self::Number(n) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            cursor: 0,
+        };
+
+        let json = parser.value()?;
+        parser.skip_whitespaces();
+
+        (parser.cursor == text.len()).then_some(json)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::Str(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Self::Str(s)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Self::Number(n as f64)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Str(s) => write_str(f, s),
+            Self::Array(elements) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    element.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            Self::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write_str(f, key)?;
+                    f.write_str(":")?;
+                    value.fmt(f)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    use fmt::Write;
+
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    cursor: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespaces();
+
+        match *self.text.get(self.cursor)? {
+            b'n' => self.keyword("null", Json::Null),
+            b't' => self.keyword("true", Json::Bool(true)),
+            b'f' => self.keyword("false", Json::Bool(false)),
+            b'"' => self.string().map(Json::Str),
+            b'[' => {
+                self.cursor += 1;
+
+                let mut elements = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        elements.push(self.value()?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+
+                Some(Json::Array(elements))
+            }
+            b'{' => {
+                self.cursor += 1;
+
+                let mut fields = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespaces();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        fields.push((key, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+
+                Some(Json::Object(fields))
+            }
+            _ => self.number(),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, json: Json) -> Option<Json> {
+        if self.text[self.cursor..].starts_with(keyword.as_bytes()) {
+            self.cursor += keyword.len();
+            Some(json)
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.cursor;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.text.get(self.cursor)
+        {
+            self.cursor += 1;
+        }
+
+        std::str::from_utf8(&self.text[start..self.cursor])
+            .ok()?
+            .parse()
+            .ok()
+            .map(Json::Number)
+    }
+
+    // `self.cursor` points to the left `"`
+    fn string(&mut self) -> Option<String> {
+        if self.text.get(self.cursor) != Some(&b'"') {
+            return None;
+        }
+        self.cursor += 1;
+
+        let mut s = Vec::new();
+        loop {
+            match *self.text.get(self.cursor)? {
+                b'"' => {
+                    self.cursor += 1;
+                    return String::from_utf8(s).ok();
+                }
+                b'\\' => {
+                    let escaped = *self.text.get(self.cursor + 1)?;
+                    self.cursor += 2;
+
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    };
+                    s.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                b => {
+                    s.push(b);
+                    self.cursor += 1;
+                }
+            }
+        }
+    }
+
+    // `\uXXXX` with the `\u` skipped, or a surrogate pair `\uXXXX\uXXXX`
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+
+        if (0xd800..0xdc00).contains(&high) {
+            if !self.text[self.cursor..].starts_with(b"\\u") {
+                return None;
+            }
+            self.cursor += 2;
+
+            let low = self.hex4()?;
+            char::from_u32(0x10000 + ((high - 0xd800) << 10) + low.checked_sub(0xdc00)?)
+        } else {
+            char::from_u32(high)
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = std::str::from_utf8(self.text.get(self.cursor..self.cursor + 4)?).ok()?;
+        self.cursor += 4;
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        self.skip_whitespaces();
+
+        if self.text.get(self.cursor) == Some(&b) {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        self.eat(b).then_some(())
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.get(self.cursor) {
+            self.cursor += 1;
+        }
+    }
+}
+
+#[test]
+fn round_trip() {
+    let text = r#"{"a":[1,-2.5,true,null],"b":"x\"\né😀","c":{}}"#;
+    let json = Json::parse(text).unwrap();
+
+    assert_eq!(json.path(&["a"]).unwrap().as_array().unwrap().len(), 4);
+    assert_eq!(json.get("b").unwrap().as_str(), Some("x\"\né😀"));
+    assert_eq!(
+        json.to_string(),
+        r#"{"a":[1,-2.5,true,null],"b":"x\"\né😀","c":{}}"#
+    );
+
+    assert_eq!(Json::parse("[1,]"), None);
+    assert_eq!(Json::parse("{} x"), None);
+}
@@ -0,0 +1,511 @@
+//! a language server for oris, speaking the Language Server Protocol over
+//! stdio
+
+mod analysis;
+mod json;
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use json::Json;
+
+// limits of the evaluation of a document, which may not terminate at all,
+// recurse without end or build huge values while being edited
+const EVAL_TIMEOUT: Duration = Duration::from_secs(1);
+const EVAL_MEMORY_LIMIT: usize = 64 << 20;
+const EVAL_MAX_DEPTH: usize = 10_000;
+// enough for `EVAL_MAX_DEPTH` nested calls even in a debug build, only the
+// part used is committed
+const EVAL_STACK_SIZE: usize = 1 << 30;
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND: i32 = -32601;
+const SERVER_NOT_INITIALIZED: i32 = -32002;
+
+// LSP enums
+const SEVERITY_ERROR: usize = 1;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_VARIABLE: usize = 6;
+const SYNC_FULL: usize = 1;
+
+fn main() {
+    let mut server = Server::new();
+
+    let mut stdin = std::io::stdin().lock();
+    while let Some(message) = read_message(&mut stdin) {
+        server.handle(message);
+    }
+
+    // stdin is closed without an `exit` notification
+    std::process::exit(1);
+}
+
+struct Server {
+    initialized: bool,
+    shutdown: bool,
+    // uri -> text
+    documents: HashMap<String, String>,
+    evaluations: Arc<Evaluations>,
+}
+
+// the documents to evaluate, one at a time by a single worker thread, which
+// publishes the diagnostics of each one
+//
+// the queue is also locked to publish the diagnostics of a document, so that
+// those of a stale evaluation are never published after newer ones
+#[derive(Default)]
+struct Evaluations {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct Queue {
+    // the latest text of each document not evaluated yet, in order
+    pending: Vec<(String, String)>,
+    // the document being evaluated and how to interrupt it
+    running: Option<(String, oris::InterruptHandle)>,
+    // set once the document being evaluated changes or is closed
+    stale: bool,
+}
+
+impl Evaluations {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap()
+    }
+
+    // evaluate the documents queued forever
+    fn work(&self) {
+        loop {
+            let mut queue = self.lock();
+            while queue.pending.is_empty() {
+                queue = self.ready.wait(queue).unwrap();
+            }
+            let (uri, text) = queue.pending.remove(0);
+
+            let mut env = oris::Env::builder()
+                .with_builtin()
+                .with_output(std::io::sink())
+                .with_timeout(EVAL_TIMEOUT)
+                .with_memory_limit(EVAL_MEMORY_LIMIT)
+                .with_max_depth(EVAL_MAX_DEPTH)
+                .build();
+            queue.running = Some((uri.clone(), env.interrupt_handle()));
+            queue.stale = false;
+            drop(queue);
+
+            let mut diagnostics = Vec::new();
+            match oris::entry(&mut env, text.as_bytes()) {
+                // code running too long isn't necessarily wrong
+                Err(err) if err.name() == "timeout" || err.name() == "interrupted" => {}
+                Err(err) => {
+                    diagnostics.push(diagnostic(&text, err.span(), err.name(), &err.to_string()));
+                }
+                Ok(_) => {}
+            }
+
+            let mut queue = self.lock();
+            if !queue.stale {
+                publish(&uri, diagnostics);
+            }
+            queue.running = None;
+        }
+    }
+}
+
+impl Queue {
+    // drop the evaluation of a document which has changed or is closed
+    fn cancel(&mut self, uri: &str) {
+        self.pending.retain(|(pending, _)| pending != uri);
+
+        if let Some((running, interrupt)) = &self.running {
+            if running == uri {
+                self.stale = true;
+                interrupt.interrupt();
+            }
+        }
+    }
+}
+
+impl Server {
+    fn new() -> Self {
+        let evaluations = Arc::new(Evaluations::default());
+
+        // deep recursion needs a large stack, only the part used is committed
+        std::thread::Builder::new()
+            .stack_size(EVAL_STACK_SIZE)
+            .spawn({
+                let evaluations = Arc::clone(&evaluations);
+                move || evaluations.work()
+            })
+            .unwrap();
+
+        Self {
+            initialized: false,
+            shutdown: false,
+            documents: HashMap::new(),
+            evaluations,
+        }
+    }
+
+    fn handle(&mut self, message: Json) {
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        let result = match method {
+            "initialize" => {
+                self.initialized = true;
+                Some(capabilities())
+            }
+            "exit" => std::process::exit(if self.shutdown { 0 } else { 1 }),
+            _ if !self.initialized => {
+                if let Some(id) = id {
+                    error(id, SERVER_NOT_INITIALIZED, "server not initialized");
+                }
+                return;
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Json::Null)
+            }
+            "textDocument/didOpen" => {
+                if let (Some(uri), Some(text)) = (
+                    params.path(&["textDocument", "uri"]).and_then(Json::as_str),
+                    params
+                        .path(&["textDocument", "text"])
+                        .and_then(Json::as_str),
+                ) {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                    self.publish_diagnostics(uri);
+                }
+                None
+            }
+            "textDocument/didChange" => {
+                // the whole text is sent with `SYNC_FULL`
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+
+                if let (Some(uri), Some(text)) = (uri(&params), text) {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                    self.publish_diagnostics(uri);
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = uri(&params) {
+                    self.documents.remove(uri);
+                    self.evaluations.lock().cancel(uri);
+                    notify(
+                        "textDocument/publishDiagnostics",
+                        Json::object([("uri", uri.into()), ("diagnostics", Json::Array(vec![]))]),
+                    );
+                }
+                None
+            }
+            "textDocument/definition" => Some(self.definition(&params).unwrap_or(Json::Null)),
+            "textDocument/hover" => Some(self.hover(&params).unwrap_or(Json::Null)),
+            "textDocument/completion" => Some(self.completion(&params).unwrap_or(Json::Null)),
+            _ => {
+                if let Some(id) = id {
+                    error(
+                        id,
+                        METHOD_NOT_FOUND,
+                        &format!("unknown method '{}'", method),
+                    );
+                }
+                return;
+            }
+        };
+
+        // notifications are never responded
+        if let (Some(id), Some(result)) = (id, result) {
+            send(Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id),
+                ("result", result),
+            ]));
+        }
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) {
+        let text = &self.documents[uri];
+
+        let (_, errors) = oris::syntax::parse_with_recovery(text.as_bytes());
+        let diagnostics: Vec<_> = errors
+            .iter()
+            .map(|err| diagnostic(text, err.span(), err.name(), &err.to_string()))
+            .collect();
+
+        let mut queue = self.evaluations.lock();
+        queue.cancel(uri);
+
+        // evaluate only valid code, a runtime error is reported at where the
+        // evaluation stops, its output is discarded
+        if diagnostics.is_empty() {
+            queue.pending.push((uri.to_owned(), text.clone()));
+            self.evaluations.ready.notify_one();
+        } else {
+            publish(uri, diagnostics);
+        }
+    }
+
+    fn definition(&self, params: &Json) -> Option<Json> {
+        let (uri, text, offset) = self.document_position(params)?;
+
+        let (nodes, _) = oris::syntax::parse_with_recovery(text.as_bytes());
+        let analysis = analysis::Analysis::new(&nodes);
+        let (_, binding) = analysis.lookup(offset)?;
+        let span = binding?.span();
+
+        Some(Json::object([
+            ("uri", uri.into()),
+            ("range", range(text, span.start()..span.end())),
+        ]))
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let (_, text, offset) = self.document_position(params)?;
+
+        let (nodes, _) = oris::syntax::parse_with_recovery(text.as_bytes());
+        let analysis = analysis::Analysis::new(&nodes);
+
+        // builtins are shadowed by any binding of the same name
+        let (ident, None) = analysis.lookup(offset)? else {
+            return None;
+        };
+        let (name, signatures) = oris::builtins().find(|(name, _)| *name == ident.sym())?;
+
+        let mut value = String::from("```oris\n");
+        for signature in signatures {
+            value.push_str(&format!("{}: {}\n", name, signature));
+        }
+        value.push_str("```");
+
+        let span = ident.span();
+        Some(Json::object([
+            (
+                "contents",
+                Json::object([("kind", "markdown".into()), ("value", value.into())]),
+            ),
+            ("range", range(text, span.start()..span.end())),
+        ]))
+    }
+
+    fn completion(&self, params: &Json) -> Option<Json> {
+        let text = self.documents.get(uri(params)?)?;
+
+        let (nodes, _) = oris::syntax::parse_with_recovery(text.as_bytes());
+        let analysis = analysis::Analysis::new(&nodes);
+
+        let globals = analysis.globals().into_iter().map(|ident| {
+            Json::object([
+                ("label", ident.sym().into()),
+                ("kind", COMPLETION_VARIABLE.into()),
+            ])
+        });
+
+        let builtins = oris::builtins()
+            .filter(|(name, _)| !analysis.globals().iter().any(|ident| ident.sym() == *name))
+            .map(|(name, signatures)| {
+                Json::object([
+                    ("label", name.into()),
+                    ("kind", COMPLETION_FUNCTION.into()),
+                    ("detail", signatures.first().copied().unwrap_or("").into()),
+                ])
+            });
+
+        Some(Json::Array(globals.chain(builtins).collect()))
+    }
+
+    // `TextDocumentPositionParams`
+    fn document_position<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a str, usize)> {
+        let uri = uri(params)?;
+        let text = self.documents.get(uri)?;
+
+        let line = params.path(&["position", "line"])?.as_usize()?;
+        let character = params.path(&["position", "character"])?.as_usize()?;
+
+        Some((uri, text, offset(text, line, character)))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", SYNC_FULL.into()),
+                ("definitionProvider", Json::Bool(true)),
+                ("hoverProvider", Json::Bool(true)),
+                ("completionProvider", Json::object([])),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "oris-lsp".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn uri(params: &Json) -> Option<&str> {
+    params.path(&["textDocument", "uri"])?.as_str()
+}
+
+fn diagnostic(text: &str, span: std::ops::Range<usize>, name: &str, message: &str) -> Json {
+    Json::object([
+        ("range", range(text, span)),
+        ("severity", SEVERITY_ERROR.into()),
+        ("code", name.into()),
+        ("source", "oris".into()),
+        ("message", message.into()),
+    ])
+}
+
+fn range(text: &str, span: std::ops::Range<usize>) -> Json {
+    Json::object([
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+// LSP positions are zero based lines and UTF-16 code units in the line
+fn position(text: &str, offset: usize) -> Json {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    Json::object([
+        ("line", before.matches('\n').count().into()),
+        (
+            "character",
+            before[line_start..].encode_utf16().count().into(),
+        ),
+    ])
+}
+
+// the byte offset of a LSP position, clamped to the end of the line
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        },
+    };
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if c == '\n' || units >= character {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+fn read_message(input: &mut impl BufRead) -> Option<Json> {
+    loop {
+        let mut length = None;
+
+        loop {
+            let mut header = String::new();
+            if input.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+
+        let mut content = vec![0; length?];
+        input.read_exact(&mut content).ok()?;
+
+        // skip a malformed message
+        if let Some(json) = std::str::from_utf8(&content).ok().and_then(Json::parse) {
+            return Some(json);
+        }
+    }
+}
+
+fn send(message: Json) {
+    let content = message.to_string();
+
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(
+        stdout,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    );
+    let _ = stdout.flush();
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) {
+    notify(
+        "textDocument/publishDiagnostics",
+        Json::object([
+            ("uri", uri.into()),
+            ("diagnostics", Json::Array(diagnostics)),
+        ]),
+    );
+}
+
+fn notify(method: &str, params: Json) {
+    send(Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ]));
+}
+
+fn error(id: Json, code: i32, message: &str) {
+    send(Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code.into())),
+                ("message", message.into()),
+            ]),
+        ),
+    ]));
+}
+
+#[test]
+fn positions() {
+    let text = "ab\n😀x\n";
+
+    assert_eq!(position(text, 4).to_string(), r#"{"line":1,"character":0}"#);
+    assert_eq!(position(text, 7).to_string(), r#"{"line":1,"character":2}"#);
+    assert_eq!(position(text, 9).to_string(), r#"{"line":2,"character":0}"#);
+
+    assert_eq!(offset(text, 1, 2), 7);
+    assert_eq!(offset(text, 0, 10), 2);
+    assert_eq!(offset(text, 5, 0), text.len());
+}
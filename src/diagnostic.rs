@@ -0,0 +1,559 @@
+use std::fmt;
+
+use crate::{embed::pos_to_line_column, eval, lint::Lint, span::Span, ErrorKind};
+
+/// human readable report of an [`Error`](crate::Error) or a
+/// [`Lint`](crate::Lint), with the offending source line and carets under the
+/// span of it
+///
+/// ```text
+/// error: undefined identifier: oo
+///  --> foo.oris:2:5
+///   |
+/// 2 |     oo + 1
+///   |     ^^
+///   |
+///   = note: a similar identifier exists: foo
+/// ```
+///
+/// or as a single line JSON object for tools, see [`json`](Self::json)
+pub struct Diagnostic<'a> {
+    report: Report<'a>,
+    code: &'a [u8],
+    file: Option<&'a str>,
+    color: bool,
+    json: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Report<'a> {
+    Error(&'a eval::Error),
+    Lint(&'a Lint),
+}
+
+struct Label {
+    span: Span,
+    message: &'static str,
+    primary: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub(crate) fn new(error: &'a eval::Error, code: &'a [u8]) -> Self {
+        Self::of(Report::Error(error), code)
+    }
+
+    pub(crate) fn lint(lint: &'a Lint, code: &'a [u8]) -> Self {
+        Self::of(Report::Lint(lint), code)
+    }
+
+    fn of(report: Report<'a>, code: &'a [u8]) -> Self {
+        Self {
+            report,
+            code,
+            file: None,
+            color: false,
+            json: false,
+        }
+    }
+
+    /// name of the source file shown in the location line
+    pub fn file(mut self, file: &'a str) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// emit ANSI colour escapes, the output is plain text by default
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// render as a JSON object on a single line instead, e.g.
+    ///
+    /// ```text
+    /// {"file":"foo.oris","line":2,"column":5,"span":{"start":17,"end":19},"kind":"eval","name":"undefined","message":"undefined identifier: oo","notes":["note: a similar identifier exists: foo"]}
+    /// ```
+    ///
+    /// `line` and `column` are 1 based, `column` is counted in chars, `span`
+    /// is the byte range in the source code and `file` is `null` if not set
+    ///
+    /// `kind` is `"lint"` for a [`Lint`](crate::Lint), whose `name` is the
+    /// name of its [`Rule`](crate::Rule)
+    pub fn json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    fn labels(&self) -> Vec<Label> {
+        let mut labels = vec![Label {
+            span: self.report.span(),
+            message: "",
+            primary: true,
+        }];
+
+        match self.report {
+            Report::Error(&eval::Error::ArgCount {
+                defined: Some(span),
+                ..
+            }) => labels.push(Label {
+                span,
+                message: "closure defined here",
+                primary: false,
+            }),
+            Report::Lint(lint) => {
+                if let Some((span, message)) = lint.related {
+                    labels.push(Label {
+                        span,
+                        message,
+                        primary: false,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        // a position from another piece of code, e.g. a closure defined in a
+        // previous line of REPL, can't be shown
+        labels.retain(|label| label.span.end <= self.code.len());
+
+        labels.sort_by_key(|label| label.span.start);
+
+        labels
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.json {
+            return self.fmt_json(f);
+        }
+
+        let style = Style { color: self.color };
+
+        let (headline, notes) = self.report.describe();
+
+        let (severity, severity_style) = match self.report {
+            Report::Error(_) => ("error", Style::ERROR),
+            Report::Lint(_) => ("warning", Style::WARNING),
+        };
+
+        writeln!(
+            f,
+            "{}{}",
+            style.paint(severity_style, severity),
+            style.paint(Style::BOLD, &format!(": {}", headline))
+        )?;
+
+        let labels = self.labels();
+
+        let lines = labels
+            .iter()
+            .map(|label| pos_to_line_column(label.span.start, self.code))
+            .collect::<Vec<_>>();
+
+        let width = lines
+            .iter()
+            .map(|(line, _)| (line + 1).to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+
+        if let Some(&(line, column)) = labels
+            .iter()
+            .zip(lines.iter())
+            .find_map(|(label, line)| label.primary.then_some(line))
+        {
+            write!(f, "{}{} ", pad, style.paint(Style::GUTTER, "-->"))?;
+            if let Some(file) = self.file {
+                write!(f, "{}:", file)?;
+            }
+            writeln!(f, "{}:{}", line + 1, column + 1)?;
+        }
+
+        if !labels.is_empty() {
+            writeln!(f, "{} {}", pad, style.paint(Style::GUTTER, "|"))?;
+        }
+
+        let mut last_line = None;
+        for (label, &(line, _)) in labels.iter().zip(lines.iter()) {
+            let (start, end) = line_bounds(self.code, label.span.start);
+
+            if last_line != Some(line) {
+                if matches!(last_line, Some(last) if line > last + 1) {
+                    writeln!(f, "{}", style.paint(Style::GUTTER, "..."))?;
+                }
+
+                let number = format!("{:>width$}", line + 1, width = width);
+                writeln!(
+                    f,
+                    "{} {} {}",
+                    style.paint(Style::GUTTER, &number),
+                    style.paint(Style::GUTTER, "|"),
+                    String::from_utf8_lossy(&self.code[start..end]).trim_end()
+                )?;
+
+                last_line = Some(line);
+            }
+
+            // keep tabs so the marker lines up with the source line
+            let indent = String::from_utf8_lossy(&self.code[start..label.span.start])
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+
+            // a span over several lines is only marked on its first line
+            let marker_len =
+                String::from_utf8_lossy(&self.code[label.span.start..label.span.end.min(end)])
+                    .chars()
+                    .count()
+                    .max(1);
+
+            let (marker, marker_style) = if label.primary {
+                ("^", severity_style)
+            } else {
+                ("-", Style::GUTTER)
+            };
+
+            let mut marker = marker.repeat(marker_len);
+            if !label.message.is_empty() {
+                marker.push(' ');
+                marker.push_str(label.message);
+            }
+
+            writeln!(
+                f,
+                "{} {} {}{}",
+                pad,
+                style.paint(Style::GUTTER, "|"),
+                indent,
+                style.paint(marker_style, &marker)
+            )?;
+        }
+
+        if !notes.is_empty() {
+            if !labels.is_empty() {
+                writeln!(f, "{} {}", pad, style.paint(Style::GUTTER, "|"))?;
+            }
+
+            for note in notes {
+                writeln!(f, "{} {} {}", pad, style.paint(Style::GUTTER, "="), note)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Diagnostic<'_> {
+    fn fmt_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.report.span();
+        let (line, column) = pos_to_line_column(span.start, self.code);
+        let (message, notes) = self.report.describe();
+
+        f.write_str("{\"file\":")?;
+        match self.file {
+            Some(file) => write_json_str(f, file)?,
+            None => f.write_str("null")?,
+        }
+
+        write!(
+            f,
+            ",\"line\":{},\"column\":{},\"span\":{{\"start\":{},\"end\":{}}}",
+            line + 1,
+            column + 1,
+            span.start,
+            span.end
+        )?;
+
+        write!(
+            f,
+            ",\"kind\":\"{}\",\"name\":\"{}\",\"message\":",
+            self.report.kind(),
+            self.report.name()
+        )?;
+        write_json_str(f, &message)?;
+
+        f.write_str(",\"notes\":[")?;
+        for (i, note) in notes.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            write_json_str(f, note)?;
+        }
+        f.write_str("]}\n")
+    }
+}
+
+fn write_json_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    use fmt::Write;
+
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Report<'_> {
+    fn span(&self) -> Span {
+        match self {
+            Self::Error(error) => error.span(),
+            Self::Lint(lint) => lint.span,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Error(error) => ErrorKind::of(error).as_str(),
+            Self::Lint(_) => "lint",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Error(error) => error.name(),
+            Self::Lint(lint) => lint.rule.as_str(),
+        }
+    }
+
+    // split the message into a headline and trailing notes
+    fn describe(&self) -> (String, Vec<String>) {
+        match self {
+            Self::Error(error) => describe(error),
+            Self::Lint(lint) => (lint.message.clone(), lint.notes.clone()),
+        }
+    }
+}
+
+fn describe(error: &eval::Error) -> (String, Vec<String>) {
+    match error {
+        eval::Error::AssertEq { left, right, .. } => (
+            "assert_eq failed".to_string(),
+            vec![format!("left: {:?}", left), format!("right: {:?}", right)],
+        ),
+        eval::Error::Undefined(ident, similar_ident) => (
+            format!("undefined identifier: {}", ident),
+            similar_ident
+                .iter()
+                .map(|similar_ident| {
+                    format!("note: a similar identifier exists: {}", similar_ident)
+                })
+                .collect(),
+        ),
+        other => (other.to_string(), Vec::new()),
+    }
+}
+
+// start and end of the line containing `pos`, excluding the line break
+fn line_bounds(code: &[u8], pos: usize) -> (usize, usize) {
+    let start = code[..pos]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+
+    let end = code[pos..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(code.len(), |i| pos + i);
+
+    (start, end)
+}
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    const BOLD: &'static str = "\x1b[1m";
+    const ERROR: &'static str = "\x1b[1;31m";
+    const WARNING: &'static str = "\x1b[1;33m";
+    const GUTTER: &'static str = "\x1b[1;34m";
+
+    fn paint<'a>(&self, escape: &'static str, text: &'a str) -> Painted<'a> {
+        Painted {
+            escape: if self.color { Some(escape) } else { None },
+            text,
+        }
+    }
+}
+
+struct Painted<'a> {
+    escape: Option<&'static str>,
+    text: &'a str,
+}
+
+impl fmt::Display for Painted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.escape {
+            Some(escape) => write!(f, "{}{}\x1b[0m", escape, self.text),
+            None => f.write_str(self.text),
+        }
+    }
+}
+
+#[cfg(test)]
+fn render(code: &str) -> String {
+    let mut env = crate::Env::new();
+
+    let error = crate::entry(&mut env, code.as_bytes()).unwrap_err();
+
+    error
+        .diagnostic(code.as_bytes())
+        .file("test.oris")
+        .to_string()
+}
+
+#[test]
+fn undefined() {
+    assert_eq!(
+        render("let foo = 1;\nfoo + oo"),
+        "\
+error: undefined identifier: oo
+ --> test.oris:2:7
+  |
+2 | foo + oo
+  |       ^^
+  |
+  = note: a similar identifier exists: foo
+"
+    );
+}
+
+#[test]
+fn arg_count() {
+    assert_eq!(
+        render("let f = fn(x) {\n    x\n};\n\n\nf(1, 2)"),
+        "\
+error: accept arg x 1, but got 2
+ --> test.oris:6:1
+  |
+1 | let f = fn(x) {
+  |         ------- closure defined here
+...
+6 | f(1, 2)
+  | ^^^^^^^
+"
+    );
+}
+
+#[test]
+fn assert_eq() {
+    assert_eq!(
+        render("assert_eq(1, 2)"),
+        "\
+error: assert_eq failed
+ --> test.oris:1:1
+  |
+1 | assert_eq(1, 2)
+  | ^^^^^^^^^^^^^^^
+  |
+  = left: 1
+  = right: 2
+"
+    );
+}
+
+#[test]
+fn incomplete() {
+    assert_eq!(
+        render("let x ="),
+        "\
+error: miss expression
+ --> test.oris:1:8
+  |
+1 | let x =
+  |        ^
+"
+    );
+}
+
+#[test]
+fn color() {
+    let code = b"1 + true";
+
+    let mut env = crate::Env::new();
+    let error = crate::entry(&mut env, code).unwrap_err();
+
+    let plain = error.diagnostic(code).to_string();
+    assert!(!plain.contains('\x1b'));
+
+    let colored = error.diagnostic(code).color(true).to_string();
+    assert!(colored.starts_with("\x1b[1;31merror\x1b[0m"));
+    assert!(colored.contains("\x1b[1;31m^^^^^^^^\x1b[0m"));
+}
+
+#[test]
+fn entry_at() {
+    let mut env = crate::Env::new();
+
+    let mut history = String::from("let f = fn(x) { x }\n");
+    crate::entry_at(&mut env, history.as_bytes(), 0).unwrap();
+
+    let start = history.len();
+    history.push_str("f()\n");
+    let error = crate::entry_at(&mut env, history.as_bytes(), start).unwrap_err();
+
+    assert_eq!(
+        error.diagnostic(history.as_bytes()).to_string(),
+        "\
+error: accept arg x 1, but got 0
+ --> 2:1
+  |
+1 | let f = fn(x) { x }
+  |         ----------- closure defined here
+2 | f()
+  | ^^^
+"
+    );
+}
+
+#[test]
+fn json() {
+    let code = b"let s = \"a\tb\";\nassert_eq(s, 1)";
+
+    let mut env = crate::Env::new();
+    let error = crate::entry(&mut env, code).unwrap_err();
+
+    assert_eq!(
+        error
+            .diagnostic(code)
+            .file("dir\\test.oris")
+            .json(true)
+            .to_string(),
+        concat!(
+            r#"{"file":"dir\\test.oris","line":2,"column":1,"span":{"start":15,"end":30},"#,
+            r#""kind":"eval","name":"assert_eq","message":"assert_eq failed","#,
+            r#""notes":["left: \"a\\tb\"","right: 1"]}"#,
+            "\n"
+        )
+    );
+
+    let error = crate::entry(&mut env, b"\"abc").unwrap_err();
+    assert_eq!(
+        error.diagnostic(b"\"abc").json(true).to_string(),
+        concat!(
+            r#"{"file":null,"line":1,"column":1,"span":{"start":0,"end":4},"#,
+            r#""kind":"lex","name":"quote","message":"missing right quote for string literal","notes":[]}"#,
+            "\n"
+        )
+    );
+
+    // tokens are rendered as written
+    let error = crate::entry(&mut env, b"let a = ;").unwrap_err();
+    assert_eq!(
+        error.diagnostic(b"let a = ;").json(true).to_string(),
+        concat!(
+            r#"{"file":null,"line":1,"column":9,"span":{"start":8,"end":9},"#,
+            r#""kind":"parse","name":"mismatch","message":"expect expression, found `;`","notes":[]}"#,
+            "\n"
+        )
+    );
+}
@@ -5,12 +5,126 @@ use crate::eval;
 pub type Result = std::result::Result<Value, Error>;
 
 pub fn entry(env: &mut Env, code: &[u8]) -> Result {
-    let value = eval::entry(&mut env.env, code)?;
-    Ok(Value { value })
+    entry_at(env, code, 0)
+}
+
+/// like [`entry`], but only `code[start..]` is evaluated
+///
+/// positions in errors are relative to the whole `code`, so a REPL can keep
+/// all the lines entered so far in one buffer and render diagnostics for
+/// closures defined in previous lines
+///
+/// # Panics
+///
+/// panics if `start` is out of bounds
+pub fn entry_at(env: &mut Env, code: &[u8], start: usize) -> Result {
+    assert!(start <= code.len(), "start is out of bounds");
+
+    env.env.begin();
+    let value = if env.type_check {
+        eval::entry_at_checked(&mut env.env, code, start)
+    } else if env.parse_first {
+        eval::entry_at_parsed(&mut env.env, code, start)
+    } else {
+        eval::entry_at(&mut env.env, code, start)
+    };
+    env.env.end();
+
+    Ok(Value { value: value? })
+}
+
+/// report all the syntax errors in `code` without evaluating it
+///
+/// unlike [`entry`], which stops at the first error, the parser skips to the
+/// next statement after an error, so the result is empty only if `code` is
+/// syntactically valid
+pub fn check(code: &[u8]) -> Vec<Error> {
+    crate::syntax::parse_with_recovery(code).1
+}
+
+/// like [`check`], and if there's no syntax error, report the type errors
+/// found by the checker of [`EnvBuilder::with_type_check`] in an environment
+/// with the builtins
+///
+/// the checker infers the types of `let` bindings and closures, and types the
+/// builtins by their signatures, an expression whose type is unknown
+/// statically, e.g. an element of a seq mixing ints and strs, is accepted
+/// anywhere
+///
+/// ```rust
+/// let errors = oris::check_types(b"let double = fn(x) { x * 2 };\ndouble(\"a\")");
+///
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].to_string(), "mismatched types: expected int, found str");
+/// ```
+pub fn check_types(code: &[u8]) -> Vec<Error> {
+    let (nodes, errors) = crate::syntax::parse_with_recovery(code);
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    crate::typeck::check(&nodes, &Env::new().env)
+        .into_iter()
+        .map(|error| Error::from(eval::Error::Type(error)))
+        .collect()
+}
+
+/// names of the builtins added by [`EnvBuilder::with_builtin`], each with
+/// its signatures, e.g. `("head", ["fn([T]) -> T"])`
+pub fn builtins() -> impl Iterator<Item = (&'static str, &'static [&'static str])> {
+    eval::value::builtin::ALL
+        .iter()
+        .map(|entry| (entry.name, entry.signatures))
+}
+
+/// a set of groups of builtins, for [`EnvBuilder::with_capabilities`],
+/// combined with `|`
+///
+/// - `CORE`: `len`, `head`, `tail`, `append`, `type` and `assert_eq`
+/// - `COLLECTIONS`: the other seq builtins, e.g. `map` or `sort`, and the map
+///   builtins, e.g. `keys` or `insert`
+/// - `IO`: `print`, see [`EnvBuilder::with_output`]
+///
+/// a builtin reading or changing anything else outside the env, e.g. the
+/// time, will get a capability of its own, so that a host enabling only the
+/// capabilities it trusts doesn't get it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cap(u8);
+
+impl Cap {
+    pub const NONE: Self = Self(0);
+    pub const CORE: Self = Self(1 << 0);
+    pub const COLLECTIONS: Self = Self(1 << 1);
+    pub const IO: Self = Self(1 << 2);
+    pub const ALL: Self = Self((1 << 3) - 1);
+
+    /// the pure builtins, which neither read nor change anything outside the
+    /// env
+    pub const PURE: Self = Self(Self::CORE.0 | Self::COLLECTIONS.0);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for Cap {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Cap {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
 }
 
 pub struct Env {
-    env: eval::Env,
+    pub(crate) env: eval::Env,
+    parse_first: bool,
+    pub(crate) type_check: bool,
 }
 
 impl Env {
@@ -21,16 +135,97 @@ impl Env {
     pub fn builder() -> EnvBuilder {
         EnvBuilder::new()
     }
+
+    /// a handle to abort the code evaluated in this env from another thread
+    ///
+    /// ```rust
+    /// let mut env = oris::Env::new();
+    ///
+    /// let handle = env.interrupt_handle();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    ///     handle.interrupt();
+    /// });
+    ///
+    /// let error = oris::entry(&mut env, b"let f = fn() { f() }; f()").unwrap_err();
+    /// assert_eq!(error.name(), "interrupted");
+    ///
+    /// // and the env is still usable
+    /// assert_eq!(oris::entry(&mut env, b"1 + 1").unwrap().as_int(), Some(2));
+    /// ```
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            flag: std::sync::Arc::clone(self.env.interrupt()),
+        }
+    }
+}
+
+/// see [`Env::interrupt_handle`]
+#[derive(Clone, Debug)]
+pub struct InterruptHandle {
+    flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
 }
 
+impl InterruptHandle {
+    /// make the evaluation running in the env fail with an error, e.g. on
+    /// Ctrl-C, or the next one if none is running
+    ///
+    /// the request is dropped once an evaluation ends, so it never aborts a
+    /// later one
+    pub fn interrupt(&self) {
+        self.flag.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// the flag set by [`interrupt`](Self::interrupt), e.g. to be set by a
+    /// signal handler instead
+    pub fn flag(&self) -> &std::sync::Arc<std::sync::atomic::AtomicBool> {
+        &self.flag
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// build an [`Env`] with values of the host and the builtins enabled
+///
+/// a value added by the host under the name of a builtin, e.g. with
+/// [`with_int`](Self::with_int), takes its place, whichever is added first
+///
+/// ```rust
+/// let mut env = oris::Env::builder()
+///     .with_int("len", 3)
+///     .with_builtin()
+///     .build();
+///
+/// assert_eq!(oris::entry(&mut env, b"len").unwrap().as_int(), Some(3));
+/// ```
 pub struct EnvBuilder {
     global: eval::env::Storage,
+    capabilities: Cap,
+    denied: Vec<Box<str>>,
+    parse_first: bool,
+    type_check: bool,
+    memory_limit: Option<usize>,
+    timeout: Option<std::time::Duration>,
+    max_depth: Option<usize>,
+    output: Option<Box<dyn std::io::Write>>,
 }
 
 impl EnvBuilder {
     fn new() -> Self {
         Self {
             global: eval::env::Storage::default(),
+            capabilities: Cap::NONE,
+            denied: Vec::new(),
+            parse_first: false,
+            type_check: false,
+            memory_limit: None,
+            timeout: None,
+            max_depth: None,
+            output: None,
         }
     }
 
@@ -47,29 +242,158 @@ impl EnvBuilder {
     }
 
     fn with_value(mut self, name: &str, value: eval::Value) -> Self {
-        let pos = None;
-        self.global.insert(std::rc::Rc::from(name), (pos, value));
+        self.global.insert(std::rc::Rc::from(name), value);
         self
     }
 
-    pub fn with_builtin(mut self) -> Self {
-        for (k, v) in eval::value::builtin::all_() {
-            self.global
-                .insert(std::rc::Rc::from(k), (None, eval::Value::Builtin(v)));
-        }
+    /// add all the builtins, see [`with_capabilities`](Self::with_capabilities)
+    pub fn with_builtin(self) -> Self {
+        self.with_capabilities(Cap::ALL)
+    }
+
+    /// add the builtins of `capabilities`, e.g. only the pure ones for
+    /// untrusted code
+    ///
+    /// ```rust
+    /// use oris::Cap;
+    ///
+    /// let mut env = oris::Env::builder()
+    ///     .with_capabilities(Cap::CORE | Cap::COLLECTIONS)
+    ///     .build();
+    ///
+    /// assert_eq!(oris::entry(&mut env, b"sum(range(4))").unwrap().as_int(), Some(6));
+    /// assert!(oris::entry(&mut env, b"print(1)").is_err());
+    /// ```
+    pub fn with_capabilities(mut self, capabilities: Cap) -> Self {
+        self.capabilities |= capabilities;
+        self
+    }
+
+    /// leave out the builtin `name` even if its capability is enabled
+    pub fn deny(mut self, name: &str) -> Self {
+        self.denied.push(name.into());
+        self
+    }
+
+    /// parse the whole code passed to [`entry`] before evaluating any of it,
+    /// so that a syntax error is reported before any side effect, e.g. a
+    /// `print()` call, happens
+    ///
+    /// by default each statement is evaluated as soon as it is parsed, which
+    /// suits a REPL reading a line at a time
+    pub fn with_parse_first(mut self) -> Self {
+        self.parse_first = true;
+        self
+    }
+
+    /// check the types of the whole code passed to [`entry`] or
+    /// [`Program::run`](crate::Program::run) before evaluating any of it, the
+    /// first type error is returned if any, see [`check_types`]
+    ///
+    /// the globals defined by the host and previous evaluations are typed by
+    /// their current values
+    pub fn with_type_check(mut self) -> Self {
+        self.type_check = true;
+        self
+    }
+
+    /// fail with an error once the values created by a call to [`entry`] or
+    /// [`Program::run`](crate::Program::run) take more than about `bytes`
+    /// bytes, so that untrusted code can't exhaust the memory of the host
+    ///
+    /// the strings and collections built by the operators, the literals and
+    /// the builtins, e.g. `range()`, are counted before they are allocated,
+    /// without freeing the ones dropped since
+    ///
+    /// ```rust
+    /// let mut env = oris::Env::builder().with_memory_limit(1 << 20).build();
+    ///
+    /// let error = oris::entry(&mut env, b"let f = fn(s) { f(s + s) }; f(\"a\")").unwrap_err();
+    /// assert_eq!(error.name(), "memory_limit");
+    /// ```
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// fail with an error once a call to [`entry`] or
+    /// [`Program::run`](crate::Program::run) takes longer than `timeout`
+    ///
+    /// ```rust
+    /// let timeout = std::time::Duration::from_millis(10);
+    /// let mut env = oris::Env::builder().with_timeout(timeout).build();
+    ///
+    /// let error = oris::entry(&mut env, b"let f = fn() { f() }; f()").unwrap_err();
+    /// assert_eq!(error.name(), "timeout");
+    /// ```
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// fail with an error once the calls to closures nest deeper than
+    /// `depth`, instead of overflowing the stack of the host, which aborts the
+    /// process
+    ///
+    /// the calls in tail position don't nest
+    ///
+    /// ```rust
+    /// let mut env = oris::Env::builder().with_max_depth(100).build();
+    ///
+    /// let error = oris::entry(&mut env, b"let f = fn(n) { 1 + f(n) }; f(0)").unwrap_err();
+    /// assert_eq!(error.name(), "depth");
+    /// ```
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
 
+    /// write the output of `print()` to `output` instead of stdout, errors
+    /// writing to it are ignored
+    pub fn with_output<W>(mut self, output: W) -> Self
+    where
+        W: std::io::Write + 'static,
+    {
+        self.output = Some(Box::new(output));
         self
     }
 
     pub fn build(self) -> Env {
+        let mut global = self.global;
+        for entry in eval::value::builtin::ALL.iter() {
+            if self.capabilities.contains(entry.cap)
+                && !self.denied.iter().any(|denied| **denied == *entry.name)
+            {
+                global
+                    .entry(std::rc::Rc::from(entry.name))
+                    .or_insert(eval::Value::Builtin(entry.f));
+            }
+        }
+
+        let mut env = eval::Env::new(global);
+        if let Some(output) = self.output {
+            env.set_output(output);
+        }
+        if let Some(limit) = self.memory_limit {
+            env.set_memory_limit(limit);
+        }
+        if let Some(timeout) = self.timeout {
+            env.set_timeout(timeout);
+        }
+        if let Some(depth) = self.max_depth {
+            env.set_max_depth(depth);
+        }
+
         Env {
-            env: eval::Env::new(self.global),
+            env,
+            parse_first: self.parse_first,
+            type_check: self.type_check,
         }
     }
 }
 
 pub struct Value {
-    value: eval::Value,
+    pub(crate) value: eval::Value,
 }
 
 impl Value {
@@ -109,27 +433,117 @@ pub struct Error {
     inner: eval::Error,
 }
 
+/// stage of the interpreter where an [`Error`] is produced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// invalid token, e.g. an unterminated string literal
+    Lex,
+
+    /// invalid syntax, e.g. a missing `)`
+    Parse,
+
+    /// runtime error, e.g. an undefined identifier
+    Eval,
+
+    /// type error found before evaluation, see [`check_types`]
+    Type,
+}
+
+impl ErrorKind {
+    pub(crate) fn of(error: &eval::Error) -> Self {
+        match error {
+            eval::Error::Parse(crate::parse::Error::Lex(_)) => Self::Lex,
+            eval::Error::Parse(_) => Self::Parse,
+            eval::Error::Type(_) => Self::Type,
+            _ => Self::Eval,
+        }
+    }
+
+    /// `"lex"`, `"parse"`, `"eval"` or `"type"`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lex => "lex",
+            Self::Parse => "parse",
+            Self::Eval => "eval",
+            Self::Type => "type",
+        }
+    }
+}
+
 impl Error {
-    /// both line and column number are 0 based
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::of(&self.inner)
+    }
+
+    /// snake_case name of the specific error within its [`kind`](Self::kind),
+    /// e.g. `"quote"` for an unterminated string literal or `"arg_count"`
+    /// for a call with a wrong number of arguments
+    pub fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    /// both line and column number are 0 based, the column is counted in
+    /// chars
     ///
     /// # Panics
     ///
     /// panics if `code` is not the original source code where this error is
     /// produced
     pub fn line_column(&self, code: &[u8]) -> (usize, usize) {
-        pos_to_line_column(self.inner.pos(), code)
+        pos_to_line_column(self.inner.span().start, code)
+    }
+
+    /// like [`line_column`](Self::line_column), but the column is counted in
+    /// UTF-16 code units, as the Language Server Protocol does by default
+    ///
+    /// # Panics
+    ///
+    /// panics if `code` is not the original source code where this error is
+    /// produced
+    pub fn line_column_utf16(&self, code: &[u8]) -> (usize, usize) {
+        let (line, column_text) = split_line(self.inner.span().start, code);
+        (line, column_text.encode_utf16().count())
+    }
+
+    /// byte range of the source code where this error is produced, e.g. the
+    /// whole `f(1, 2)` for a call with a wrong number of arguments
+    ///
+    /// the range is empty if the error is at the end of the source code,
+    /// e.g. a missing expression
+    pub fn span(&self) -> std::ops::Range<usize> {
+        self.inner.span().into()
+    }
+
+    /// render this error with the offending line in `code`, see
+    /// [`Diagnostic`](crate::Diagnostic)
+    ///
+    /// `code` should be the original source code where this error is
+    /// produced
+    pub fn diagnostic<'a>(&'a self, code: &'a [u8]) -> crate::Diagnostic<'a> {
+        crate::Diagnostic::new(&self.inner, code)
     }
 }
 
-fn pos_to_line_column(pos: usize, code: &[u8]) -> (usize, usize) {
-    match code[..pos].iter().rposition(|b| *b == b'\n') {
+pub(crate) fn pos_to_line_column(pos: usize, code: &[u8]) -> (usize, usize) {
+    let (line, column_text) = split_line(pos, code);
+    (line, column_text.chars().count())
+}
+
+// 0 based line number of `pos`, and the text between the start of that line
+// and `pos`
+//
+// an invalid UTF-8 sequence counts as one char
+fn split_line(pos: usize, code: &[u8]) -> (usize, std::borrow::Cow<'_, str>) {
+    let (line, column_text) = match code[..pos].iter().rposition(|b| *b == b'\n') {
         Some(i) => {
             let (lines, column_text) = code[..pos].split_at(i + 1);
-            let line_number = lines.iter().filter(|b| **b == b'\n').count();
-            (line_number, column_text.len()) // TODO: support UTF-8?
+            (lines.iter().filter(|b| **b == b'\n').count(), column_text)
         }
-        None => (0, code[..pos].len()), // TODO: support UTF-8?
-    }
+        None => (0, &code[..pos]),
+    };
+
+    (line, String::from_utf8_lossy(column_text))
 }
 
 impl From<eval::Error> for Error {
@@ -1,7 +1,8 @@
+pub(crate) mod binary;
 pub(crate) mod env;
+pub(crate) mod resolve;
 pub(crate) mod value;
 
-mod binary;
 mod error;
 
 #[cfg(test)]
@@ -9,26 +10,83 @@ mod tests;
 
 use std::rc::Rc;
 
-use crate::parse::ast;
+use crate::{parse::ast, span::Span};
 
 pub(crate) type Env = env::Env;
 pub(crate) type Value = value::Value;
 
 pub(crate) type Error = error::Error;
+pub(crate) type Arity = error::Arity;
 type Result<T> = std::result::Result<T, self::error::Error>;
 
+#[cfg(test)]
 pub(crate) fn entry(env: &mut Env, code: &[u8]) -> Result<Value> {
-    let lexer = crate::lex::Lexer::new(code);
+    entry_at(env, code, 0)
+}
+
+// evaluate `code[start..]`, positions are still relative to `code`
+pub(crate) fn entry_at(env: &mut Env, code: &[u8], start: usize) -> Result<Value> {
+    let lexer = crate::lex::Lexer::with_cursor(code, start);
     let parser = crate::parse::Parser::new(lexer);
 
     let mut output = Value::Unit;
 
     for node in parser {
-        let node = node?;
+        let mut node = node?;
+        let globals = resolve::resolve(std::slice::from_mut(&mut node));
+        env.link(&globals);
 
+        env.poll(node.span())?;
         match eval_node(env, &node)? {
             Eval::Continue(x) => output = x,
             Eval::Return(x) => return Ok(x),
+            Eval::TailCall(call) => return call.call(env),
+        }
+    }
+    Ok(output)
+}
+
+// like `entry_at()`, but parse the whole `code[start..]` first, so nothing is
+// evaluated if there's a syntax error
+pub(crate) fn entry_at_parsed(env: &mut Env, code: &[u8], start: usize) -> Result<Value> {
+    let lexer = crate::lex::Lexer::with_cursor(code, start);
+    let mut nodes = crate::parse::Parser::new(lexer).collect::<crate::parse::Result<Vec<_>>>()?;
+    let globals = resolve::resolve(&mut nodes);
+
+    entry_nodes(env, &nodes, &globals)
+}
+
+// like `entry_at_parsed()`, but also check the types of the whole code, the
+// first type error is returned if any
+pub(crate) fn entry_at_checked(env: &mut Env, code: &[u8], start: usize) -> Result<Value> {
+    let lexer = crate::lex::Lexer::with_cursor(code, start);
+    let mut nodes = crate::parse::Parser::new(lexer).collect::<crate::parse::Result<Vec<_>>>()?;
+    let globals = resolve::resolve(&mut nodes);
+
+    if let Some(error) = crate::typeck::check(&nodes, env).into_iter().next() {
+        return Err(Error::Type(error));
+    }
+
+    entry_nodes(env, &nodes, &globals)
+}
+
+// evaluate nodes parsed and resolved in advance, with the `globals` returned by
+// `resolve()`
+pub(crate) fn entry_nodes(
+    env: &mut Env,
+    nodes: &[ast::Node],
+    globals: &[Rc<str>],
+) -> Result<Value> {
+    env.link(globals);
+
+    let mut output = Value::Unit;
+
+    for node in nodes {
+        env.poll(node.span())?;
+        match eval_node(env, node)? {
+            Eval::Continue(x) => output = x,
+            Eval::Return(x) => return Ok(x),
+            Eval::TailCall(call) => return call.call(env),
         }
     }
     Ok(output)
@@ -37,6 +95,21 @@ pub(crate) fn entry(env: &mut Env, code: &[u8]) -> Result<Value> {
 enum Eval<T = Value> {
     Continue(T),
     Return(Value),
+    TailCall(TailCall),
+}
+
+// a call to a closure in tail position, which is returned to be made by the
+// caller instead, so the stack doesn't grow with the calls
+struct TailCall {
+    span: Span,
+    closure: Rc<value::Closure>,
+    args: Vec<Value>,
+}
+
+impl TailCall {
+    fn call(self, env: &mut Env) -> Result<Value> {
+        call_value(env, self.span, Value::Closure(self.closure), self.args)
+    }
 }
 
 fn eval_node(env: &mut Env, node: &ast::Node) -> Result<Eval> {
@@ -45,6 +118,7 @@ fn eval_node(env: &mut Env, node: &ast::Node) -> Result<Eval> {
         ast::Node::Stmt(stmt) => eval_stmt(env, stmt).map(|r| match r {
             Eval::Continue(()) => Eval::Continue(Value::Unit),
             Eval::Return(v) => Eval::Return(v),
+            Eval::TailCall(call) => Eval::TailCall(call),
         }),
     }
 }
@@ -56,26 +130,27 @@ fn eval_stmt(env: &mut Env, stmt: &ast::Stmt) -> Result<Eval<()>> {
 
             let value = match value {
                 Value::Closure(mut closure) => {
-                    if let Some(i) = closure
-                        .undefined
-                        .iter()
-                        .position(|ident_| ident_.sym() == let_.ident.sym())
-                    {
-                        let c = Rc::get_mut(&mut closure).unwrap();
-                        c.recursive = Some(c.undefined.remove(i));
+                    let mut captures = closure.f.layout.captures.iter().zip(&*closure.captured);
+                    if let Some(i) = captures.position(|(ident, value)| {
+                        value.is_none() && ident.sym() == let_.ident.sym()
+                    }) {
+                        Rc::get_mut(&mut closure).unwrap().recursive = Some(i);
                     }
                     Value::Closure(closure)
                 }
                 other => other,
             };
 
-            env.set(let_.ident.clone(), value);
-            Ok(()).map(Eval::Continue)
+            check_type(let_.value.span(), &value, let_.ty.as_ref(), Annotated::Let)?;
+
+            env.set(&let_.ident, value);
+            Ok(Eval::Continue(()))
         }
         ast::Stmt::Return(return_) => match return_.value {
             None => Ok(Eval::Continue(())),
-            Some(ref expr) => eval_expr(env, expr).map(|r| match r {
+            Some(ref expr) => eval_tail(env, expr).map(|r| match r {
                 Eval::Continue(v) | Eval::Return(v) => Eval::Return(v),
+                Eval::TailCall(call) => Eval::TailCall(call),
             }),
         },
     }
@@ -83,23 +158,24 @@ fn eval_stmt(env: &mut Env, stmt: &ast::Stmt) -> Result<Eval<()>> {
 
 fn eval_expr(env: &mut Env, expr: &ast::Expr) -> Result<Eval> {
     match expr {
-        ast::Expr::Int(expr) => Ok(Value::Int(expr.value)).map(Eval::Continue),
-        ast::Expr::Bool(expr) => Ok(Value::Bool(expr.value)).map(Eval::Continue),
-        ast::Expr::Str(expr) => Ok(Value::Str(expr.value_rc_str().clone())).map(Eval::Continue),
+        ast::Expr::Int(expr) => Ok(Eval::Continue(Value::Int(expr.value))),
+        ast::Expr::Bool(expr) => Ok(Eval::Continue(Value::Bool(expr.value))),
+        ast::Expr::Str(expr) => Ok(Eval::Continue(Value::Str(expr.value_rc_str().clone()))),
         ast::Expr::Ident(ident) => env
-            .get(ident.sym())
+            .get(ident)
             .cloned()
             .ok_or_else(|| {
                 Error::Undefined(ident.clone(), env.find_similar_symbol(ident.sym()).cloned())
             })
             .map(Eval::Continue),
         ast::Expr::Seq(seq) => {
+            env.memory().allocate_values(seq.span, seq.elements.len())?;
             let mut elements = Vec::with_capacity(seq.elements.len());
             for expr in seq.elements.iter() {
                 let elem = propagate!(eval_expr(env, expr));
                 elements.push(elem);
             }
-            Ok(Value::Seq(elements.into())).map(Eval::Continue)
+            Ok(Eval::Continue(Value::Seq(elements.into())))
         }
         ast::Expr::Unary(expr) => {
             let value = propagate!(eval_expr(env, &expr.value));
@@ -108,7 +184,7 @@ fn eval_expr(env: &mut Env, expr: &ast::Expr) -> Result<Eval> {
                 ast::UnaryOp::Neg => match value {
                     Value::Int(x) => Ok(Value::Int(-x)),
                     other => Err(Error::Unary {
-                        pos: expr.pos,
+                        span: expr.span,
                         op: expr.op,
                         operand: other,
                     }),
@@ -116,7 +192,7 @@ fn eval_expr(env: &mut Env, expr: &ast::Expr) -> Result<Eval> {
                 ast::UnaryOp::Not => match value {
                     Value::Bool(x) => Ok(Value::Bool(!x)),
                     other => Err(Error::Unary {
-                        pos: expr.pos,
+                        span: expr.span,
                         op: expr.op,
                         operand: other,
                     }),
@@ -127,14 +203,16 @@ fn eval_expr(env: &mut Env, expr: &ast::Expr) -> Result<Eval> {
         ast::Expr::Binary(expr) => {
             let left = propagate!(eval_expr(env, &expr.left));
             let right = propagate!(eval_expr(env, &expr.right));
-            binary::eval(expr.pos, left, expr.op, right).map(Eval::Continue)
+            binary::eval(env.memory(), expr.span, left, expr.op, right).map(Eval::Continue)
         }
-        ast::Expr::Closure(closure) => Ok(value::Closure::new(Rc::clone(closure), env))
-            .map(Rc::new)
-            .map(Value::Closure)
-            .map(Eval::Continue),
+        ast::Expr::Closure(closure) => Ok(Eval::Continue(Value::Closure(Rc::new(
+            value::Closure::new(Rc::clone(closure), env),
+        )))),
         ast::Expr::Map(map_expr) => {
-            let mut map = std::collections::HashMap::with_capacity(map_expr.entries.len());
+            // a key and a value for each entry
+            env.memory()
+                .allocate_values(map_expr.span, map_expr.entries.len() * 2)?;
+            let mut map = value::Map::default();
 
             for (k_expr, v) in map_expr.entries.iter() {
                 let k = propagate!(eval_expr(env, k_expr));
@@ -145,23 +223,65 @@ fn eval_expr(env: &mut Env, expr: &ast::Expr) -> Result<Eval> {
                     }
                     None => {
                         return Err(Error::ArgType {
-                            pos: k_expr.pos(),
+                            span: k_expr.span(),
                             supplied: k,
-                            expected: "int | bool | str as map key",
+                            expected: "int | bool | str | tuple as map key".into(),
+                        });
+                    }
+                }
+            }
+
+            Ok(Eval::Continue(Value::Map(Rc::from(map))))
+        }
+        ast::Expr::Set(set_expr) => {
+            env.memory()
+                .allocate_values(set_expr.span, set_expr.elements.len())?;
+            let mut set = value::Set::default();
+
+            for expr in set_expr.elements.iter() {
+                let element = propagate!(eval_expr(env, expr));
+                match value::to_key(&element) {
+                    Some(key) => set.insert(key),
+                    None => {
+                        return Err(Error::ArgType {
+                            span: expr.span(),
+                            supplied: element,
+                            expected: "int | bool | str | tuple as set element".into(),
                         });
                     }
                 }
             }
 
-            Ok(map).map(Rc::from).map(Value::Map).map(Eval::Continue)
+            Ok(Eval::Continue(Value::Set(Rc::from(set))))
         }
-        ast::Expr::Call(call) => eval_call(env, call),
+        ast::Expr::Tuple(tuple) => {
+            env.memory()
+                .allocate_values(tuple.span, tuple.elements.len())?;
+            let mut elements = Vec::with_capacity(tuple.elements.len());
+            for expr in tuple.elements.iter() {
+                let elem = propagate!(eval_expr(env, expr));
+                elements.push(elem);
+            }
+            Ok(Eval::Continue(Value::Tuple(elements.into())))
+        }
+        ast::Expr::Call(call) => eval_call(env, call, false),
         ast::Expr::Index(index) => eval_index(env, index),
-        ast::Expr::If(expr) => eval_if(env, expr),
+        ast::Expr::If(expr) => eval_if(env, expr, false),
     }
 }
 
-fn eval_call(env: &mut Env, call: &ast::Call) -> Result<Eval> {
+// evaluate `expr` whose value is returned by the closure being called, e.g.
+// the last expression of its body or of a branch of an `if` there
+fn eval_tail(env: &mut Env, expr: &ast::Expr) -> Result<Eval> {
+    match expr {
+        ast::Expr::Call(call) => eval_call(env, call, true),
+        ast::Expr::If(expr) => eval_if(env, expr, true),
+        expr => eval_expr(env, expr),
+    }
+}
+
+// with `tail`, a closure is not called but returned as `Eval::TailCall`
+fn eval_call(env: &mut Env, call: &ast::Call, tail: bool) -> Result<Eval> {
     fn eval_args(env: &mut Env, args: &[ast::Expr]) -> Result<Eval<Vec<Value>>> {
         let mut values = Vec::with_capacity(args.len());
 
@@ -170,68 +290,162 @@ fn eval_call(env: &mut Env, call: &ast::Call) -> Result<Eval> {
             values.push(value);
         }
 
-        Ok(values).map(Eval::Continue)
+        Ok(Eval::Continue(values))
     }
 
+    env.poll(call.span)?;
+
     let target = propagate!(eval_expr(env, &call.target));
     let args = propagate!(eval_args(env, &call.args));
 
     match target {
+        Value::Closure(closure) if tail => Ok(Eval::TailCall(TailCall {
+            span: call.span,
+            closure,
+            args,
+        })),
+        target => call_value(env, call.span, target, args).map(Eval::Continue),
+    }
+}
+
+/// call `target` with `args`, `span` is where the call happens
+pub(crate) fn call_value(
+    env: &mut Env,
+    span: Span,
+    target: Value,
+    args: Vec<Value>,
+) -> Result<Value> {
+    match target {
+        // the calls in tail position are made one after another in the same
+        // frame
         Value::Closure(closure) => {
-            if args.len() != closure.f.parameters.len() {
-                Err(Error::ArgCount {
-                    pos: call.pos,
-                    supplied: args.len(),
-                    expected: closure.f.parameters.len(),
-                })
-            } else {
-                env.enclosed(|env| {
-                    if let Some(ref name) = closure.recursive {
-                        env.set(name.clone(), Value::Closure(Rc::clone(&closure)));
+            env.check_depth(span)?;
+            env.enclosed(&Rc::clone(&closure), |env| {
+                let mut call = TailCall {
+                    span,
+                    closure,
+                    args,
+                };
+                // closures with a return type, each one with its innermost call,
+                // which checks the result first
+                let mut typed = Vec::<(Span, Rc<value::Closure>)>::new();
+
+                let result = loop {
+                    let TailCall {
+                        span,
+                        closure,
+                        args,
+                    } = call;
+
+                    if args.len() != closure.f.parameters.len() {
+                        return Err(Error::ArgCount {
+                            span,
+                            supplied: args.len(),
+                            expected: Arity::exactly(closure.f.parameters.len()),
+                            defined: Some(closure.f.span),
+                        });
                     }
 
-                    for (ident, value) in closure.captured.iter() {
-                        env.set(ident.clone(), value.clone());
+                    for (arg, ty) in std::iter::zip(&args, closure.f.parameter_types.iter()) {
+                        check_type(span, arg, ty.as_ref(), Annotated::Arg)?;
                     }
 
+                    if closure.f.ret.is_some() {
+                        typed.retain(|(_, typed)| !Rc::ptr_eq(typed, &closure));
+                        typed.push((span, Rc::clone(&closure)));
+                    }
+
+                    env.reset(&closure);
+
                     for (ident, arg) in std::iter::zip(closure.f.parameters.iter(), args) {
-                        env.set(ident.clone(), arg);
+                        env.set(ident, arg);
                     }
 
-                    match eval_block(env, &closure.f.body)? {
-                        Eval::Continue(v) | Eval::Return(v) => Ok(v),
+                    match eval_block(env, &closure.f.body, true)? {
+                        Eval::Continue(v) | Eval::Return(v) => break v,
+                        Eval::TailCall(next) => call = next,
                     }
-                })
-            }
+                };
+
+                for (span, closure) in typed.iter().rev() {
+                    check_type(*span, &result, closure.f.ret.as_ref(), Annotated::Return)?;
+                }
+
+                Ok(result)
+            })
         }
-        Value::Builtin(f) => f(call.pos, args),
-        _ => Err(Error::Call {
-            pos: call.pos,
-            target,
-            args: args.into_boxed_slice(),
-        }),
+        Value::Builtin(f) => f(env, span, args),
+        _ => Err(Error::Call { span, target }),
     }
-    .map(Eval::Continue)
 }
 
-fn eval_block(env: &mut Env, block: &ast::Block) -> Result<Eval> {
+// fail unless `value` is of the annotated type `ty`
+fn check_type(
+    span: Span,
+    value: &Value,
+    ty: Option<&ast::Type>,
+    annotated: Annotated,
+) -> Result<()> {
+    let ty = match ty {
+        Some(ty) if !value::has_type(value, ty) => ty,
+        _ => return Ok(()),
+    };
+
+    let supplied = value.clone();
+    let expected = ty.to_string().into();
+    Err(match annotated {
+        Annotated::Arg => Error::ArgType {
+            span,
+            supplied,
+            expected,
+        },
+        Annotated::Let => Error::LetType {
+            span,
+            supplied,
+            expected,
+        },
+        Annotated::Return => Error::ReturnType {
+            span,
+            supplied,
+            expected,
+        },
+    })
+}
+
+// what a type annotation checked by `check_type()` is on
+#[derive(Clone, Copy)]
+enum Annotated {
+    Arg,
+    Let,
+    Return,
+}
+
+// with `tail`, the last expression is evaluated by `eval_tail()`
+fn eval_block(env: &mut Env, block: &ast::Block, tail: bool) -> Result<Eval> {
+    env.poll(block.span)?;
+
     let mut result = Value::Unit;
-    for node in block.nodes.iter() {
-        result = propagate!(eval_node(env, node));
+    for (i, node) in block.nodes.iter().enumerate() {
+        result = match node {
+            ast::Node::Expr(expr) if tail && i + 1 == block.nodes.len() => {
+                return eval_tail(env, expr);
+            }
+            node => propagate!(eval_node(env, node)),
+        };
     }
     Ok(Eval::Continue(result))
 }
 
-fn eval_if(env: &mut Env, expr: &ast::If) -> Result<Eval> {
+fn eval_if(env: &mut Env, expr: &ast::If, tail: bool) -> Result<Eval> {
     for (condition, consequence) in expr.conditioned.iter() {
         if let Value::Bool(true) = propagate!(eval_expr(env, condition)) {
-            return eval_block(env, consequence);
+            return eval_block(env, consequence, tail);
         }
     }
 
     match expr.alternative {
-        Some(ref expr) => eval_block(env, expr),
-        None => Ok(Value::Unit).map(Eval::Continue),
+        Some(ref expr) => eval_block(env, expr, tail),
+        None => Ok(Eval::Continue(Value::Unit)),
     }
 }
 
@@ -245,7 +459,7 @@ fn eval_index(env: &mut Env, index: &ast::Index) -> Result<Eval> {
                 Value::Int(i) => i,
                 other => {
                     return Err(Error::Index {
-                        pos: index.pos,
+                        span: index.span,
                         base: Value::Seq(seq),
                         subscript: other,
                     });
@@ -256,7 +470,7 @@ fn eval_index(env: &mut Env, index: &ast::Index) -> Result<Eval> {
                 Ok(iusize) => iusize,
                 Err(_) => {
                     return Err(Error::Index {
-                        pos: index.pos,
+                        span: index.span,
                         base: Value::Seq(seq),
                         subscript: Value::Int(i),
                     });
@@ -266,21 +480,30 @@ fn eval_index(env: &mut Env, index: &ast::Index) -> Result<Eval> {
             match seq.get(iusize) {
                 Some(value) => Ok(value.clone()),
                 _ => Err(Error::Index {
-                    pos: index.pos,
+                    span: index.span,
                     base: Value::Seq(seq),
                     subscript: Value::Int(i),
                 }),
             }
         }
+        Value::Tuple(tuple) => match subscript {
+            Value::Int(i) => usize::try_from(i).ok().and_then(|i| tuple.get(i).cloned()),
+            _ => None,
+        }
+        .ok_or_else(|| Error::Index {
+            span: index.span,
+            base: Value::Tuple(tuple),
+            subscript,
+        }),
         Value::Map(map) => value::to_key(&subscript)
             .and_then(|key| map.get(&key).cloned())
             .ok_or_else(|| Error::Index {
-                pos: index.pos,
+                span: index.span,
                 base: Value::Map(map),
                 subscript,
             }),
         other => Err(Error::Index {
-            pos: index.pos,
+            span: index.span,
             base: other,
             subscript,
         }),
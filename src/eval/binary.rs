@@ -1,24 +1,28 @@
 use std::rc::Rc;
 
 use crate::{
-    eval::{self, Value},
+    eval::{self, env::Memory, Value},
     parse::ast,
+    span::Span,
 };
 
-pub(super) fn eval(
-    pos: usize,
+pub(crate) fn eval(
+    memory: &mut Memory,
+    span: Span,
     left: Value,
     op: ast::BinaryOp,
     right: Value,
 ) -> eval::Result<Value> {
     match (left, right) {
         (Value::Int(left), Value::Int(right)) => Ok(int_(left, op, right)),
-        (Value::Bool(left), Value::Bool(right)) => bool_(pos, left, op, right),
-        (Value::Str(left), Value::Str(right)) => str_(pos, left, op, right),
-        (Value::Seq(left), Value::Seq(right)) => seq_(pos, left, op, right),
-        (Value::Map(left), Value::Map(right)) => map_(pos, left, op, right),
+        (Value::Bool(left), Value::Bool(right)) => bool_(span, left, op, right),
+        (Value::Str(left), Value::Str(right)) => str_(memory, span, left, op, right),
+        (Value::Seq(left), Value::Seq(right)) => seq_(memory, span, left, op, right),
+        (Value::Map(left), Value::Map(right)) => map_(memory, span, left, op, right),
+        (Value::Set(left), Value::Set(right)) => set_(memory, span, left, op, right),
+        (Value::Tuple(left), Value::Tuple(right)) => tuple_(span, left, op, right),
         (left, right) => Err(eval::Error::Binary {
-            pos,
+            span,
             left,
             op,
             right,
@@ -41,12 +45,12 @@ fn int_(left: i32, op: ast::BinaryOp, right: i32) -> Value {
     }
 }
 
-fn bool_(pos: usize, left: bool, op: ast::BinaryOp, right: bool) -> eval::Result<Value> {
+fn bool_(span: Span, left: bool, op: ast::BinaryOp, right: bool) -> eval::Result<Value> {
     match op {
         ast::BinaryOp::Eq => Ok(Value::Bool(left == right)),
         ast::BinaryOp::Ne => Ok(Value::Bool(left != right)),
         _ => Err(eval::Error::Binary {
-            pos,
+            span,
             left: Value::Bool(left),
             op,
             right: Value::Bool(right),
@@ -54,9 +58,16 @@ fn bool_(pos: usize, left: bool, op: ast::BinaryOp, right: bool) -> eval::Result
     }
 }
 
-fn str_(pos: usize, left: Rc<str>, op: ast::BinaryOp, right: Rc<str>) -> eval::Result<Value> {
+fn str_(
+    memory: &mut Memory,
+    span: Span,
+    left: Rc<str>,
+    op: ast::BinaryOp,
+    right: Rc<str>,
+) -> eval::Result<Value> {
     match op {
         ast::BinaryOp::Add => {
+            memory.allocate(span, left.len() + right.len())?;
             let mut new_str = String::with_capacity(left.len() + right.len());
             new_str += &left;
             new_str += &right;
@@ -65,7 +76,7 @@ fn str_(pos: usize, left: Rc<str>, op: ast::BinaryOp, right: Rc<str>) -> eval::R
         ast::BinaryOp::Eq => Ok(Value::Bool(left == right)),
         ast::BinaryOp::Ne => Ok(Value::Bool(left != right)),
         _ => Err(eval::Error::Binary {
-            pos,
+            span,
             left: Value::Str(left),
             op,
             right: Value::Str(right),
@@ -74,22 +85,22 @@ fn str_(pos: usize, left: Rc<str>, op: ast::BinaryOp, right: Rc<str>) -> eval::R
 }
 
 fn seq_(
-    pos: usize,
-    left: Rc<[Value]>,
+    memory: &mut Memory,
+    span: Span,
+    mut left: super::value::Seq,
     op: ast::BinaryOp,
-    right: Rc<[Value]>,
+    right: super::value::Seq,
 ) -> eval::Result<Value> {
     match op {
         ast::BinaryOp::Add => {
-            let mut new_seq = Vec::with_capacity(left.len() + right.len());
-            new_seq.extend(left.iter().cloned());
-            new_seq.extend(right.iter().cloned());
-            Ok(Value::Seq(new_seq.into()))
+            memory.allocate_values(span, right.len())?;
+            left.append(right);
+            Ok(Value::Seq(left))
         }
         ast::BinaryOp::Eq => Ok(Value::Bool(left == right)),
         ast::BinaryOp::Ne => Ok(Value::Bool(left != right)),
         _ => Err(eval::Error::Binary {
-            pos,
+            span,
             left: Value::Seq(left),
             op,
             right: Value::Seq(right),
@@ -98,19 +109,73 @@ fn seq_(
 }
 
 fn map_(
-    pos: usize,
-    left: Rc<std::collections::HashMap<super::value::Key, Value>>,
+    memory: &mut Memory,
+    span: Span,
+    left: Rc<super::value::Map>,
     op: ast::BinaryOp,
-    right: Rc<std::collections::HashMap<super::value::Key, Value>>,
+    right: Rc<super::value::Map>,
 ) -> eval::Result<Value> {
     match op {
+        ast::BinaryOp::Add => {
+            memory.allocate_values(span, right.len() * 2)?;
+            Ok(Value::Map(super::value::merge(left, right)))
+        }
         ast::BinaryOp::Eq => Ok(Value::Bool(left == right)),
         ast::BinaryOp::Ne => Ok(Value::Bool(left != right)),
         _ => Err(eval::Error::Binary {
-            pos,
+            span,
             left: Value::Map(left),
             op,
             right: Value::Map(right),
         }),
     }
 }
+
+fn set_(
+    memory: &mut Memory,
+    span: Span,
+    left: Rc<super::value::Set>,
+    op: ast::BinaryOp,
+    right: Rc<super::value::Set>,
+) -> eval::Result<Value> {
+    // at most the elements added to the left set, or kept from it
+    let len = match op {
+        ast::BinaryOp::Add => right.len(),
+        ast::BinaryOp::Mul => left.len().min(right.len()),
+        ast::BinaryOp::Sub => left.len(),
+        _ => 0,
+    };
+    memory.allocate_values(span, len)?;
+
+    match op {
+        ast::BinaryOp::Add => Ok(Value::Set(left.union(&right).into())),
+        ast::BinaryOp::Mul => Ok(Value::Set(left.intersection(&right).into())),
+        ast::BinaryOp::Sub => Ok(Value::Set(left.difference(&right).into())),
+        ast::BinaryOp::Eq => Ok(Value::Bool(left == right)),
+        ast::BinaryOp::Ne => Ok(Value::Bool(left != right)),
+        _ => Err(eval::Error::Binary {
+            span,
+            left: Value::Set(left),
+            op,
+            right: Value::Set(right),
+        }),
+    }
+}
+
+fn tuple_(
+    span: Span,
+    left: Rc<[Value]>,
+    op: ast::BinaryOp,
+    right: Rc<[Value]>,
+) -> eval::Result<Value> {
+    match op {
+        ast::BinaryOp::Eq => Ok(Value::Bool(left == right)),
+        ast::BinaryOp::Ne => Ok(Value::Bool(left != right)),
+        _ => Err(eval::Error::Binary {
+            span,
+            left: Value::Tuple(left),
+            op,
+            right: Value::Tuple(right),
+        }),
+    }
+}
@@ -1,62 +1,316 @@
-use crate::{eval::Value, parse::ast::Ident};
+use std::io::Write;
+
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    eval::{self, value, Value},
+    parse::ast::Ident,
+    span::Span,
+};
 
 pub(crate) struct Env {
-    global: Storage,
-    frames: Vec<Storage>,
-    cached: Vec<Storage>,
+    // name and value of each global, `None` until bound
+    global: Vec<(Rc<str>, Option<Value>)>,
+    // index of each global by name
+    indices: HashMap<Rc<str>, usize>,
+    // indices of the globals of the code evaluated at the top level, see
+    // `link()`
+    link: Rc<[usize]>,
+    frames: Vec<Frame>,
+    max_depth: Option<usize>,
+    cached: Vec<Vec<Option<Value>>>,
+    memory: Memory,
+
+    // set by another thread to abort the evaluation
+    interrupt: Arc<AtomicBool>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    // calls to `poll()` until the interrupt and the deadline are checked
+    countdown: u32,
+
+    // where `print()` writes to, stdout if `None`
+    output: Option<Box<dyn std::io::Write>>,
 }
 
-pub(crate) type Storage = std::collections::HashMap<std::rc::Rc<str>, (Option<usize>, Value)>;
+pub(crate) type Storage = HashMap<Rc<str>, Value>;
+
+// bindings of a call to a closure, by the slots of its layout
+struct Frame {
+    // the closure called, also the value of its recursive upvalue
+    closure: Value,
+    slots: Vec<Option<Value>>,
+}
+
+impl Frame {
+    fn closure(&self) -> &value::Closure {
+        match &self.closure {
+            Value::Closure(closure) => closure,
+            _ => unreachable!(),
+        }
+    }
+}
 
 impl Env {
     pub(crate) fn new(global: Storage) -> Self {
+        let global: Vec<_> = global
+            .into_iter()
+            .map(|(sym, value)| (sym, Some(value)))
+            .collect();
+        let indices = global
+            .iter()
+            .enumerate()
+            .map(|(i, (sym, _))| (sym.clone(), i))
+            .collect();
+
         Self {
             global,
+            indices,
+            link: Rc::from([]),
             frames: Default::default(),
+            max_depth: None,
             cached: Default::default(),
+            memory: Memory::default(),
+            interrupt: Default::default(),
+            timeout: None,
+            deadline: None,
+            countdown: 0,
+            output: None,
         }
     }
 
-    pub(super) fn get(&self, sym: &str) -> Option<&Value> {
-        self.frames
-            .last()
-            .and_then(|frame| frame.get(sym))
-            .or_else(|| self.global.get(sym)) // FIXME: filter out values in global but defined after the closure?
-            .map(|(_, value)| value)
+    pub(crate) fn set_output(&mut self, output: Box<dyn std::io::Write>) {
+        self.output = Some(output);
+    }
+
+    pub(crate) fn set_memory_limit(&mut self, limit: usize) {
+        self.memory.limit = Some(limit);
+    }
+
+    pub(crate) fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = Some(depth);
+    }
+
+    // fail if a call at `span` would nest the frames deeper than allowed,
+    // before the native stack overflows
+    pub(super) fn check_depth(&self, span: Span) -> eval::Result<()> {
+        match self.max_depth {
+            Some(limit) if self.frames.len() >= limit => Err(eval::Error::Depth { span, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    pub(crate) fn interrupt(&self) -> &Arc<AtomicBool> {
+        &self.interrupt
+    }
+
+    // start a new evaluation: count the memory allocated from zero and start
+    // the timeout, an interrupt requested before aborts it at the first poll
+    pub(crate) fn begin(&mut self) {
+        self.memory.used = 0;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.countdown = 0;
+    }
+
+    // end an evaluation, dropping an interrupt requested too late to abort it
+    pub(crate) fn end(&mut self) {
+        self.interrupt.store(false, Ordering::Relaxed);
+    }
+
+    // fail if the evaluation is interrupted or out of time, only checked
+    // every `POLL_INTERVAL` calls to keep it cheap
+    pub(super) fn poll(&mut self, span: Span) -> eval::Result<()> {
+        const POLL_INTERVAL: u32 = 1024;
+
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return Ok(());
+        }
+        self.countdown = POLL_INTERVAL;
+
+        if self.interrupt.swap(false, Ordering::Relaxed) {
+            return Err(eval::Error::Interrupted { span });
+        }
+        match (self.deadline, self.timeout) {
+            (Some(deadline), Some(timeout)) if Instant::now() >= deadline => {
+                Err(eval::Error::Timeout { span, timeout })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(super) fn memory(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    pub(super) fn print(&mut self, args: std::fmt::Arguments<'_>) {
+        match self.output {
+            // a host capturing the output decides how to handle its errors
+            Some(ref mut output) => {
+                let _ = writeln!(output, "{}", args);
+            }
+            None => println!("{}", args),
+        }
+    }
+
+    // values defined by the host or previous evaluations
+    pub(crate) fn globals(&self) -> impl Iterator<Item = (&Rc<str>, &Value)> {
+        self.global
+            .iter()
+            .filter_map(|(sym, value)| Some((sym, value.as_ref()?)))
+    }
+
+    // make `globals`, the names of the globals of the code about to be
+    // evaluated at the top level, indices into the globals of the env, the
+    // unbound ones are added
+    pub(crate) fn link(&mut self, globals: &[Rc<str>]) {
+        let link = globals
+            .iter()
+            .map(|sym| {
+                *self.indices.entry(sym.clone()).or_insert_with(|| {
+                    self.global.push((sym.clone(), None));
+                    self.global.len() - 1
+                })
+            })
+            .collect();
+        self.link = link;
+    }
+
+    // indices of the globals of the code evaluated in the current frame, for
+    // a closure created there
+    pub(super) fn current_link(&self) -> &Rc<[usize]> {
+        match self.frames.last() {
+            Some(frame) => &frame.closure().globals,
+            None => &self.link,
+        }
+    }
+
+    pub(super) fn get(&self, ident: &Ident) -> Option<&Value> {
+        let local = self.frames.last().and_then(|frame| {
+            let bound = ident
+                .slot
+                .and_then(|slot| frame.slots[slot as usize].as_ref());
+            bound.or_else(|| {
+                let upvalue = ident.upvalue? as usize;
+                let closure = frame.closure();
+                if closure.recursive == Some(upvalue) {
+                    Some(&frame.closure)
+                } else {
+                    closure.captured[upvalue].as_ref()
+                }
+            })
+        });
+
+        // FIXME: filter out values in global but defined after the closure?
+        local.or_else(|| {
+            let global = self.current_link()[ident.global? as usize];
+            self.global[global].1.as_ref()
+        })
     }
 
     pub(super) fn find_similar_symbol<'a>(&'a self, sym: &str) -> Option<&'a std::rc::Rc<str>> {
         self.frames
             .last()
-            .and_then(|frame| find_closest_symbol(sym, frame.keys()))
-            .or_else(|| find_closest_symbol(sym, self.global.keys()))
+            .and_then(|frame| {
+                let closure = frame.closure();
+                let layout = &closure.f.layout;
+                let bound = layout.slots.iter().zip(&frame.slots);
+                let captured = layout
+                    .captures
+                    .iter()
+                    .map(|ident| ident.sym_rc_str())
+                    .zip(closure.captured.iter());
+                find_closest_symbol(
+                    sym,
+                    bound
+                        .chain(captured)
+                        .filter(|(_, value)| value.is_some())
+                        .map(|(sym, _)| sym),
+                )
+            })
+            .or_else(|| find_closest_symbol(sym, self.globals().map(|(sym, _)| sym)))
     }
 
-    pub(super) fn set(&mut self, ident: Ident, value: Value) {
-        self.frames
-            .last_mut()
-            .unwrap_or(&mut self.global)
-            .insert(ident.sym_rc_str().clone(), (Some(ident.pos()), value));
+    pub(super) fn set(&mut self, ident: &Ident, value: Value) {
+        match (ident.slot, self.frames.last_mut()) {
+            (Some(slot), Some(frame)) => frame.slots[slot as usize] = Some(value),
+            _ => {
+                let global = self.current_link()[ident.global.unwrap() as usize];
+                self.global[global].1 = Some(value);
+            }
+        }
     }
 
-    pub(super) fn enclosed<F, T>(&mut self, f: F) -> T
+    // unbind the slots of the current frame and make it the frame of a call
+    // to `closure`, e.g. to reuse it for a call in tail position
+    pub(super) fn reset(&mut self, closure: &Rc<value::Closure>) {
+        let frame = self.frames.last_mut().unwrap();
+        if !matches!(&frame.closure, Value::Closure(other) if Rc::ptr_eq(other, closure)) {
+            frame.closure = Value::Closure(Rc::clone(closure));
+        }
+        frame.slots.clear();
+        frame.slots.resize(closure.f.layout.slots.len(), None);
+    }
+
+    // evaluate `run` in a new frame of a call to `closure`
+    pub(super) fn enclosed<F, T>(&mut self, closure: &Rc<value::Closure>, run: F) -> T
     where
         F: FnOnce(&mut Env) -> T,
     {
-        let new = self.cached.pop().unwrap_or_default();
-        self.frames.push(new);
+        let slots = self.cached.pop().unwrap_or_default();
+        self.frames.push(Frame {
+            closure: Value::Closure(Rc::clone(closure)),
+            slots,
+        });
+        self.reset(closure);
 
-        let result = f(self);
+        let result = run(self);
 
         let mut old = self.frames.pop().unwrap();
-        old.clear();
-        self.cached.push(old);
+        old.slots.clear();
+        self.cached.push(old.slots);
 
         result
     }
 }
 
-fn find_closest_symbol<'a, I>(
+// approximate size of the values created by an evaluation, only the new
+// storage is counted, e.g. not the elements a seq shares with another one
+#[derive(Default)]
+pub(crate) struct Memory {
+    used: usize,
+    limit: Option<usize>,
+}
+
+impl Memory {
+    // count `bytes` allocated for a value created at `span`, before they are
+    // allocated, so a value too large fails instead of exhausting the memory
+    pub(crate) fn allocate(&mut self, span: Span, bytes: usize) -> eval::Result<()> {
+        self.used = self.used.saturating_add(bytes);
+        match self.limit {
+            Some(limit) if self.used > limit => Err(eval::Error::MemoryLimit { span, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    // bytes allocated for `len` values, e.g. the elements of a seq
+    pub(crate) fn allocate_values(&mut self, span: Span, len: usize) -> eval::Result<()> {
+        self.allocate(span, len.saturating_mul(std::mem::size_of::<Value>()))
+    }
+}
+
+pub(crate) fn find_closest_symbol<'a, I>(
     undefined_symbol: &str,
     defined_symbols: I,
 ) -> Option<&'a std::rc::Rc<str>>
@@ -70,7 +324,7 @@ where
 }
 
 fn compute_edit_distance(a: &str, b: &str) -> usize {
-    let mut row = (0..b.chars().count() + 1).into_iter().collect::<Vec<_>>();
+    let mut row = (0..b.chars().count() + 1).collect::<Vec<_>>();
 
     for (ai, ac) in a.chars().enumerate() {
         let mut left = ai + 1;
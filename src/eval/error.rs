@@ -1,64 +1,164 @@
-use crate::{eval::value::Value, parse::ast};
+use crate::{eval::value::Value, parse::ast, span::Span};
 
 #[derive(Debug)]
 pub(crate) enum Error {
     AssertEq {
-        pos: usize,
+        span: Span,
         left: Value,
         right: Value,
     },
     Parse(crate::parse::Error),
+    Type(crate::typeck::Error),
     Undefined(ast::Ident, Option<std::rc::Rc<str>>),
     Index {
-        pos: usize,
+        span: Span,
         base: Value,
         subscript: Value,
     },
     Unary {
-        pos: usize,
+        span: Span,
         op: ast::UnaryOp,
         operand: Value,
     },
     Binary {
-        pos: usize,
+        span: Span,
         left: Value,
         op: ast::BinaryOp,
         right: Value,
     },
     Call {
-        pos: usize,
+        span: Span,
         target: Value,
-        args: Box<[Value]>,
     },
     ArgCount {
-        pos: usize,
+        span: Span,
         supplied: usize,
-        expected: usize,
+        expected: Arity,
+        // span of the callee closure, `None` for builtins
+        defined: Option<Span>,
     },
     ArgType {
-        pos: usize,
+        span: Span,
         supplied: Value,
-        expected: &'static str,
+        // e.g. `seq` for a builtin, or the annotated type of a parameter
+        expected: std::borrow::Cow<'static, str>,
     },
     ArgValue {
-        pos: usize,
+        span: Span,
         message: &'static str,
     },
+    // a value bound by a `let` of another type than its annotation
+    LetType {
+        span: Span,
+        supplied: Value,
+        expected: std::borrow::Cow<'static, str>,
+    },
+    // a closure returning a value of another type than its annotation, or a
+    // callback passed to a builtin returning a value of the wrong type
+    ReturnType {
+        span: Span,
+        supplied: Value,
+        expected: std::borrow::Cow<'static, str>,
+    },
+    // see `EnvBuilder::with_memory_limit()`
+    MemoryLimit {
+        span: Span,
+        limit: usize,
+    },
+    // see `EnvBuilder::with_max_depth()`
+    Depth {
+        span: Span,
+        limit: usize,
+    },
+    // see `Env::interrupt_handle()`
+    Interrupted {
+        span: Span,
+    },
+    // see `EnvBuilder::with_timeout()`
+    Timeout {
+        span: Span,
+        timeout: std::time::Duration,
+    },
+}
+
+// the number of args a callee accepts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Arity {
+    min: usize,
+    // `None` if there's no limit
+    max: Option<usize>,
+}
+
+impl Arity {
+    pub(crate) fn exactly(n: usize) -> Self {
+        Self::between(n, n)
+    }
+
+    pub(crate) fn between(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub(crate) fn at_least(min: usize) -> Self {
+        Self { min, max: None }
+    }
+}
+
+impl std::fmt::Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{} to {}", self.min, max),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
 }
 
 impl Error {
-    pub(crate) fn pos(&self) -> usize {
+    pub(crate) fn span(&self) -> Span {
         match self {
-            Self::AssertEq { pos, .. } => *pos,
-            Self::Parse(error) => error.pos(),
-            Self::Undefined(ident, _) => ident.pos(),
-            Self::Index { pos, .. } => *pos,
-            Self::Unary { pos, .. } => *pos,
-            Self::Binary { pos, .. } => *pos,
-            Self::Call { pos, .. } => *pos,
-            Self::ArgCount { pos, .. } => *pos,
-            Self::ArgType { pos, .. } => *pos,
-            Self::ArgValue { pos, .. } => *pos,
+            Self::AssertEq { span, .. } => *span,
+            Self::Parse(error) => error.span(),
+            Self::Type(error) => error.span(),
+            Self::Undefined(ident, _) => ident.span(),
+            Self::Index { span, .. } => *span,
+            Self::Unary { span, .. } => *span,
+            Self::Binary { span, .. } => *span,
+            Self::Call { span, .. } => *span,
+            Self::ArgCount { span, .. } => *span,
+            Self::ArgType { span, .. } => *span,
+            Self::ArgValue { span, .. } => *span,
+            Self::LetType { span, .. } => *span,
+            Self::ReturnType { span, .. } => *span,
+            Self::MemoryLimit { span, .. } => *span,
+            Self::Depth { span, .. } => *span,
+            Self::Interrupted { span } => *span,
+            Self::Timeout { span, .. } => *span,
+        }
+    }
+
+    // stable snake_case name of the variant, for machine-readable output
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::AssertEq { .. } => "assert_eq",
+            Self::Parse(error) => error.name(),
+            Self::Type(error) => error.name(),
+            Self::Undefined(..) => "undefined",
+            Self::Index { .. } => "index",
+            Self::Unary { .. } => "unary",
+            Self::Binary { .. } => "binary",
+            Self::Call { .. } => "call",
+            Self::ArgCount { .. } => "arg_count",
+            Self::ArgType { .. } => "arg_type",
+            Self::ArgValue { .. } => "arg_value",
+            Self::LetType { .. } => "let_type",
+            Self::ReturnType { .. } => "return_type",
+            Self::MemoryLimit { .. } => "memory_limit",
+            Self::Depth { .. } => "depth",
+            Self::Interrupted { .. } => "interrupted",
+            Self::Timeout { .. } => "timeout",
         }
     }
 }
@@ -73,12 +173,12 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
             Self::AssertEq {
-                pos: _,
+                span: _,
                 left,
                 right,
             } => {
                 f.write_str("assert_eq failed\n")?;
-                write!(f, " left: {:?}\n", left)?;
+                writeln!(f, " left: {:?}", left)?;
                 write!(f, "right: {:?}", right)?;
                 Ok(())
             }
@@ -94,21 +194,21 @@ impl std::fmt::Display for Error {
                 Ok(())
             }
             Self::Index {
-                pos: _,
+                span: _,
                 base,
                 subscript,
             } => {
                 write!(f, "index {:?} with {:?}", base, subscript)
             }
             Self::Unary {
-                pos: _,
+                span: _,
                 op,
                 operand,
             } => {
                 write!(f, "invalid unary operator {} for {:?}", op, operand)
             }
             Self::Binary {
-                pos: _,
+                span: _,
                 left,
                 op,
                 right,
@@ -123,21 +223,52 @@ impl std::fmt::Display for Error {
                 write!(f, "{:?} is not callable", target)
             }
             Self::ArgCount {
-                pos: _,
-                supplied,
-                expected,
+                supplied, expected, ..
             } => {
                 write!(f, "accept arg x {}, but got {}", expected, supplied)
             }
             Self::ArgType {
-                pos: _,
+                span: _,
                 supplied,
                 expected,
             } => {
                 write!(f, "accept arg of type {}, but got {:?}", expected, supplied)
             }
-            Self::ArgValue { pos: _, message } => f.write_str(message),
+            Self::ArgValue { span: _, message } => f.write_str(message),
+            Self::LetType {
+                span: _,
+                supplied,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "expect a value of type {} to bind, but got {:?}",
+                    expected, supplied
+                )
+            }
+            Self::ReturnType {
+                span: _,
+                supplied,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "expect a return value of type {}, but got {:?}",
+                    expected, supplied
+                )
+            }
+            Self::MemoryLimit { span: _, limit } => {
+                write!(f, "memory limit of {} bytes exceeded", limit)
+            }
+            Self::Depth { span: _, limit } => {
+                write!(f, "calls nested deeper than {}", limit)
+            }
+            Self::Interrupted { span: _ } => f.write_str("interrupted"),
+            Self::Timeout { span: _, timeout } => {
+                write!(f, "timed out after {:?}", timeout)
+            }
             Self::Parse(error) => error.fmt(f),
+            Self::Type(error) => error.fmt(f),
         }
     }
 }
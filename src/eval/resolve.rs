@@ -0,0 +1,156 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    eval::value::closure,
+    parse::{
+        ast::{self, Ident, Layout},
+        visit::{self, Visitor, VisitorMut},
+    },
+};
+
+// names of the globals of resolved code, by index, see `Env::link()`
+pub(crate) type Globals = Rc<[Rc<str>]>;
+
+// assign each identifier an index where its value is looked up instead of by
+// name: a slot in the frame of the closure it's in if it's bound there, an
+// upvalue if it's one of the captures of the closure, and a global otherwise
+//
+// a closure captures the values of its free identifiers when it's created, so
+// there are three places an identifier is looked up in: the frame of the
+// closure it's in, then the captures of the closure if the slot is not bound
+// yet, then the globals if the capture was not bound when the closure was
+// created
+pub(crate) fn resolve(nodes: &mut [ast::Node]) -> Globals {
+    let mut resolver = Resolver {
+        frames: Vec::new(),
+        globals: Vec::new(),
+        indices: HashMap::new(),
+    };
+
+    for node in nodes {
+        resolver.visit_node_mut(node);
+    }
+
+    resolver.globals.into()
+}
+
+struct Resolver {
+    // the closures being resolved, none at the top level
+    frames: Vec<Frame>,
+    globals: Vec<Rc<str>>,
+    // index of each global by name
+    indices: HashMap<Rc<str>, u32>,
+}
+
+struct Frame {
+    // name of each slot, the parameters then the `let`s of the closure
+    slots: Vec<Rc<str>>,
+    // name of each capture
+    captures: Vec<Rc<str>>,
+}
+
+impl Resolver {
+    fn resolve(&mut self, ident: &mut Ident) {
+        let sym = ident.sym_rc_str().clone();
+        let global = match self.frames.last() {
+            Some(frame) => {
+                ident.slot = position(&frame.slots, &sym);
+                ident.upvalue = position(&frame.captures, &sym);
+                ident.upvalue.is_some()
+            }
+            None => {
+                ident.slot = None;
+                ident.upvalue = None;
+                true
+            }
+        };
+        ident.global = global.then(|| self.global(sym));
+    }
+
+    fn global(&mut self, sym: Rc<str>) -> u32 {
+        *self.indices.entry(sym).or_insert_with_key(|sym| {
+            self.globals.push(sym.clone());
+            u32::try_from(self.globals.len() - 1).unwrap()
+        })
+    }
+}
+
+impl VisitorMut for Resolver {
+    fn visit_let_mut(&mut self, let_: &mut ast::Let) {
+        visit::walk_let_mut(self, let_);
+        self.resolve(&mut let_.ident);
+    }
+
+    fn visit_ident_mut(&mut self, ident: &mut Ident) {
+        self.resolve(ident);
+    }
+
+    fn visit_closure_mut(&mut self, f: &mut ast::Closure) {
+        let mut captures = Vec::<Ident>::new();
+        for ident in closure::analyze_unbounded(f) {
+            if !captures.iter().any(|other| other.sym() == ident.sym()) {
+                captures.push(ident.clone());
+            }
+        }
+
+        // looked up in the frame where the closure is created, down to the
+        // globals
+        for ident in captures.iter_mut() {
+            self.resolve(ident);
+            ident.global = Some(self.global(ident.sym_rc_str().clone()));
+        }
+
+        let mut slots = Vec::new();
+        for ident in f.parameters.iter_mut() {
+            ident.slot = Some(u32::try_from(index(&mut slots, ident.sym_rc_str())).unwrap());
+        }
+        let mut bindings = Bindings(Vec::new());
+        bindings.visit_block(&f.body);
+        for sym in &bindings.0 {
+            index(&mut slots, sym);
+        }
+
+        self.frames.push(Frame {
+            slots,
+            captures: captures
+                .iter()
+                .map(|ident| ident.sym_rc_str().clone())
+                .collect(),
+        });
+        visit::walk_closure_mut(self, f);
+        let frame = self.frames.pop().unwrap();
+
+        f.layout = Layout {
+            slots: frame.slots.into(),
+            captures: captures.into(),
+        };
+    }
+}
+
+// names bound by the `let`s of a closure, nested closures have their own
+struct Bindings(Vec<Rc<str>>);
+
+impl<'a> Visitor<'a> for Bindings {
+    fn visit_let(&mut self, let_: &'a ast::Let) {
+        visit::walk_let(self, let_);
+        self.0.push(let_.ident.sym_rc_str().clone());
+    }
+
+    fn visit_closure(&mut self, _: &'a ast::Closure) {}
+}
+
+fn position(syms: &[Rc<str>], sym: &Rc<str>) -> Option<u32> {
+    let index = syms.iter().position(|other| other == sym)?;
+    Some(u32::try_from(index).unwrap())
+}
+
+// index of `sym` in `syms`, a new one if it's not there yet
+fn index(syms: &mut Vec<Rc<str>>, sym: &Rc<str>) -> usize {
+    match syms.iter().position(|other| other == sym) {
+        Some(index) => index,
+        None => {
+            syms.push(sym.clone());
+            syms.len() - 1
+        }
+    }
+}
@@ -3,8 +3,8 @@ use super::*;
 fn test_env() -> Env {
     let mut global = env::Storage::default();
 
-    for (k, v) in value::builtin::all_() {
-        global.insert(std::rc::Rc::from(k), (None, Value::Builtin(v)));
+    for entry in value::builtin::ALL.iter() {
+        global.insert(std::rc::Rc::from(entry.name), Value::Builtin(entry.f));
     }
 
     Env::new(global)
@@ -156,6 +156,92 @@ fn closure_early_return() {
     t!("let f = fn(x) { if x < 0 { return 0; } x }; f(1)", 1);
 }
 
+#[test]
+fn tail_call() {
+    t!(
+        "let f = fn(n, acc) { if n == 0 { acc } else { f(n - 1, acc + 1) } }; f(1000000, 0)",
+        1000000
+    );
+    t!(
+        "let f = fn(n) { if n == 0 { return 1; } return f(n - 1); 2 }; f(100000)",
+        1
+    );
+    t!(
+        "
+let even = fn(n) { if n == 0 { true } else { odd(n - 1) } };
+let odd = fn(n) { if n == 0 { false } else { even(n - 1) } };
+if even(100001) { 1 } else { 0 }",
+        0
+    );
+
+    // the result is still checked against the return type of each closure
+    t!(
+        "let f = fn(n) -> int { if n == 0 { 1 } else { g(n - 1) } }; let g = fn(n) { f(n) }; f(3)",
+        1
+    );
+    let mut env = test_env();
+    let code = "let f = fn(n) -> int { if n == 0 { \"a\" } else { g(n - 1) } };\nlet g = fn(n) -> str { f(n) };\ng(3)";
+    let error = entry(&mut env, code.as_bytes()).unwrap_err();
+    assert_eq!(&code[error.span().start..error.span().end], "f(n)");
+    assert_eq!(
+        error.to_string(),
+        "expect a return value of type int, but got \"a\""
+    );
+}
+
+#[test]
+fn slot() {
+    // a capture not bound when the closure is created is a global
+    t!("let f = fn() { x }; let x = 1; let x = 2; f()", 2);
+    t!("let x = 1; let f = fn() { x }; let x = 2; f()", 1);
+    t!(
+        "let f = fn(x) { let g = fn() { x }; let x = 2; g() * 10 + x }; f(1)",
+        12
+    );
+    t!("let f = fn(x, x) { x }; f(1, 2)", 2);
+    // a capture is read until a binding of the same name in the closure
+    t!(
+        "let f = fn(x) { fn() { let y = x; let x = 10; x + y } }; f(1)() * 10 + f(2)()",
+        122
+    );
+    t!("let f = fn(c) { if c { let y = 1; } y }; f(true)", 1);
+    t!(error: "let f = fn(c) { if c { let y = 1; } y }; f(false)");
+
+    // a global bound by a later evaluation, or before a program is run
+    let mut env = test_env();
+    entry(&mut env, b"let f = fn() { g() + x };").unwrap();
+    entry(&mut env, b"let x = 2;").unwrap();
+    assert_eq!(
+        entry(&mut env, b"let g = fn() { 1 }; f()").unwrap(),
+        Value::Int(3)
+    );
+    let program = crate::Program::compile(b"let y = x + 1; y * f()").unwrap();
+    for (x, expected) in [(1, 6), (2, 15)] {
+        let mut env = crate::Env::builder().with_int("x", x).build();
+        crate::entry(&mut env, b"let f = fn() { x + y };").unwrap();
+        assert_eq!(program.run(&mut env).unwrap().as_int(), Some(expected));
+    }
+
+    let mut env = test_env();
+    match entry(&mut env, b"let f = fn(count) { cout }; f(1)") {
+        Err(Error::Undefined(ident, Some(similar))) => {
+            assert_eq!(ident.sym(), "cout");
+            assert_eq!(&*similar, "count");
+        }
+        other => panic!("{:?}", other),
+    }
+    match entry(
+        &mut env,
+        b"let g = fn(count) { fn() { count + cout } }; g(1)()",
+    ) {
+        Err(Error::Undefined(ident, Some(similar))) => {
+            assert_eq!(ident.sym(), "cout");
+            assert_eq!(&*similar, "count");
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
 #[test]
 fn seq() {
     t!("[1, 2, 3]", [1, 2, 3]);
@@ -250,3 +336,682 @@ fn builtin_type() {
     t!(str: "type(type)", "builtin");
     t!(str: "type(fn(){})", "closure");
 }
+
+#[test]
+fn builtin_map() {
+    t!("map([], fn(x) { x * 2 })", []);
+    t!("map([1, 2, 3], fn(x) { x * 2 })", [2, 4, 6]);
+    t!("let k = 10; map([1, 2], fn(x) { x + k })", [11, 12]);
+
+    t!(error: "map(1, fn(x) { x })");
+    t!(error: "map([1], 1)");
+    t!(error: "map([1], fn(x, y) { x })");
+}
+
+#[test]
+fn builtin_filter() {
+    t!("filter([1, 2, 3, 4], fn(x) { x / 2 * 2 == x })", [2, 4]);
+    t!("filter([1, 2], fn(x) { false })", []);
+
+    t!(error: "filter([1, 2], fn(x) { x })");
+}
+
+#[test]
+fn builtin_reduce() {
+    t!("reduce([1, 2, 3], 0, fn(acc, x) { acc + x })", 6);
+    t!("reduce([], 7, fn(acc, x) { acc + x })", 7);
+    t!("reduce([1, 2], [], fn(acc, x) { [x] + acc })", [2, 1]);
+
+    t!(error: "reduce([1, 2], 0, fn(x) { x })");
+}
+
+#[test]
+fn builtin_any_all() {
+    t!(str: "type(any([], fn(x) { true }))", "bool");
+    t!("if any([1, 2], fn(x) { x == 2 }) { 1 } else { 0 }", 1);
+    t!("if any([1, 2], fn(x) { x == 3 }) { 1 } else { 0 }", 0);
+    t!(
+        "if all([2, 4], fn(x) { x / 2 * 2 == x }) { 1 } else { 0 }",
+        1
+    );
+    t!(
+        "if all([2, 3], fn(x) { x / 2 * 2 == x }) { 1 } else { 0 }",
+        0
+    );
+    t!("if all([], fn(x) { false }) { 1 } else { 0 }", 1);
+
+    // stop at the first decided element
+    t!("if any([1, \"a\"], fn(x) { x == 1 }) { 1 } else { 0 }", 1);
+
+    t!(error: "any([1], fn(x) { 1 })");
+    t!(error: "all([1], fn(x) { 1 })");
+}
+
+#[test]
+fn builtin_find() {
+    t!("find([1, 2, 3], fn(x) { x > 1 })", 2);
+    t!(unit: "find([1, 2, 3], fn(x) { x > 3 })");
+}
+
+#[test]
+fn builtin_sort() {
+    t!("sort([3, 1, 2])", [1, 2, 3]);
+    t!("sort([])", []);
+    t!(str: "head(sort([\"b\", \"c\", \"a\"]))", "a");
+
+    t!(error: "sort([1, \"a\"])");
+    t!(error: "sort([true, false])");
+}
+
+#[test]
+fn builtin_sort_by() {
+    t!("sort_by([3, 1, 2], fn(x) { -x })", [3, 2, 1]);
+    t!(
+        "map(sort_by([[2, 1], [1, 2], [1, 3]], fn(p) { p[0] }), fn(p) { p[1] })",
+        [2, 3, 1]
+    );
+
+    t!(error: "sort_by([1, 2], fn(x) { [x] })");
+}
+
+#[test]
+fn builtin_reverse() {
+    t!("reverse([1, 2, 3])", [3, 2, 1]);
+    t!("reverse([])", []);
+
+    t!(error: "reverse(1)");
+}
+
+#[test]
+fn builtin_zip_enumerate() {
+    t!(
+        "map(zip([1, 2, 3], [4, 5]), fn(p) { p[0] * p[1] })",
+        [4, 10]
+    );
+    t!("map(enumerate([5, 6]), fn(p) { p[0] + p[1] })", [5, 7]);
+
+    t!(error: "zip([1], 2)");
+}
+
+#[test]
+fn builtin_range() {
+    t!("range(3)", [0, 1, 2]);
+    t!("range(2, 5)", [2, 3, 4]);
+    t!("range(5, 2)", []);
+
+    t!(error: "range()");
+    t!(error: "range(1, 2, 3)");
+    t!(error: "range(\"3\")");
+}
+
+#[test]
+fn builtin_flat_map() {
+    t!("flat_map([1, 2], fn(x) { [x, x] })", [1, 1, 2, 2]);
+
+    t!(error: "flat_map([1, 2], fn(x) { x })");
+}
+
+#[test]
+fn builtin_sum_min_max() {
+    t!("sum([1, 2, 3])", 6);
+    t!("sum([])", 0);
+    t!("min([3, 1, 2])", 1);
+    t!("max([3, 1, 2])", 3);
+    t!(str: "max([\"a\", \"c\", \"b\"])", "c");
+
+    t!(error: "sum([1, \"a\"])");
+    t!(error: "min([])");
+    t!(error: "max([])");
+}
+
+#[test]
+fn builtin_callback_error_position() {
+    let code = "map([1, 2], fn(x) {\n    x + y\n})";
+
+    let mut env = test_env();
+    match entry(&mut env, code.as_bytes()) {
+        Err(Error::Undefined(ident, _)) => assert_eq!(ident.span().start, code.find("y").unwrap()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn builtin_error_message() {
+    let message = |code: &str| {
+        let mut env = test_env();
+        entry(&mut env, code.as_bytes()).unwrap_err().to_string()
+    };
+
+    assert_eq!(message("len()"), "accept arg x 1, but got 0");
+    assert_eq!(message("range()"), "accept arg x 1 to 2, but got 0");
+    assert_eq!(message("append([])"), "accept arg x at least 2, but got 1");
+    assert_eq!(
+        message("filter([1, 2], fn(x) { x })"),
+        "expect a return value of type bool, but got 1"
+    );
+}
+
+#[test]
+fn error_span() {
+    let code = "let x = 1;\nx + \"1\";";
+
+    let mut env = test_env();
+    let error = entry(&mut env, code.as_bytes()).unwrap_err();
+    assert_eq!(&code[error.span().start..error.span().end], "x + \"1\"");
+
+    let mut env = crate::Env::new();
+    let error = crate::entry(&mut env, b"f(1").unwrap_err();
+    assert_eq!(error.span(), 3..3);
+}
+
+#[test]
+fn map_merge() {
+    t!("({1: 2} + {3: 4})[3]", 4);
+    t!("({1: 2} + {1: 3})[1]", 3);
+    t!("len({1: 2, 3: 4} + {1: 3})", 2);
+    t!("len({} + {})", 0);
+}
+
+#[test]
+fn builtin_keys_values_entries() {
+    t!("sort(keys({3: true, 1: false}))", [1, 3]);
+    t!("sort(values({true: 3, false: 1}))", [1, 3]);
+    t!("keys({})", []);
+    t!("map(entries({2: 3}), fn(e) { e[0] * e[1] })", [6]);
+
+    // insertion order
+    t!("keys({3: true, 1: false, 2: true})", [3, 1, 2]);
+    t!("values({true: 3, false: 1})", [3, 1]);
+    t!(
+        "keys(insert(remove({1: 0, 2: 0, 3: 0}, 2), 2, 0))",
+        [1, 3, 2]
+    );
+    t!("keys({1: 2, 3: 4} + {5: 6, 1: 7})", [1, 3, 5]);
+
+    t!(error: "keys([])");
+    t!(error: "values(1)");
+    t!(error: "entries(\"\")");
+}
+
+#[test]
+fn builtin_has() {
+    t!("if has({1: 2}, 1) { 1 } else { 0 }", 1);
+    t!("if has({1: 2}, 2) { 1 } else { 0 }", 0);
+
+    t!(error: "has({1: 2}, [])");
+}
+
+#[test]
+fn builtin_insert_remove() {
+    t!("insert({1: 2}, 3, 4)[3]", 4);
+    t!("insert({1: 2}, 1, 4)[1]", 4);
+    t!("let m = {1: 2}; insert(m, 1, 4); m[1]", 2);
+    t!("len(remove({1: 2, 3: 4}, 1))", 1);
+    t!("len(remove({1: 2}, 3))", 1);
+    t!("let m = {1: 2}; remove(m, 1); len(m)", 1);
+
+    t!(error: "remove({1: 2}, 1)[1]");
+    t!(error: "insert({}, fn() {}, 1)");
+}
+
+#[test]
+fn builtin_merge() {
+    t!("merge({1: 2}, {1: 3, 2: 4})[1]", 3);
+    t!("len(merge({1: 2}, {}))", 1);
+
+    t!(error: "merge({}, [])");
+}
+
+#[test]
+fn builtin_from_entries() {
+    t!("from_entries([[1, 2], [3, 4]])[3]", 4);
+    t!("from_entries([[1, 2], [1, 4]])[1]", 4);
+    t!("len(from_entries([]))", 0);
+    t!("let m = {1: 2, \"a\": 3}; len(from_entries(entries(m)))", 2);
+
+    t!(error: "from_entries([[1]])");
+    t!(error: "from_entries([1, 2])");
+    t!(error: "from_entries({})");
+}
+
+#[test]
+fn set() {
+    t!("len(#{1, 2, 1})", 2);
+    t!("len(#{})", 0);
+    t!("len(#{1, 2} + #{2, 3})", 3);
+    t!("len(#{1, 2} * #{2, 3})", 1);
+    t!("len(#{1, 2} - #{2, 3})", 1);
+    t!("if #{1, 2} == #{2, 1} { 1 } else { 0 }", 1);
+    t!("if has(#{1, 2} - #{2}, 2) { 1 } else { 0 }", 0);
+    t!(str: "type(#{})", "set");
+
+    t!(error: "#{[]}");
+    t!(error: "#{1} < #{2}");
+}
+
+#[test]
+fn tuple() {
+    t!("(1, 2)[1]", 2);
+    t!("len((1, 2, 3))", 3);
+    t!("{(1, 2): 3, (2, 1): 4}[(2, 1)]", 4);
+    t!("len(#{(1, 2), (1, 2), (1, 3)})", 2);
+    t!("if (1, \"a\") == (1, \"a\") { 1 } else { 0 }", 1);
+    t!(str: "type((1,))", "tuple");
+
+    t!(error: "(1, 2)[2]");
+    t!(error: "{(1, []): 2}");
+}
+
+#[test]
+fn debug_set_tuple() {
+    let mut env = test_env();
+
+    let value = entry(&mut env, b"#{3, 1, (2, true), 3}").unwrap();
+    assert_eq!(format!("{:?}", value), "#{3, 1, (2, true)}");
+
+    let value = entry(&mut env, b"((1,), [2], {(3, \"a\"): 4})").unwrap();
+    assert_eq!(format!("{:?}", value), "((1,), [2], {(3, \"a\"): 4})");
+}
+
+#[test]
+fn unicode() {
+    t!("let größe = 2; größe * 3", 6);
+    t!(str: "let 名字 = \"世界\"; 名字", "世界");
+
+    let code = "let s = \"héllo\";\ns + 变量";
+
+    let mut env = crate::Env::new();
+    let error = crate::entry(&mut env, code.as_bytes()).unwrap_err();
+    assert_eq!(error.line_column(code.as_bytes()), (1, 4));
+
+    let code = "\"😀\" + 变量";
+    let error = crate::entry(&mut env, code.as_bytes()).unwrap_err();
+    assert_eq!(error.line_column(code.as_bytes()), (0, 6));
+    assert_eq!(error.line_column_utf16(code.as_bytes()), (0, 7));
+
+    let code = b"let s = \"\xff\";";
+    let error = crate::entry(&mut env, code).unwrap_err();
+    assert_eq!(error.span(), 9..10);
+}
+
+#[test]
+fn error_kind() {
+    let kind = |code: &str| {
+        let mut env = crate::Env::new();
+        let error = crate::entry(&mut env, code.as_bytes()).unwrap_err();
+        (error.kind(), error.name())
+    };
+
+    assert_eq!(kind("1 + $"), (crate::ErrorKind::Lex, "unexpected"));
+    assert_eq!(kind("let = 1"), (crate::ErrorKind::Parse, "mismatch"));
+    assert_eq!(kind("[1"), (crate::ErrorKind::Parse, "incomplete"));
+    assert_eq!(kind("len(1, 2)"), (crate::ErrorKind::Eval, "arg_count"));
+    assert_eq!(kind("foo"), (crate::ErrorKind::Eval, "undefined"));
+}
+
+#[test]
+fn check() {
+    let spans = |code: &str| {
+        crate::check(code.as_bytes())
+            .iter()
+            .map(|error| error.span())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(spans("let a = 1; a + 1"), []);
+    assert_eq!(spans("foo(1, 2)"), []);
+
+    assert_eq!(spans("let a = 1 +;\nlet b = ;"), [11..12, 21..22]);
+    assert_eq!(spans("let a = let b = ;"), [8..11, 16..17]);
+    assert_eq!(spans("let f = fn() { 1 + ; 2 }; f("), [19..20, 28..28]);
+    assert_eq!(spans("1 $ 2; $ 3; 4 +; let"), [2..3, 7..8, 15..16, 20..20]);
+}
+
+#[test]
+fn parse_first() {
+    let code = b"let a = 1; let b = ;";
+
+    let mut env = crate::Env::new();
+    assert!(crate::entry(&mut env, code).is_err());
+    assert_eq!(crate::entry(&mut env, b"a").unwrap().as_int(), Some(1));
+
+    let mut env = crate::Env::builder()
+        .with_builtin()
+        .with_parse_first()
+        .build();
+    assert!(crate::entry(&mut env, code).is_err());
+    assert!(crate::entry(&mut env, b"a").is_err());
+    assert_eq!(
+        crate::entry(&mut env, b"let a = 2; a").unwrap().as_int(),
+        Some(2)
+    );
+}
+
+#[test]
+fn annotation() {
+    t!(
+        "let x: [int] = [1]; let f = fn(x: int, xs: [int]) -> int { x + len(xs) }; f(1, x)",
+        2
+    );
+    t!(
+        "let f = fn(x: any, p: (int, str)) { x }; f(1, (2, \"a\"))",
+        1
+    );
+    t!(error: "let x: {str: int} = {\"a\": true};");
+    t!(error: "let f = fn(x: [int]) { x }; f([\"a\"])");
+    t!(error: "let f = fn(x) -> int { x }; f(\"a\")");
+
+    let mut env = test_env();
+    let code = "let f = fn(x: #{int}) { x };\nf(#{true})";
+    let error = entry(&mut env, code.as_bytes()).unwrap_err();
+    assert_eq!(&code[error.span().start..error.span().end], "f(#{true})");
+    assert_eq!(
+        error.to_string(),
+        "accept arg of type #{int}, but got #{true}"
+    );
+
+    let error = entry(&mut env, b"let x: {str: int} = {\"a\": true};").unwrap_err();
+    assert_eq!(error.name(), "let_type");
+    assert_eq!(
+        error.to_string(),
+        "expect a value of type {str: int} to bind, but got {\"a\": true}"
+    );
+    let error = entry(&mut env, b"let f = fn(x) -> int { x }; f(\"a\")").unwrap_err();
+    assert_eq!(error.name(), "return_type");
+}
+
+#[test]
+fn type_check() {
+    let code = b"let a = 1; print(a); a + \"b\"";
+
+    let mut env = crate::Env::builder()
+        .with_builtin()
+        .with_output(std::io::sink())
+        .with_type_check()
+        .with_int("n", 1)
+        .build();
+    let error = crate::entry(&mut env, code).unwrap_err();
+    assert_eq!(error.kind(), crate::ErrorKind::Type);
+    assert_eq!(error.span(), 21..28);
+    assert!(crate::entry(&mut env, b"a").is_err());
+
+    // typed by the values of the globals
+    assert!(crate::entry(&mut env, b"let a = n + 1;").is_ok());
+    assert!(crate::entry(&mut env, b"a + n").is_ok());
+    assert!(crate::entry(&mut env, b"len(a)").is_err());
+
+    let program = crate::Program::compile(b"n + true").unwrap();
+    assert_eq!(
+        program.run(&mut env).unwrap_err().to_string(),
+        "invalid binary operator + between int and bool"
+    );
+}
+
+#[test]
+fn program() {
+    let program = crate::Program::compile(b"let f = fn(x) { x + n };\nf(1)").unwrap();
+
+    for n in [1, 2] {
+        let mut env = crate::Env::builder().with_int("n", n).build();
+        assert_eq!(program.run(&mut env).unwrap().as_int(), Some(n + 1));
+        assert_eq!(program.run(&mut env).unwrap().as_int(), Some(n + 1));
+    }
+
+    let mut env = crate::Env::new();
+    let error = program.run(&mut env).unwrap_err();
+    assert_eq!(program.source_map().line_column(&error), (0, 20));
+
+    let error = crate::Program::compile(b"let a = ;").err().unwrap();
+    assert_eq!(error.kind(), crate::ErrorKind::Parse);
+}
+
+#[test]
+fn output() {
+    #[derive(Clone, Default)]
+    struct Output(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output = Output::default();
+    let mut env = crate::Env::builder()
+        .with_builtin()
+        .with_output(output.clone())
+        .build();
+
+    crate::entry(&mut env, b"print(1, \"a\"); print()").unwrap();
+    assert_eq!(*output.0.borrow(), b"1\n\"a\"\n\n");
+}
+
+#[test]
+fn memory_limit() {
+    let code = b"let f = fn(s) { f(s + s) };\nf(\"a\")";
+
+    let mut env = crate::Env::builder()
+        .with_builtin()
+        .with_memory_limit(1 << 20)
+        .build();
+    let error = crate::entry(&mut env, code).unwrap_err();
+    assert_eq!(error.kind(), crate::ErrorKind::Eval);
+    assert_eq!(error.name(), "memory_limit");
+    assert_eq!(error.span(), 18..23);
+    assert_eq!(error.to_string(), "memory limit of 1048576 bytes exceeded");
+
+    // even for strings of literals, which are never folded by the optimizer
+    let mut code = String::from("let s0 = \"a\";");
+    for i in 1..=22 {
+        code += &format!(" let s{} = s{} + s{};", i, i - 1, i - 1);
+    }
+    let program = crate::Program::compile(code.as_bytes()).unwrap();
+    for program in [program.clone(), program.optimize()] {
+        let mut env = crate::Env::builder().with_memory_limit(1 << 16).build();
+        assert_eq!(program.run(&mut env).unwrap_err().name(), "memory_limit");
+    }
+
+    // counted for each evaluation
+    let mut env = crate::Env::builder()
+        .with_builtin()
+        .with_memory_limit(1500 * std::mem::size_of::<Value>())
+        .build();
+    let code = b"let f = fn(n, xs) { if n == 0 { xs } else { f(n - 1, append(xs, n)) } }; len(f(1000, []))";
+    for _ in 0..2 {
+        assert_eq!(crate::entry(&mut env, code).unwrap().as_int(), Some(1000));
+    }
+
+    let fails = |code: &str| {
+        let mut env = crate::Env::builder()
+            .with_builtin()
+            .with_memory_limit(1000)
+            .build();
+        crate::entry(&mut env, code.as_bytes()).is_err()
+    };
+    assert!(fails(
+        "let f = fn(n, xs) { if n == 0 { xs } else { f(n - 1, append(xs, n)) } }; f(1000, [])"
+    ));
+    assert!(fails(
+        "let f = fn(n, xs) { if n == 0 { xs } else { f(n - 1, xs + [n]) } }; f(1000, [])"
+    ));
+    assert!(fails(
+        "let f = fn(n) { if n == 0 { 0 } else { let m = {1: n}; f(n - 1) } }; f(1000)"
+    ));
+    assert!(!fails(
+        "let f = fn(n, s) { if n == 0 { s } else { f(n - 1, s + \"a\") } }; f(10, \"\")"
+    ));
+
+    // the builtins and operators building collections are counted too
+    let fails = |code: &str| {
+        let mut env = crate::Env::builder()
+            .with_builtin()
+            .with_memory_limit(1 << 20)
+            .build();
+        match crate::entry(&mut env, code.as_bytes()) {
+            Ok(_) => false,
+            Err(error) => error.name() == "memory_limit",
+        }
+    };
+    assert!(fails("len(range(50000000))"));
+    assert!(fails("range(2147483647)"));
+    assert!(fails("range(-2147483647, 2147483647)"));
+    assert!(fails("len(map(range(40000), fn(x) { x }))"));
+    assert!(fails("len(flat_map(range(1000), fn(x) { range(1000) }))"));
+    assert!(fails(
+        "let f = fn(n, m) { if n == 0 { m } else { f(n - 1, insert(m, n, n)) } }; len(f(200000, {}))"
+    ));
+    assert!(fails(
+        "let m = from_entries(map(range(10000), fn(x) { [x, x] }));\nlet f = fn(n) { if n == 0 { 0 } else { let a = {} + m; f(n - 1) } }; f(100)"
+    ));
+    assert!(fails("let s = #{1} + #{2}; let t = (1, 2); len(entries(from_entries(zip(range(30000), range(30000)))))"));
+    assert!(!fails(
+        "len(range(1000)) + len(map(range(1000), fn(x) { x })) + len({1: 2} + {3: 4})"
+    ));
+
+    // unlimited by default
+    let mut env = crate::Env::new();
+    let code = b"let f = fn(n, s) { if n == 0 { len(s) } else { f(n - 1, s + s) } }; f(20, \"a\")";
+    assert_eq!(
+        crate::entry(&mut env, code).unwrap().as_int(),
+        Some(1 << 20)
+    );
+}
+
+#[test]
+fn interrupt() {
+    let mut env = crate::Env::new();
+    let handle = env.interrupt_handle();
+
+    // requested before the evaluation, which is aborted at once
+    handle.interrupt();
+    let error = crate::entry(&mut env, b"let a = 1; a").unwrap_err();
+    assert_eq!(error.name(), "interrupted");
+    let program = crate::Program::compile(b"let a = 1; a").unwrap();
+    handle.interrupt();
+    assert_eq!(program.run(&mut env).unwrap_err().name(), "interrupted");
+
+    // but not the one after it
+    assert_eq!(
+        crate::entry(&mut env, b"let a = 1; a").unwrap().as_int(),
+        Some(1)
+    );
+
+    let done = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let interrupter = std::thread::spawn({
+        let done = done.clone();
+        move || {
+            while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                handle.interrupt();
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+    });
+
+    let code = b"let f = fn(n) { if n > 0 { 1 + f(n - 1) } else { 0 } };\nlet g = fn() { f(50); g() };\ng()";
+    let error = crate::entry(&mut env, code).unwrap_err();
+    done.store(true, std::sync::atomic::Ordering::Relaxed);
+    interrupter.join().unwrap();
+
+    assert_eq!(error.kind(), crate::ErrorKind::Eval);
+    assert_eq!(error.name(), "interrupted");
+    assert_eq!(error.to_string(), "interrupted");
+
+    // the frames of the calls aborted are gone
+    assert_eq!(
+        crate::entry(&mut env, b"a + f(0) * 0").unwrap().as_int(),
+        Some(1)
+    );
+    assert_eq!(
+        crate::entry(&mut env, b"let g = fn(x) { x + a }; g(1)")
+            .unwrap()
+            .as_int(),
+        Some(2)
+    );
+
+    let timeout = std::time::Duration::from_millis(20);
+    let mut env = crate::Env::builder().with_timeout(timeout).build();
+    let error = crate::entry(&mut env, b"let f = fn() { f() }; f()").unwrap_err();
+    assert_eq!(error.name(), "timeout");
+    assert_eq!(error.to_string(), "timed out after 20ms");
+
+    // the timeout starts with each evaluation
+    assert_eq!(
+        crate::entry(&mut env, b"let g = fn(x) { x }; g(1)")
+            .unwrap()
+            .as_int(),
+        Some(1)
+    );
+
+    let mut env = crate::Env::builder().with_max_depth(50).build();
+    let error = crate::entry(&mut env, b"let f = fn(n) { 1 + f(n) };\nf(0)").unwrap_err();
+    assert_eq!(error.name(), "depth");
+    assert_eq!(error.span(), 20..24);
+    assert_eq!(error.to_string(), "calls nested deeper than 50");
+
+    // tail calls don't nest
+    assert_eq!(
+        crate::entry(
+            &mut env,
+            b"let g = fn(n) { if n == 0 { 0 } else { g(n - 1) } }; g(1000)"
+        )
+        .unwrap()
+        .as_int(),
+        Some(0)
+    );
+}
+
+#[test]
+fn capabilities() {
+    use crate::Cap;
+
+    // every builtin is sorted into a single capability
+    for entry in value::builtin::ALL.iter() {
+        assert!(
+            [Cap::CORE, Cap::COLLECTIONS, Cap::IO].contains(&entry.cap),
+            "{}",
+            entry.name
+        );
+    }
+
+    let run = |builder: crate::EnvBuilder, code: &str| {
+        let mut env = builder.with_output(std::io::sink()).build();
+        crate::entry(&mut env, code.as_bytes()).map_err(|error| error.name())
+    };
+
+    let pure = || crate::Env::builder().with_capabilities(Cap::PURE);
+    assert!(run(pure(), "sum(map([1, 2], fn(x) { x * len(\"ab\") }))").is_ok());
+    assert_eq!(run(pure(), "print(1)").unwrap_err(), "undefined");
+    assert!(run(pure().with_capabilities(Cap::IO), "print(1)").is_ok());
+
+    let core = || crate::Env::builder().with_capabilities(Cap::CORE);
+    assert!(run(core(), "head(append([], 1))").is_ok());
+    assert_eq!(run(core(), "sort([1])").unwrap_err(), "undefined");
+    assert_eq!(
+        run(crate::Env::builder(), "len([])").unwrap_err(),
+        "undefined"
+    );
+
+    assert_eq!(
+        run(
+            crate::Env::builder().with_builtin().deny("print"),
+            "print(1)"
+        )
+        .unwrap_err(),
+        "undefined"
+    );
+    assert!(run(
+        crate::Env::builder().deny("print").with_builtin(),
+        "len([])"
+    )
+    .is_ok());
+
+    // the host values take the place of the builtins, whichever is added first
+    let env = crate::Env::builder().with_int("len", 1).with_builtin();
+    assert_eq!(run(env, "len + 1").unwrap().as_int(), Some(2));
+    let env = crate::Env::builder().with_builtin().with_str("print", "no");
+    assert_eq!(run(env, "print").unwrap().as_str(), Some("no"));
+}
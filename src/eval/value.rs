@@ -1,10 +1,18 @@
 pub(crate) mod builtin;
-mod closure;
+pub(crate) mod closure;
+mod map;
+mod seq;
+mod set;
 
-use std::{collections::HashMap, fmt, rc::Rc};
+use std::{fmt, rc::Rc};
+
+use crate::parse::ast;
 
 pub(crate) type Builtin = builtin::Builtin;
 pub(crate) type Closure = closure::Closure;
+pub(crate) type Map = map::Map;
+pub(crate) type Seq = seq::Seq;
+pub(crate) type Set = set::Set;
 
 #[derive(Clone)]
 pub(crate) enum Value {
@@ -12,17 +20,20 @@ pub(crate) enum Value {
     Int(i32),
     Bool(bool),
     Str(Rc<str>),
-    Seq(Rc<[Value]>),
-    Map(Rc<HashMap<Key, Value>>),
+    Seq(Seq),
+    Map(Rc<Map>),
+    Set(Rc<Set>),
+    Tuple(Rc<[Value]>),
     Builtin(Builtin),
     Closure(Rc<Closure>),
 }
 
-#[derive(Hash, PartialEq, Eq)]
+#[derive(Clone, Hash, PartialEq, Eq)]
 pub(crate) enum Key {
     Int(i32),
     Bool(bool),
     Str(Rc<str>),
+    Tuple(Rc<[Key]>),
 }
 
 pub(super) fn to_key(value: &Value) -> Option<Key> {
@@ -30,10 +41,68 @@ pub(super) fn to_key(value: &Value) -> Option<Key> {
         Value::Int(x) => Some(Key::Int(*x)),
         Value::Bool(x) => Some(Key::Bool(*x)),
         Value::Str(s) => Some(Key::Str(Rc::clone(s))),
+        Value::Tuple(tuple) => tuple
+            .iter()
+            .map(to_key)
+            .collect::<Option<_>>()
+            .map(Key::Tuple),
         _ => None,
     }
 }
 
+pub(super) fn from_key(key: &Key) -> Value {
+    match key {
+        Key::Int(x) => Value::Int(*x),
+        Key::Bool(x) => Value::Bool(*x),
+        Key::Str(s) => Value::Str(Rc::clone(s)),
+        Key::Tuple(tuple) => Value::Tuple(tuple.iter().map(from_key).collect()),
+    }
+}
+
+// whether `value` is of the annotated type `ty`, the elements of a container
+// are checked too, only the arity of a closure is
+pub(super) fn has_type(value: &Value, ty: &ast::Type) -> bool {
+    match (value, ty.kind()) {
+        (_, ast::TypeKind::Any) => true,
+        (Value::Int(_), ast::TypeKind::Int) => true,
+        (Value::Bool(_), ast::TypeKind::Bool) => true,
+        (Value::Str(_), ast::TypeKind::Str) => true,
+        (Value::Seq(seq), ast::TypeKind::Seq(elem)) => seq.iter().all(|v| has_type(v, elem)),
+        (Value::Map(map), ast::TypeKind::Map(key, value)) => map
+            .iter()
+            .all(|(k, v)| has_type(&from_key(k), key) && has_type(v, value)),
+        (Value::Set(set), ast::TypeKind::Set(elem)) => {
+            set.iter().all(|k| has_type(&from_key(k), elem))
+        }
+        (Value::Tuple(tuple), ast::TypeKind::Tuple(elems)) => {
+            tuple.len() == elems.len() && std::iter::zip(tuple.iter(), elems.iter()).all(|(v, t)| has_type(v, t))
+        }
+        (Value::Closure(closure), ast::TypeKind::Fn(params, _)) => {
+            closure.f.parameters.len() == params.len()
+        }
+        (Value::Builtin(_), ast::TypeKind::Fn(..)) => true,
+        _ => false,
+    }
+}
+
+// entries in `right` win if both maps have the same key
+pub(super) fn merge(mut left: Rc<Map>, right: Rc<Map>) -> Rc<Map> {
+    if right.is_empty() {
+        return left;
+    }
+
+    if left.is_empty() {
+        return right;
+    }
+
+    let map = Rc::make_mut(&mut left);
+    for (k, v) in right.iter() {
+        map.insert(k.clone(), v.clone());
+    }
+
+    left
+}
+
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -43,6 +112,11 @@ impl fmt::Debug for Value {
             Value::Str(v) => v.fmt(f),
             Value::Seq(seq) => f.debug_list().entries(seq.iter()).finish(),
             Value::Map(map) => f.debug_map().entries(map.iter()).finish(),
+            Value::Set(set) => {
+                f.write_str("#")?;
+                f.debug_set().entries(set.iter()).finish()
+            }
+            Value::Tuple(tuple) => debug_tuple(f, tuple),
             Value::Closure(_) => f.write_str("<closure>"),
             Value::Builtin(_) => f.write_str("<builtin>"),
         }
@@ -55,10 +129,20 @@ impl fmt::Debug for Key {
             Key::Int(v) => v.fmt(f),
             Key::Bool(v) => v.fmt(f),
             Key::Str(v) => v.fmt(f),
+            Key::Tuple(tuple) => debug_tuple(f, tuple),
         }
     }
 }
 
+// `(1, 2)`, or `(1,)` with a single element
+fn debug_tuple<T: fmt::Debug>(f: &mut fmt::Formatter<'_>, elements: &[T]) -> fmt::Result {
+    let mut tuple = f.debug_tuple("");
+    for element in elements {
+        tuple.field(element);
+    }
+    tuple.finish()
+}
+
 impl std::cmp::PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -68,7 +152,9 @@ impl std::cmp::PartialEq for Value {
             (Self::Str(left), Self::Str(right)) => left == right,
             (Self::Seq(left), Self::Seq(right)) => left == right,
             (Self::Map(left), Self::Map(right)) => left == right,
-            (Self::Builtin(left), Self::Builtin(right)) => left == right,
+            (Self::Set(left), Self::Set(right)) => left == right,
+            (Self::Tuple(left), Self::Tuple(right)) => left == right,
+            (Self::Builtin(left), Self::Builtin(right)) => std::ptr::fn_addr_eq(*left, *right),
             (Self::Closure(left), Self::Closure(right)) => Rc::ptr_eq(left, right),
             _ => false,
         }
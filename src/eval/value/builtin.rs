@@ -1,86 +1,293 @@
-use std::rc::Rc;
-
-use crate::eval::{self, Value};
-
-pub(crate) type Builtin = fn(usize, Vec<Value>) -> eval::Result<Value>;
-
-pub(crate) fn all_() -> [(&'static str, Builtin); 7] {
-    [
-        ("len", len),
-        ("head", head),
-        ("tail", tail),
-        ("append", append),
-        ("print", print),
-        ("assert_eq", assert_eq),
-        ("type", type_),
-    ]
+mod map;
+mod seq;
+
+use crate::{
+    embed::Cap,
+    eval::{self, Env, Value},
+    span::Span,
+};
+
+pub(crate) type Builtin = fn(&mut Env, Span, Vec<Value>) -> eval::Result<Value>;
+
+// a builtin with what's needed to add it to an env and check its calls
+pub(crate) struct Entry {
+    pub(crate) name: &'static str,
+    pub(crate) f: Builtin,
+    // the group of builtins it's added with
+    pub(crate) cap: Cap,
+    // e.g. `fn([T]) -> T`, several if it accepts different types or numbers
+    // of args
+    pub(crate) signatures: &'static [&'static str],
 }
 
-// fn(str) -> int
-// fn(seq) -> int
-fn len(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
-    args!(args @ pos = value);
+pub(crate) static ALL: [Entry; 31] = [
+    Entry {
+        name: "len",
+        f: len,
+        cap: Cap::CORE,
+        signatures: &[
+            "fn(str) -> int",
+            "fn(seq) -> int",
+            "fn(map) -> int",
+            "fn(set) -> int",
+            "fn(tuple) -> int",
+        ],
+    },
+    Entry {
+        name: "head",
+        f: head,
+        cap: Cap::CORE,
+        signatures: &["fn([T]) -> T"],
+    },
+    Entry {
+        name: "tail",
+        f: tail,
+        cap: Cap::CORE,
+        signatures: &["fn([T]) -> [T]"],
+    },
+    Entry {
+        name: "append",
+        f: append,
+        cap: Cap::CORE,
+        signatures: &["fn([T], T...) -> [T]"],
+    },
+    Entry {
+        name: "print",
+        f: print,
+        cap: Cap::IO,
+        signatures: &["fn(T...)"],
+    },
+    Entry {
+        name: "assert_eq",
+        f: assert_eq,
+        cap: Cap::CORE,
+        signatures: &["fn(T, T)"],
+    },
+    Entry {
+        name: "type",
+        f: type_,
+        cap: Cap::CORE,
+        signatures: &["fn(T) -> str"],
+    },
+    Entry {
+        name: "map",
+        f: seq::map,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], fn(T) -> U) -> [U]"],
+    },
+    Entry {
+        name: "filter",
+        f: seq::filter,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], fn(T) -> bool) -> [T]"],
+    },
+    Entry {
+        name: "reduce",
+        f: seq::reduce,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], U, fn(U, T) -> U) -> U"],
+    },
+    Entry {
+        name: "any",
+        f: seq::any,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], fn(T) -> bool) -> bool"],
+    },
+    Entry {
+        name: "all",
+        f: seq::all,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], fn(T) -> bool) -> bool"],
+    },
+    Entry {
+        name: "find",
+        f: seq::find,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], fn(T) -> bool) -> T"],
+    },
+    Entry {
+        name: "sort",
+        f: seq::sort,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([int]) -> [int]", "fn([str]) -> [str]"],
+    },
+    Entry {
+        name: "sort_by",
+        f: seq::sort_by,
+        cap: Cap::COLLECTIONS,
+        signatures: &[
+            "fn([T], fn(T) -> int) -> [T]",
+            "fn([T], fn(T) -> str) -> [T]",
+        ],
+    },
+    Entry {
+        name: "reverse",
+        f: seq::reverse,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T]) -> [T]"],
+    },
+    Entry {
+        name: "zip",
+        f: seq::zip,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], [U]) -> [[T, U]]"],
+    },
+    Entry {
+        name: "enumerate",
+        f: seq::enumerate,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T]) -> [[int, T]]"],
+    },
+    Entry {
+        name: "range",
+        f: seq::range,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn(int) -> [int]", "fn(int, int) -> [int]"],
+    },
+    Entry {
+        name: "flat_map",
+        f: seq::flat_map,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([T], fn(T) -> [U]) -> [U]"],
+    },
+    Entry {
+        name: "sum",
+        f: seq::sum,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([int]) -> int"],
+    },
+    Entry {
+        name: "min",
+        f: seq::min,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([int]) -> int", "fn([str]) -> str"],
+    },
+    Entry {
+        name: "max",
+        f: seq::max,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([int]) -> int", "fn([str]) -> str"],
+    },
+    Entry {
+        name: "keys",
+        f: map::keys,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}) -> [K]"],
+    },
+    Entry {
+        name: "values",
+        f: map::values,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}) -> [V]"],
+    },
+    Entry {
+        name: "entries",
+        f: map::entries,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}) -> [[K, V]]"],
+    },
+    Entry {
+        name: "has",
+        f: map::has,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}, K) -> bool", "fn(#{K}, K) -> bool"],
+    },
+    Entry {
+        name: "insert",
+        f: map::insert,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}, K, V) -> {K: V}"],
+    },
+    Entry {
+        name: "remove",
+        f: map::remove,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}, K) -> {K: V}"],
+    },
+    Entry {
+        name: "merge",
+        f: map::merge,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn({K: V}, {K: V}) -> {K: V}"],
+    },
+    Entry {
+        name: "from_entries",
+        f: map::from_entries,
+        cap: Cap::COLLECTIONS,
+        signatures: &["fn([[K, V]]) -> {K: V}"],
+    },
+];
+
+// signatures of a builtin, empty if `name` is not a builtin
+pub(crate) fn signatures(name: &str) -> &'static [&'static str] {
+    ALL.iter()
+        .find(|entry| entry.name == name)
+        .map_or(&[], |entry| entry.signatures)
+}
+
+fn len(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = value);
 
     match value {
         Value::Str(s) => Ok(Value::Int(s.len().try_into().expect("len as i32"))),
         Value::Seq(seq) => Ok(Value::Int(seq.len().try_into().expect("len as i32"))),
         Value::Map(map) => Ok(Value::Int(map.len().try_into().expect("len as i32"))),
+        Value::Set(set) => Ok(Value::Int(set.len().try_into().expect("len as i32"))),
+        Value::Tuple(tuple) => Ok(Value::Int(tuple.len().try_into().expect("len as i32"))),
         _ => Err(eval::Error::ArgType {
-            pos,
+            span,
             supplied: value,
-            expected: "seq | str | map",
+            expected: "seq | str | map | set | tuple".into(),
         }),
     }
 }
 
-// fn([T]) -> T
-fn head(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
-    args!(args @ pos = value);
+fn head(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = value);
 
     match value {
         Value::Seq(seq) => match seq.first() {
             Some(x) => Ok(x.clone()),
             None => Err(eval::Error::ArgValue {
-                pos,
+                span,
                 message: "call head() with an empty seq",
             }),
         },
         _ => Err(eval::Error::ArgType {
-            pos,
+            span,
             supplied: value,
-            expected: "seq",
+            expected: "seq".into(),
         }),
     }
 }
 
-// fn([T]) -> [T]
-fn tail(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
-    args!(args @ pos = value);
+fn tail(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = value);
 
     match value {
-        Value::Seq(seq) => match seq.split_first() {
-            Some((_, tail)) => Ok(tail.to_vec()).map(Rc::from).map(Value::Seq),
+        Value::Seq(seq) => match seq.tail() {
+            Some(tail) => Ok(Value::Seq(tail)),
             None => Err(eval::Error::ArgValue {
-                pos,
+                span,
                 message: "call tail() with an empty seq",
             }),
         },
 
         _ => Err(eval::Error::ArgType {
-            pos,
+            span,
             supplied: value,
-            expected: "Seq",
+            expected: "Seq".into(),
         }),
     }
 }
 
-// fn([T], T...) -> [T]
-fn append(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
+fn append(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
     if args.len() < 2 {
         return Err(eval::Error::ArgCount {
-            pos,
+            span,
             supplied: args.len(),
-            expected: 2, // TODO: 2 or more
+            expected: eval::Arity::at_least(2),
+            defined: None,
         });
     }
 
@@ -88,48 +295,47 @@ fn append(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
 
     match first {
         Value::Seq(seq) => {
-            let mut new_seq = Vec::with_capacity(seq.len() + tail.len());
+            env.memory().allocate_values(span, tail.len())?;
+            let mut new_seq = seq.clone();
 
-            new_seq.extend(seq.iter().cloned());
-            new_seq.extend(tail.iter().cloned());
+            for x in tail {
+                new_seq.push_back(x.clone());
+            }
 
-            Ok(Value::Seq(Rc::from(new_seq)))
+            Ok(Value::Seq(new_seq))
         }
         other => Err(eval::Error::ArgType {
-            pos,
+            span,
             supplied: other.clone(),
-            expected: "append(seq, T...)",
+            expected: "append(seq, T...)".into(),
         }),
     }
 }
 
-// fn(T...)
-fn print(_pos: usize, args: Vec<Value>) -> eval::Result<Value> {
+fn print(env: &mut Env, _span: Span, args: Vec<Value>) -> eval::Result<Value> {
     if args.is_empty() {
-        println!()
+        env.print(format_args!(""));
     }
 
     for arg in args {
-        println!("{:?}", arg);
+        env.print(format_args!("{:?}", arg));
     }
 
     Ok(Value::Unit)
 }
 
-// fn(T, T)
-fn assert_eq(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
-    args!(args @ pos = left, right);
+fn assert_eq(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = left, right);
 
     if left == right {
         Ok(Value::Unit)
     } else {
-        Err(eval::Error::AssertEq { pos, left, right })
+        Err(eval::Error::AssertEq { span, left, right })
     }
 }
 
-// fn(T) -> str
-fn type_(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
-    args!(args @ pos = arg);
+fn type_(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = arg);
 
     let name = match arg {
         Value::Unit => "unit",
@@ -138,9 +344,22 @@ fn type_(pos: usize, args: Vec<Value>) -> eval::Result<Value> {
         Value::Str(_) => "str",
         Value::Seq(_) => "seq",
         Value::Map(_) => "map",
+        Value::Set(_) => "set",
+        Value::Tuple(_) => "tuple",
         Value::Builtin(_) => "builtin",
         Value::Closure(_) => "closure",
     };
 
     Ok(Value::Str(name.into()))
 }
+
+#[test]
+fn all_signatures() {
+    for entry in ALL.iter() {
+        assert!(
+            !entry.signatures.is_empty(),
+            "no signature for {}",
+            entry.name
+        );
+    }
+}
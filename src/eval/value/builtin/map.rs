@@ -0,0 +1,158 @@
+use std::rc::Rc;
+
+use crate::{
+    eval::{
+        self, Env, Value,
+        value::{self, Key, Map},
+    },
+    span::Span,
+};
+
+pub(super) fn keys(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = map);
+
+    let map = expect_map(span, map)?;
+    env.memory().allocate_values(span, map.len())?;
+
+    let keys = map.keys().map(value::from_key).collect::<Vec<_>>();
+
+    Ok(Value::Seq(keys.into()))
+}
+
+pub(super) fn values(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = map);
+
+    let map = expect_map(span, map)?;
+    env.memory().allocate_values(span, map.len())?;
+
+    let values = map.values().cloned().collect::<Vec<_>>();
+
+    Ok(Value::Seq(values.into()))
+}
+
+pub(super) fn entries(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = map);
+
+    let map = expect_map(span, map)?;
+    // a pair for each entry
+    env.memory().allocate_values(span, map.len() * 3)?;
+
+    let entries = map
+        .iter()
+        .map(|(k, v)| Value::Seq([value::from_key(k), v.clone()].into()))
+        .collect::<Vec<_>>();
+
+    Ok(Value::Seq(entries.into()))
+}
+
+pub(super) fn has(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = container, key);
+
+    let key = expect_key(span, key)?;
+
+    match container {
+        Value::Map(map) => Ok(Value::Bool(map.contains_key(&key))),
+        Value::Set(set) => Ok(Value::Bool(set.contains(&key))),
+        other => Err(eval::Error::ArgType {
+            span,
+            supplied: other,
+            expected: "map | set".into(),
+        }),
+    }
+}
+
+// the original map is left untouched
+pub(super) fn insert(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = map, key, value);
+
+    let mut map = expect_map(span, map)?;
+    let key = expect_key(span, key)?;
+    env.memory().allocate_values(span, 2)?;
+
+    Rc::make_mut(&mut map).insert(key, value);
+
+    Ok(Value::Map(map))
+}
+
+// the original map is left untouched
+pub(super) fn remove(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = map, key);
+
+    let mut map = expect_map(span, map)?;
+    let key = expect_key(span, key)?;
+
+    if map.contains_key(&key) {
+        Rc::make_mut(&mut map).remove(&key);
+    }
+
+    Ok(Value::Map(map))
+}
+
+// entries in the second map win
+pub(super) fn merge(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = left, right);
+
+    let left = expect_map(span, left)?;
+    let right = expect_map(span, right)?;
+    env.memory().allocate_values(span, right.len() * 2)?;
+
+    Ok(Value::Map(value::merge(left, right)))
+}
+
+pub(super) fn from_entries(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = entries);
+
+    let entries = match entries {
+        Value::Seq(entries) => entries,
+        other => {
+            return Err(eval::Error::ArgType {
+                span,
+                supplied: other,
+                expected: "[[K, V]]".into(),
+            });
+        }
+    };
+
+    env.memory().allocate_values(span, entries.len() * 2)?;
+
+    let mut map = Map::default();
+    for entry in entries.iter() {
+        match entry {
+            Value::Seq(pair) if pair.len() == 2 => {
+                let key = expect_key(span, pair[0].clone())?;
+                map.insert(key, pair[1].clone());
+            }
+            other => {
+                return Err(eval::Error::ArgType {
+                    span,
+                    supplied: other.clone(),
+                    expected: "[K, V]".into(),
+                });
+            }
+        }
+    }
+
+    Ok(Value::Map(Rc::new(map)))
+}
+
+fn expect_map(span: Span, value: Value) -> eval::Result<Rc<Map>> {
+    match value {
+        Value::Map(map) => Ok(map),
+        other => Err(eval::Error::ArgType {
+            span,
+            supplied: other,
+            expected: "map".into(),
+        }),
+    }
+}
+
+fn expect_key(span: Span, value: Value) -> eval::Result<Key> {
+    match value::to_key(&value) {
+        Some(key) => Ok(key),
+        None => Err(eval::Error::ArgType {
+            span,
+            supplied: value,
+            expected: "int | bool | str | tuple as map key".into(),
+        }),
+    }
+}
@@ -0,0 +1,337 @@
+use std::cmp::Ordering;
+
+use crate::{
+    eval::{self, Env, Value, value::Seq},
+    span::Span,
+};
+
+pub(super) fn map(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, f);
+
+    let seq = expect_seq(span, seq)?;
+    env.memory().allocate_values(span, seq.len())?;
+
+    let mut new_seq = Vec::with_capacity(seq.len());
+    for x in seq.iter() {
+        new_seq.push(eval::call_value(env, span, f.clone(), vec![x.clone()])?);
+    }
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+pub(super) fn filter(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, predicate);
+
+    let seq = expect_seq(span, seq)?;
+
+    let mut new_seq = Vec::new();
+    for x in seq.iter() {
+        if test(env, span, &predicate, x)? {
+            env.memory().allocate_values(span, 1)?;
+            new_seq.push(x.clone());
+        }
+    }
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+pub(super) fn reduce(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, init, f);
+
+    let seq = expect_seq(span, seq)?;
+
+    let mut acc = init;
+    for x in seq.iter() {
+        acc = eval::call_value(env, span, f.clone(), vec![acc, x.clone()])?;
+    }
+
+    Ok(acc)
+}
+
+pub(super) fn any(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, predicate);
+
+    for x in expect_seq(span, seq)?.iter() {
+        if test(env, span, &predicate, x)? {
+            return Ok(Value::Bool(true));
+        }
+    }
+
+    Ok(Value::Bool(false))
+}
+
+pub(super) fn all(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, predicate);
+
+    for x in expect_seq(span, seq)?.iter() {
+        if !test(env, span, &predicate, x)? {
+            return Ok(Value::Bool(false));
+        }
+    }
+
+    Ok(Value::Bool(true))
+}
+
+// return unit if no element is found
+pub(super) fn find(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, predicate);
+
+    for x in expect_seq(span, seq)?.iter() {
+        if test(env, span, &predicate, x)? {
+            return Ok(x.clone());
+        }
+    }
+
+    Ok(Value::Unit)
+}
+
+pub(super) fn sort(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq);
+
+    let seq = expect_seq(span, seq)?;
+    env.memory().allocate_values(span, seq.len())?;
+
+    let mut new_seq = seq.iter().cloned().collect::<Vec<_>>();
+
+    try_sort_by(&mut new_seq, |left, right| compare(span, left, right))?;
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+// stable, elements are ordered by the key returned from the callback
+pub(super) fn sort_by(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, f);
+
+    let seq = expect_seq(span, seq)?;
+    // the keys and the sorted seq
+    env.memory().allocate_values(span, seq.len() * 2)?;
+
+    let mut keyed = Vec::with_capacity(seq.len());
+    for x in seq.iter() {
+        let key = eval::call_value(env, span, f.clone(), vec![x.clone()])?;
+        keyed.push((key, x.clone()));
+    }
+
+    try_sort_by(&mut keyed, |(left, _), (right, _)| {
+        compare(span, left, right)
+    })?;
+
+    let new_seq = keyed.into_iter().map(|(_, x)| x).collect::<Vec<_>>();
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+pub(super) fn reverse(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq);
+
+    let seq = expect_seq(span, seq)?;
+    env.memory().allocate_values(span, seq.len())?;
+
+    let new_seq = seq.iter().rev().cloned().collect::<Vec<_>>();
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+// stop at the end of the shorter one
+pub(super) fn zip(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = left, right);
+
+    let left = expect_seq(span, left)?;
+    let right = expect_seq(span, right)?;
+    // a pair for each element
+    env.memory()
+        .allocate_values(span, left.len().min(right.len()) * 3)?;
+
+    let new_seq = std::iter::zip(left.iter(), right.iter())
+        .map(|(x, y)| Value::Seq([x.clone(), y.clone()].into()))
+        .collect::<Vec<_>>();
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+pub(super) fn enumerate(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq);
+
+    let seq = expect_seq(span, seq)?;
+    // a pair for each element
+    env.memory().allocate_values(span, seq.len() * 3)?;
+
+    let new_seq = seq
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let i = Value::Int(i.try_into().expect("index as i32"));
+            Value::Seq([i, x.clone()].into())
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+// `range(end)` is `range(0, end)`, `end` is exclusive
+pub(super) fn range(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    let (start, end) = match args.as_slice() {
+        [Value::Int(end)] => (0, *end),
+        [Value::Int(start), Value::Int(end)] => (*start, *end),
+        [_] | [_, _] => {
+            let other = args.into_iter().find(|x| !matches!(x, Value::Int(_)));
+            return Err(eval::Error::ArgType {
+                span,
+                supplied: other.unwrap(),
+                expected: "int".into(),
+            });
+        }
+        _ => {
+            return Err(eval::Error::ArgCount {
+                span,
+                supplied: args.len(),
+                expected: eval::Arity::between(1, 2),
+                defined: None,
+            });
+        }
+    };
+
+    let len = (i64::from(end) - i64::from(start)).max(0);
+    env.memory()
+        .allocate_values(span, len.try_into().unwrap_or(usize::MAX))?;
+
+    Ok(Value::Seq((start..end).map(Value::Int).collect()))
+}
+
+pub(super) fn flat_map(env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq, f);
+
+    let seq = expect_seq(span, seq)?;
+
+    let mut new_seq = Vec::with_capacity(seq.len());
+    for x in seq.iter() {
+        let ys = eval::call_value(env, span, f.clone(), vec![x.clone()])?;
+        let ys = expect_seq(span, ys)?;
+        env.memory().allocate_values(span, ys.len())?;
+        new_seq.extend(ys.iter().cloned());
+    }
+
+    Ok(Value::Seq(new_seq.into()))
+}
+
+pub(super) fn sum(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq);
+
+    let mut sum = 0;
+    for x in expect_seq(span, seq)?.iter() {
+        match x {
+            Value::Int(x) => sum += x,
+            other => {
+                return Err(eval::Error::ArgType {
+                    span,
+                    supplied: other.clone(),
+                    expected: "int".into(),
+                });
+            }
+        }
+    }
+
+    Ok(Value::Int(sum))
+}
+
+pub(super) fn min(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq);
+
+    extremum(span, seq, Ordering::Less, "call min() with an empty seq")
+}
+
+pub(super) fn max(_env: &mut Env, span: Span, args: Vec<Value>) -> eval::Result<Value> {
+    args!(args @ span = seq);
+
+    extremum(span, seq, Ordering::Greater, "call max() with an empty seq")
+}
+
+fn extremum(
+    span: Span,
+    seq: Value,
+    wanted: Ordering,
+    empty_message: &'static str,
+) -> eval::Result<Value> {
+    let seq = expect_seq(span, seq)?;
+
+    let mut iter = seq.iter();
+
+    let mut found = iter.next().ok_or(eval::Error::ArgValue {
+        span,
+        message: empty_message,
+    })?;
+
+    for x in iter {
+        if compare(span, x, found)? == wanted {
+            found = x;
+        }
+    }
+
+    Ok(found.clone())
+}
+
+fn expect_seq(span: Span, value: Value) -> eval::Result<Seq> {
+    match value {
+        Value::Seq(seq) => Ok(seq),
+        other => Err(eval::Error::ArgType {
+            span,
+            supplied: other,
+            expected: "seq".into(),
+        }),
+    }
+}
+
+// call `predicate` with `x`, which must return a bool
+fn test(env: &mut Env, span: Span, predicate: &Value, x: &Value) -> eval::Result<bool> {
+    match eval::call_value(env, span, predicate.clone(), vec![x.clone()])? {
+        Value::Bool(b) => Ok(b),
+        other => Err(eval::Error::ReturnType {
+            span,
+            supplied: other,
+            expected: "bool".into(),
+        }),
+    }
+}
+
+fn compare(span: Span, left: &Value, right: &Value) -> eval::Result<Ordering> {
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => Ok(left.cmp(right)),
+        (Value::Str(left), Value::Str(right)) => Ok(left.cmp(right)),
+        (Value::Int(_), other) => Err(eval::Error::ArgType {
+            span,
+            supplied: other.clone(),
+            expected: "int".into(),
+        }),
+        (Value::Str(_), other) => Err(eval::Error::ArgType {
+            span,
+            supplied: other.clone(),
+            expected: "str".into(),
+        }),
+        (other, _) => Err(eval::Error::ArgType {
+            span,
+            supplied: other.clone(),
+            expected: "int | str".into(),
+        }),
+    }
+}
+
+// stable sort with a comparison which may fail, the first error is returned
+fn try_sort_by<T, F>(xs: &mut [T], mut compare: F) -> eval::Result<()>
+where
+    F: FnMut(&T, &T) -> eval::Result<Ordering>,
+{
+    let mut error = None;
+
+    xs.sort_by(|left, right| match compare(left, right) {
+        Ok(ordering) => ordering,
+        Err(err) => {
+            error.get_or_insert(err);
+            Ordering::Equal
+        }
+    });
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
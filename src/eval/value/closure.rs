@@ -2,46 +2,49 @@ use std::{collections::HashSet, rc::Rc};
 
 use crate::{
     eval::Value,
-    parse::ast::{self, Ident},
+    parse::{
+        ast::{self, Ident},
+        visit::{self, Visitor},
+    },
 };
 
 pub(crate) struct Closure {
     pub(crate) f: Rc<ast::Closure>,
-    pub(crate) captured: Box<[(ast::Ident, Value)]>,
-    pub(crate) undefined: Vec<ast::Ident>,
-    pub(crate) recursive: Option<Ident>,
+    // values of the captures by upvalue, read by the calls to the closure,
+    // `None` for the ones not bound when the closure is created, which are
+    // looked up in the globals instead
+    pub(crate) captured: Box<[Option<Value>]>,
+    // upvalue of the closure itself if it's bound by a `let` to one of them
+    pub(crate) recursive: Option<usize>,
+    // indices of the globals of the code the closure is in, see `Env::link()`
+    pub(crate) globals: Rc<[usize]>,
 }
 
 impl Closure {
     pub(crate) fn new(f: Rc<ast::Closure>, env: &crate::eval::Env) -> Self {
-        let unbounded = analyze_unbounded(&f);
-
-        let mut undefined = Vec::new();
-        let mut captured = Vec::<(Ident, Value)>::with_capacity(unbounded.len());
-        for ident in unbounded {
-            if let Some(value) = env.get(ident.sym()) {
-                captured.push((ident.clone(), value.clone()));
-            } else {
-                undefined.push(ident.clone());
-            }
-        }
+        let captured = f
+            .layout
+            .captures
+            .iter()
+            .map(|ident| env.get(ident).cloned())
+            .collect();
 
         Self {
             f,
-            captured: captured.into_boxed_slice(),
-            undefined,
+            captured,
             recursive: None,
+            globals: Rc::clone(env.current_link()),
         }
     }
 }
 
-fn analyze_unbounded(f: &ast::Closure) -> Vec<&Ident> {
+pub(crate) fn analyze_unbounded(f: &ast::Closure) -> Vec<&Ident> {
     let mut env = AnalyzeEnv {
         scopes: vec![f.parameters.iter().map(|ident| ident.sym()).collect()],
         unbounded: Default::default(),
     };
 
-    env.walk_block(&f.body);
+    env.visit_block(&f.body);
 
     env.unbounded
 }
@@ -84,74 +87,22 @@ impl<'a> AnalyzeEnv<'a> {
     }
 }
 
-impl<'a> AnalyzeEnv<'a> {
-    fn walk_block(&mut self, block: &'a ast::Block) {
-        self.with(|env| {
-            for node in block.nodes.iter() {
-                match node {
-                    ast::Node::Expr(expr) => env.walk_expr(expr),
-                    ast::Node::Stmt(stmt) => env.walk_stmt(stmt),
-                }
-            }
-        })
+// nested closures are skipped, they are analyzed on their own when created
+impl<'a> Visitor<'a> for AnalyzeEnv<'a> {
+    fn visit_block(&mut self, block: &'a ast::Block) {
+        self.with(|env| visit::walk_block(env, block))
     }
 
-    fn walk_stmt(&mut self, stmt: &'a ast::Stmt) {
-        match stmt {
-            ast::Stmt::Return(return_) => {
-                if let Some(ref expr) = return_.value {
-                    self.walk_expr(expr)
-                }
-            }
-            ast::Stmt::Let(let_) => {
-                self.walk_expr(&let_.value);
-                self.create_ident(&let_.ident);
-            }
-        }
+    fn visit_let(&mut self, let_: &'a ast::Let) {
+        visit::walk_let(self, let_);
+        self.create_ident(&let_.ident);
     }
 
-    fn walk_expr(&mut self, expr: &'a ast::Expr) {
-        match expr {
-            ast::Expr::Int(_) | ast::Expr::Bool(_) | ast::Expr::Str(_) | ast::Expr::Closure(_) => {}
-            ast::Expr::Seq(seq) => {
-                for expr in seq.elements.iter() {
-                    self.walk_expr(expr);
-                }
-            }
-            ast::Expr::Ident(ident) => self.access_ident(ident),
-            ast::Expr::Unary(expr) => self.walk_expr(&expr.value),
-            ast::Expr::Binary(expr) => {
-                self.walk_expr(&expr.left);
-                self.walk_expr(&expr.right);
-            }
-            ast::Expr::If(expr) => {
-                for (condition, consequence) in expr.conditioned.iter() {
-                    self.walk_expr(condition);
-                    self.walk_block(consequence);
-                }
-                if let Some(ref alternative) = expr.alternative {
-                    self.walk_block(alternative);
-                }
-            }
-            ast::Expr::Call(call) => {
-                self.walk_expr(&call.target);
-
-                for arg in call.args.iter() {
-                    self.walk_expr(arg);
-                }
-            }
-            ast::Expr::Map(map) => {
-                for (k, v) in map.entries.iter() {
-                    self.walk_expr(k);
-                    self.walk_expr(v);
-                }
-            }
-            ast::Expr::Index(index) => {
-                self.walk_expr(&index.base);
-                self.walk_expr(&index.subscript);
-            }
-        }
+    fn visit_ident(&mut self, ident: &'a Ident) {
+        self.access_ident(ident);
     }
+
+    fn visit_closure(&mut self, _: &'a ast::Closure) {}
 }
 
 #[test]
@@ -168,7 +119,7 @@ fn(y) {
 }
 ";
 
-    let lexer = crate::lex::Lexer::new(input.as_bytes().into());
+    let lexer = crate::lex::Lexer::new(input.as_bytes());
     let mut parser = crate::parse::Parser::new(lexer);
     let f = parser.next().unwrap().unwrap();
     assert!(parser.next().is_none());
@@ -0,0 +1,112 @@
+use crate::eval::value::{Key, Value};
+
+/// persistent map (HAMT) which remembers the insertion order of keys
+///
+/// iteration, printing and builtins like `keys()` follow the insertion order,
+/// so script output is stable between runs, while equality ignores the order.
+/// clone is O(1), `insert()`, `remove()` and `get()` are O(log n)
+#[derive(Clone, Default)]
+pub(crate) struct Map {
+    // insertion sequence number => entry
+    entries: im_rc::OrdMap<usize, (Key, Value)>,
+    index: im_rc::HashMap<Key, usize>,
+    next: usize,
+}
+
+impl Map {
+    pub(crate) fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub(crate) fn get(&self, key: &Key) -> Option<&Value> {
+        self.index
+            .get(key)
+            .and_then(|i| self.entries.get(i))
+            .map(|(_, v)| v)
+    }
+
+    pub(crate) fn contains_key(&self, key: &Key) -> bool {
+        self.index.contains_key(key)
+    }
+
+    // an existing key keeps its original position
+    pub(crate) fn insert(&mut self, key: Key, value: Value) {
+        match self.index.get(&key) {
+            Some(&i) => {
+                self.entries.insert(i, (key, value));
+            }
+            None => {
+                let i = self.next;
+                self.next += 1;
+
+                self.index.insert(key.clone(), i);
+                self.entries.insert(i, (key, value));
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &Key) -> Option<Value> {
+        let i = self.index.remove(key)?;
+
+        self.entries.remove(&i).map(|(_, value)| value)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        self.entries.values().map(|(k, v)| (k, v))
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.entries.values().map(|(k, _)| k)
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.values().map(|(_, v)| v)
+    }
+}
+
+impl std::cmp::PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl std::cmp::Eq for Map {}
+
+#[test]
+fn insertion_order() {
+    let mut map = Map::default();
+    map.insert(Key::Int(3), Value::Int(0));
+    map.insert(Key::Int(1), Value::Int(1));
+    map.insert(Key::Int(2), Value::Int(2));
+    map.insert(Key::Int(3), Value::Int(3));
+
+    assert_eq!(
+        format!("{:?}", Value::Map(map.clone().into())),
+        "{3: 3, 1: 1, 2: 2}"
+    );
+
+    assert_eq!(map.remove(&Key::Int(1)), Some(Value::Int(1)));
+    assert_eq!(map.remove(&Key::Int(1)), None);
+    assert_eq!(map.get(&Key::Int(2)), Some(&Value::Int(2)));
+    assert_eq!(format!("{:?}", Value::Map(map.into())), "{3: 3, 2: 2}");
+}
+
+#[test]
+fn order_insensitive_eq() {
+    let mut left = Map::default();
+    left.insert(Key::Int(1), Value::Int(1));
+    left.insert(Key::Bool(true), Value::Int(2));
+
+    let mut right = Map::default();
+    right.insert(Key::Bool(true), Value::Int(2));
+    right.insert(Key::Int(1), Value::Int(1));
+
+    assert!(left == right);
+
+    right.insert(Key::Int(1), Value::Int(0));
+    assert!(left != right);
+}
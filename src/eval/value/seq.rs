@@ -0,0 +1,131 @@
+use std::rc::Rc;
+
+use crate::eval::value::Value;
+
+/// persistent vector (RRB tree), clone is O(1) and shares the storage
+///
+/// `tail()`, `push_back()` and `get()` are O(log n), so the recursive list
+/// processing idiom in Monkey stays linear
+#[derive(Clone, Default)]
+pub(crate) struct Seq {
+    inner: Rc<im_rc::Vector<Value>>,
+}
+
+impl Seq {
+    pub(crate) fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub(crate) fn get(&self, i: usize) -> Option<&Value> {
+        self.inner.get(i)
+    }
+
+    pub(crate) fn first(&self) -> Option<&Value> {
+        self.inner.front()
+    }
+
+    // all but the first element, `None` if empty
+    pub(crate) fn tail(&self) -> Option<Seq> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Self::from_vector(self.inner.skip(1)))
+        }
+    }
+
+    pub(crate) fn iter(&self) -> im_rc::vector::Iter<'_, Value> {
+        self.inner.iter()
+    }
+
+    pub(crate) fn push_back(&mut self, value: Value) {
+        Rc::make_mut(&mut self.inner).push_back(value);
+    }
+
+    pub(crate) fn append(&mut self, other: Seq) {
+        if other.is_empty() {
+            return;
+        }
+
+        if self.is_empty() {
+            *self = other;
+            return;
+        }
+
+        let other = Rc::try_unwrap(other.inner).unwrap_or_else(|rc| (*rc).clone());
+        Rc::make_mut(&mut self.inner).append(other);
+    }
+
+    fn from_vector(vector: im_rc::Vector<Value>) -> Self {
+        Self {
+            inner: Rc::new(vector),
+        }
+    }
+}
+
+impl From<Vec<Value>> for Seq {
+    fn from(values: Vec<Value>) -> Self {
+        Self::from_vector(values.into())
+    }
+}
+
+impl<const N: usize> From<[Value; N]> for Seq {
+    fn from(values: [Value; N]) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl FromIterator<Value> for Seq {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Self::from_vector(iter.into_iter().collect())
+    }
+}
+
+impl std::ops::Index<usize> for Seq {
+    type Output = Value;
+
+    fn index(&self, i: usize) -> &Value {
+        &self.inner[i]
+    }
+}
+
+impl<'a> IntoIterator for &'a Seq {
+    type Item = &'a Value;
+    type IntoIter = im_rc::vector::Iter<'a, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl std::cmp::PartialEq for Seq {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner) || self.inner == other.inner
+    }
+}
+
+impl std::cmp::Eq for Seq {}
+
+#[test]
+fn shared_storage() {
+    let seq = Seq::from(vec![Value::Int(1), Value::Int(2)]);
+
+    let mut appended = seq.clone();
+    appended.push_back(Value::Int(3));
+
+    assert_eq!(seq.len(), 2);
+    assert_eq!(appended.len(), 3);
+    assert_eq!(appended.tail().unwrap().first(), Some(&Value::Int(2)));
+    assert!(appended
+        .tail()
+        .unwrap()
+        .tail()
+        .unwrap()
+        .tail()
+        .unwrap()
+        .tail()
+        .is_none());
+}
@@ -0,0 +1,52 @@
+use crate::eval::value::{Key, Map, Value};
+
+/// persistent set which remembers the insertion order of elements, see [`Map`]
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct Set {
+    map: Map,
+}
+
+impl Set {
+    pub(crate) fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub(crate) fn contains(&self, key: &Key) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub(crate) fn insert(&mut self, key: Key) {
+        self.map.insert(key, Value::Unit);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Key> {
+        self.map.keys()
+    }
+
+    // elements in either set
+    pub(crate) fn union(&self, other: &Set) -> Set {
+        let mut set = self.clone();
+        for key in other.iter() {
+            set.insert(key.clone());
+        }
+        set
+    }
+
+    // elements in both sets
+    pub(crate) fn intersection(&self, other: &Set) -> Set {
+        let mut set = Set::default();
+        for key in self.iter().filter(|key| other.contains(key)) {
+            set.insert(key.clone());
+        }
+        set
+    }
+
+    // elements in `self` but not in `other`
+    pub(crate) fn difference(&self, other: &Set) -> Set {
+        let mut set = Set::default();
+        for key in self.iter().filter(|key| !other.contains(key)) {
+            set.insert(key.clone());
+        }
+        set
+    }
+}
@@ -0,0 +1,472 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Write;
+
+use crate::{
+    embed::Error,
+    eval,
+    parse::ast::{self, BinaryOp, Expr, Node, Stmt},
+    span::Span,
+};
+
+const INDENT: &str = "    ";
+
+/// pretty-print `code` in the canonical style, the first syntax error is
+/// returned if any
+///
+/// - one statement per line, indented by 4 spaces
+/// - `let`, `return` and expression statements end with `;`, except the last
+///   expression of a block, which is its value
+/// - a block with a single expression stays on one line, e.g. `fn(x) { x + 1 }`
+/// - a `[...]`, `{...}` or `#{...}` literal is split into one element per line
+///   if it starts a new line after the opening bracket in `code`
+/// - at most one blank line is kept between statements
+///
+/// `#` comments are kept, a comment inside an expression is moved to the end
+/// of its statement
+///
+/// ```rust
+/// let code = b"let f = fn(x){x+1}\n\n\n# call it\nf( 2 )";
+///
+/// assert_eq!(
+///     oris::format(code).unwrap(),
+///     "let f = fn(x) { x + 1 };\n\n# call it\nf(2);\n"
+/// );
+/// ```
+pub fn format(code: &[u8]) -> Result<String, Error> {
+    let lexer = crate::lex::Lexer::with_cursor(code, 0);
+    let mut parser = crate::parse::Parser::new(lexer);
+
+    let nodes = parser
+        .by_ref()
+        .collect::<crate::parse::Result<Vec<_>>>()
+        .map_err(|error| Error::from(eval::Error::from(error)))?;
+
+    let mut printer = Printer {
+        code,
+        comments: parser.comments(),
+        out: String::new(),
+        indent: 0,
+    };
+
+    printer.lines(&nodes, code.len(), Node::span, |printer, node, _| {
+        printer.node(node);
+        if let Node::Expr(_) = node {
+            printer.out.push(';');
+        }
+    });
+
+    Ok(printer.out)
+}
+
+struct Printer<'a> {
+    code: &'a [u8],
+
+    // comments not printed yet
+    comments: &'a [Span],
+
+    out: String,
+    indent: usize,
+}
+
+impl Printer<'_> {
+    // one item per line at the current indentation, with the comments before
+    // `end`, e.g. the closing `}` of a block
+    //
+    // `print` is called with whether the item is the last one
+    fn lines<T, S, P>(&mut self, items: &[T], end: usize, span: S, mut print: P)
+    where
+        S: Fn(&T) -> Span,
+        P: FnMut(&mut Self, &T, bool),
+    {
+        let mut prev = None;
+
+        for (i, item) in items.iter().enumerate() {
+            let item_span = span(item);
+            self.comment_lines(item_span.start, &mut prev);
+
+            self.start_line(prev, item_span.start);
+            let last = i + 1 == items.len();
+            print(self, item, last);
+
+            let next = if last { end } else { span(&items[i + 1]).start };
+            self.trailing_comments(item_span.end, next);
+            self.out.push('\n');
+
+            prev = Some(item_span.end);
+        }
+
+        self.comment_lines(end, &mut prev);
+    }
+
+    // comments before `pos`, each on its own line
+    fn comment_lines(&mut self, pos: usize, prev: &mut Option<usize>) {
+        while let Some(&comment) = self.comments.first().filter(|c| c.start < pos) {
+            self.comments = &self.comments[1..];
+
+            self.start_line(*prev, comment.start);
+            self.comment(comment);
+            self.out.push('\n');
+
+            *prev = Some(comment.end);
+        }
+    }
+
+    // comments on the same line after an item ending at `end`, and the ones
+    // inside the item which are not printed yet, `next` is where the next
+    // item starts
+    fn trailing_comments(&mut self, end: usize, next: usize) {
+        let mut first = true;
+
+        while let Some(&comment) = self.comments.first() {
+            if comment.start >= next
+                || (comment.start >= end && self.code[end..comment.start].contains(&b'\n'))
+            {
+                break;
+            }
+            self.comments = &self.comments[1..];
+
+            if first {
+                self.out.push(' ');
+            } else {
+                self.out.push('\n');
+                self.push_indent();
+            }
+            self.comment(comment);
+
+            first = false;
+        }
+    }
+
+    fn has_comment(&self, span: Span) -> bool {
+        self.comments
+            .iter()
+            .take_while(|comment| comment.start < span.end)
+            .any(|comment| comment.start >= span.start)
+    }
+
+    fn comment(&mut self, comment: Span) {
+        let text = String::from_utf8_lossy(&self.code[comment.start..comment.end]);
+        self.out.push_str(text.trim_end());
+    }
+
+    // indent a new line, after a blank line if there's any between `prev`
+    // and `start` in the code
+    fn start_line(&mut self, prev: Option<usize>, start: usize) {
+        if let Some(prev) = prev {
+            let line_breaks = self.code[prev..start].iter().filter(|b| **b == b'\n');
+            if line_breaks.count() > 1 {
+                self.out.push('\n');
+            }
+        }
+
+        self.push_indent();
+    }
+
+    fn push_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+}
+
+impl Printer<'_> {
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Expr(expr) => self.expr(expr, true),
+            Node::Stmt(Stmt::Let(let_)) => {
+                self.out.push_str("let ");
+                self.out.push_str(let_.ident.sym());
+                if let Some(ref ty) = let_.ty {
+                    write!(self.out, ": {}", ty).unwrap();
+                }
+                self.out.push_str(" = ");
+                self.expr(&let_.value, true);
+                self.out.push(';');
+            }
+            Node::Stmt(Stmt::Return(return_)) => {
+                self.out.push_str("return");
+                if let Some(ref value) = return_.value {
+                    self.out.push(' ');
+                    self.expr(value, true);
+                }
+                self.out.push(';');
+            }
+        }
+    }
+
+    // `tail` is whether nothing of the enclosing expression follows `expr`,
+    // a unary operator takes all the rest of the expression as its operand
+    fn expr(&mut self, expr: &Expr, tail: bool) {
+        match expr {
+            Expr::Int(int) => write!(self.out, "{}", int.value).unwrap(),
+            Expr::Bool(bool) => write!(self.out, "{}", bool.value).unwrap(),
+            Expr::Str(str) => write!(self.out, "\"{}\"", str.value()).unwrap(),
+            Expr::Ident(ident) => self.out.push_str(ident.sym()),
+            Expr::Seq(seq) => self.elements(
+                "[",
+                "]",
+                seq.span,
+                &seq.elements,
+                Expr::span,
+                |printer, expr| printer.expr(expr, true),
+            ),
+            Expr::Map(map) => self.elements(
+                "{",
+                "}",
+                map.span,
+                &map.entries,
+                |(key, value)| key.span().to(value.span()),
+                |printer, (key, value)| {
+                    printer.expr(key, true);
+                    printer.out.push_str(": ");
+                    printer.expr(value, true);
+                },
+            ),
+            Expr::Set(set) => self.elements(
+                "#{",
+                "}",
+                set.span,
+                &set.elements,
+                Expr::span,
+                |printer, expr| printer.expr(expr, true),
+            ),
+            Expr::Tuple(tuple) => {
+                self.out.push('(');
+                self.list(&tuple.elements);
+                if tuple.elements.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            Expr::Index(index) => {
+                self.operand(&index.base, is_operator(&index.base), false);
+                self.out.push('[');
+                self.expr(&index.subscript, true);
+                self.out.push(']');
+            }
+            Expr::Call(call) => {
+                self.operand(&call.target, is_operator(&call.target), false);
+                self.out.push('(');
+                self.list(&call.args);
+                self.out.push(')');
+            }
+            Expr::Unary(unary) => {
+                write!(self.out, "{}", unary.op).unwrap();
+                let parens = matches!(unary.value, Expr::Binary(_));
+                self.operand(&unary.value, parens, tail);
+            }
+            Expr::Binary(binary) => {
+                let precedence = precedence(binary.op);
+
+                let parens = match binary.left {
+                    Expr::Binary(ref left) => self::precedence(left.op) < precedence,
+                    Expr::Unary(_) => true,
+                    _ => false,
+                };
+                self.operand(&binary.left, parens, false);
+
+                write!(self.out, " {} ", binary.op).unwrap();
+
+                let parens = match binary.right {
+                    Expr::Binary(ref right) => self::precedence(right.op) <= precedence,
+                    Expr::Unary(_) => !tail,
+                    _ => false,
+                };
+                self.operand(&binary.right, parens, tail);
+            }
+            Expr::Closure(closure) => {
+                self.out.push_str("fn(");
+                let parameters = std::iter::zip(&*closure.parameters, &*closure.parameter_types);
+                for (i, (parameter, ty)) in parameters.enumerate() {
+                    if i != 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(parameter.sym());
+                    if let Some(ty) = ty {
+                        write!(self.out, ": {}", ty).unwrap();
+                    }
+                }
+                self.out.push(')');
+                if let Some(ref ret) = closure.ret {
+                    write!(self.out, " -> {}", ret).unwrap();
+                }
+                self.out.push(' ');
+                self.block(&closure.body);
+            }
+            Expr::If(if_) => self.if_(if_),
+        }
+    }
+
+    fn operand(&mut self, expr: &Expr, parens: bool, tail: bool) {
+        if parens {
+            self.out.push('(');
+            self.expr(expr, true);
+            self.out.push(')');
+        } else {
+            self.expr(expr, tail);
+        }
+    }
+
+    // `a, b, c` on one line
+    fn list(&mut self, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i != 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr, true);
+        }
+    }
+
+    // elements of a seq, map or set literal, one per line if the first
+    // element doesn't start on the line of `open` or there's a comment
+    // inside
+    fn elements<T, S, P>(
+        &mut self,
+        open: &str,
+        close: &str,
+        span: Span,
+        items: &[T],
+        item_span: S,
+        print: P,
+    ) where
+        S: Fn(&T) -> Span,
+        P: Fn(&mut Self, &T),
+    {
+        self.out.push_str(open);
+
+        let multiline = self.has_comment(span)
+            || items
+                .first()
+                .is_some_and(|item| self.code[span.start..item_span(item).start].contains(&b'\n'));
+
+        if multiline {
+            self.out.push('\n');
+            self.indent += 1;
+            self.lines(
+                items,
+                span.end - close.len(),
+                item_span,
+                |printer, item, last| {
+                    print(printer, item);
+                    if !last {
+                        printer.out.push(',');
+                    }
+                },
+            );
+            self.indent -= 1;
+            self.push_indent();
+        } else {
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    self.out.push_str(", ");
+                }
+                print(self, item);
+            }
+        }
+
+        self.out.push_str(close);
+    }
+
+    fn if_(&mut self, if_: &ast::If) {
+        // either all the blocks are on one line or none of them
+        let inline = if_
+            .conditioned
+            .iter()
+            .map(|(_, block)| block)
+            .chain(if_.alternative.as_ref())
+            .map(|block| self.inline_block(block))
+            .collect::<Option<Vec<_>>>();
+
+        let mut blocks = if_
+            .conditioned
+            .iter()
+            .map(|(_, block)| block)
+            .chain(if_.alternative.as_ref())
+            .enumerate();
+        let mut block = |printer: &mut Self| {
+            let (i, block) = blocks.next().unwrap();
+            match inline {
+                Some(ref inline) => printer.out.push_str(&inline[i]),
+                None => printer.multiline_block(block),
+            }
+        };
+
+        for (i, (condition, _)) in if_.conditioned.iter().enumerate() {
+            if i != 0 {
+                self.out.push_str(" else ");
+            }
+            self.out.push_str("if ");
+            self.expr(condition, true);
+            self.out.push(' ');
+            block(self);
+        }
+
+        if if_.alternative.is_some() {
+            self.out.push_str(" else ");
+            block(self);
+        }
+    }
+
+    fn block(&mut self, block: &ast::Block) {
+        match self.inline_block(block) {
+            Some(inline) => self.out.push_str(&inline),
+            None => self.multiline_block(block),
+        }
+    }
+
+    // `{}` or `{ <expr> }` if the block fits on one line
+    fn inline_block(&mut self, block: &ast::Block) -> Option<String> {
+        if self.has_comment(block.span) {
+            return None;
+        }
+
+        match *block.nodes {
+            [] => Some("{}".to_owned()),
+            [Node::Expr(ref expr)] => {
+                let outer = std::mem::take(&mut self.out);
+                self.expr(expr, true);
+                let inner = std::mem::replace(&mut self.out, outer);
+
+                (!inner.contains('\n')).then(|| format!("{{ {} }}", inner))
+            }
+            _ => None,
+        }
+    }
+
+    fn multiline_block(&mut self, block: &ast::Block) {
+        self.out.push_str("{\n");
+        self.indent += 1;
+
+        // the last expression is the value of the block
+        self.lines(
+            &block.nodes,
+            block.span.end - 1,
+            Node::span,
+            |printer, node, last| {
+                printer.node(node);
+                if matches!(node, Node::Expr(_)) && !last {
+                    printer.out.push(';');
+                }
+            },
+        );
+
+        self.indent -= 1;
+        self.push_indent();
+        self.out.push('}');
+    }
+}
+
+fn is_operator(expr: &Expr) -> bool {
+    matches!(expr, Expr::Unary(_) | Expr::Binary(_))
+}
+
+fn precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Eq | BinaryOp::Ne => 1,
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 1,
+        BinaryOp::Add | BinaryOp::Sub => 2,
+        BinaryOp::Mul | BinaryOp::Div => 3,
+    }
+}
@@ -0,0 +1,102 @@
+use super::format;
+
+macro_rules! t {
+    ($code:literal, $formatted:literal) => {
+        let formatted = format($code.as_bytes()).unwrap();
+        assert_eq!(formatted, $formatted);
+        assert_eq!(format(formatted.as_bytes()).unwrap(), formatted);
+    };
+}
+
+#[test]
+fn stmts() {
+    t!("let a=1 let b=a+2;b", "let a = 1;\nlet b = a + 2;\nb;\n");
+    t!("return;", "return;\n");
+    t!("return 1 + 2", "return 1 + 2;\n");
+    t!("\n\nf(1)\n\n\n\ng(2)\nh(3)\n\n", "f(1);\n\ng(2);\nh(3);\n");
+}
+
+#[test]
+fn exprs() {
+    t!(
+        r#"[1,"a",true,(1,),(2,3),{1:2},#{3},{},#{},[]]"#,
+        "[1, \"a\", true, (1,), (2, 3), {1: 2}, #{3}, {}, #{}, []];\n"
+    );
+    t!("a[0][f(1)](2,3)", "a[0][f(1)](2, 3);\n");
+    t!("(1+2)*3-(4-5)", "(1 + 2) * 3 - (4 - 5);\n");
+    t!("1+2*3<4==true", "1 + 2 * 3 < 4 == true;\n");
+    t!("-(1+2)", "-(1 + 2);\n");
+    t!("(-a)+b", "(-a) + b;\n");
+    t!("a+(-b)*c", "a + (-b) * c;\n");
+    t!("a*(-b)", "a * -b;\n");
+    t!("(-a)[0]", "(-a)[0];\n");
+    t!("!!a", "!!a;\n");
+}
+
+#[test]
+fn blocks() {
+    t!("let f=fn(x,y){x+y}", "let f = fn(x, y) { x + y };\n");
+    t!("fn(){}", "fn() {};\n");
+    t!(
+        "let f = fn(x) { let y = x; y }",
+        "let f = fn(x) {\n    let y = x;\n    y\n};\n"
+    );
+    t!(
+        "if a { 1 } else if b { 2 } else { 3 }",
+        "if a { 1 } else if b { 2 } else { 3 };\n"
+    );
+    t!(
+        "if a { 1 } else { f(); 2 }",
+        "if a {\n    1\n} else {\n    f();\n    2\n};\n"
+    );
+    t!(
+        "map(xs, fn(x) { let y = x * 2\n y })",
+        "map(xs, fn(x) {\n    let y = x * 2;\n    y\n});\n"
+    );
+}
+
+#[test]
+fn annotations() {
+    t!(
+        "let f:fn(int,[str])->{str:int}=fn(n:int,xs:[str])->{str:int}{{}}",
+        "let f: fn(int, [str]) -> {str: int} = fn(n: int, xs: [str]) -> {str: int} { {} };\n"
+    );
+    t!(
+        "let t:((int),#{bool})=(1,#{})",
+        "let t: (int, #{bool}) = (1, #{});\n"
+    );
+    t!("fn(f:fn(any),g:fn()->(int,)){}", "fn(f: fn(any), g: fn() -> (int,)) {};\n");
+}
+
+#[test]
+fn multiline_literal() {
+    t!(
+        "let m = {\n\"a\": 1, \"b\": [1, 2]}",
+        "let m = {\n    \"a\": 1,\n    \"b\": [1, 2]\n};\n"
+    );
+    t!("let s = [1,\n2]", "let s = [1, 2];\n");
+}
+
+#[test]
+fn comments() {
+    t!(
+        "# head\n\nlet a = 1; # one\n# before b\nlet b = 2\n# tail",
+        "# head\n\nlet a = 1; # one\n# before b\nlet b = 2;\n# tail\n"
+    );
+    t!(
+        "let f = fn() {   # start\n  1 # value\n  # end\n}",
+        "let f = fn() {\n    # start\n    1 # value\n    # end\n};\n"
+    );
+    t!(
+        "let s = [1, # one\n 2]",
+        "let s = [\n    1, # one\n    2\n];\n"
+    );
+    t!("f(1, # one\n2)", "f(1, 2); # one\n");
+    t!("#", "#\n");
+}
+
+#[test]
+fn error() {
+    let error = format(b"let a = 1;\nlet = 2").unwrap_err();
+    assert_eq!(error.kind(), crate::ErrorKind::Parse);
+}
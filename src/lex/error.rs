@@ -1,6 +1,8 @@
+use crate::span::Span;
+
 #[derive(Debug)]
 pub(crate) struct Error {
-    pub(crate) pos: usize,
+    pub(crate) span: Span,
     pub(crate) kind: Kind,
 }
 
@@ -16,6 +18,21 @@ pub(crate) enum Kind {
     BadDigit,
 
     Unexpected,
+
+    /// source code is not valid UTF-8
+    Utf8,
+}
+
+impl Kind {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Quote => "quote",
+            Self::Overflow => "overflow",
+            Self::BadDigit => "bad_digit",
+            Self::Unexpected => "unexpected",
+            Self::Utf8 => "utf8",
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -24,7 +41,8 @@ impl std::fmt::Display for Error {
             Kind::Quote => f.write_str("missing right quote for string literal"),
             Kind::Overflow => f.write_str("integer literal is too large"),
             Kind::BadDigit => write!(f, "bad digit in integer literal"),
-            Kind::Unexpected => write!(f, "unexpected byte"),
+            Kind::Unexpected => write!(f, "unexpected character"),
+            Kind::Utf8 => write!(f, "invalid UTF-8"),
         }
     }
 }
@@ -1,20 +1,41 @@
-use crate::lex::{
-    self,
-    token::{Kind, Token},
+use crate::{
+    lex::{
+        self,
+        token::{Kind, Token},
+    },
+    span::Span,
 };
 
 pub(crate) struct Lexer<'a> {
     input: &'a [u8],
     cursor: usize,
     peeked: Option<lex::Result<Token>>,
+
+    // end of the last token returned by `next()`
+    prev_end: usize,
+
+    // nesting level of braces among the tokens returned by `next()`
+    depth: usize,
+
+    // `#` comments skipped so far, kept as trivia for the formatter
+    comments: Vec<Span>,
 }
 
 impl<'a> Lexer<'a> {
+    #[cfg(test)]
     pub(crate) fn new(input: &'a [u8]) -> Self {
+        Self::with_cursor(input, 0)
+    }
+
+    // start lexing from `cursor` instead of the beginning of `input`
+    pub(crate) fn with_cursor(input: &'a [u8], cursor: usize) -> Self {
         Self {
             input,
-            cursor: 0,
+            cursor,
             peeked: None,
+            prev_end: cursor,
+            depth: 0,
+            comments: Vec::new(),
         }
     }
 
@@ -22,19 +43,65 @@ impl<'a> Lexer<'a> {
         self.cursor
     }
 
+    pub(crate) fn prev_end(&self) -> usize {
+        self.prev_end
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // each span starts from `#` and ends before the line break
+    pub(crate) fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
+    // continue lexing from `pos`, which must be the start of a token returned
+    // by `next()`
+    pub(crate) fn rewind(&mut self, pos: usize) {
+        debug_assert!(pos <= self.cursor);
+
+        self.cursor = pos;
+        self.peeked = None;
+
+        // they are going to be skipped again
+        while self
+            .comments
+            .last()
+            .is_some_and(|comment| comment.start >= pos)
+        {
+            self.comments.pop();
+        }
+    }
+
     pub(crate) fn peek(&mut self) -> Option<&lex::Result<Token>> {
         if self.peeked.is_none() {
-            self.peeked = self.next();
+            self.peeked = self.lex();
         }
 
         self.peeked.as_ref()
     }
 
     pub(crate) fn next(&mut self) -> Option<lex::Result<Token>> {
-        if let Some(peeked) = self.peeked.take() {
-            return Some(peeked);
+        let next = match self.peeked.take() {
+            Some(peeked) => Some(peeked),
+            None => self.lex(),
+        };
+
+        if let Some(Ok(ref token)) = next {
+            self.prev_end = token.span.end;
+
+            match token.kind {
+                Kind::LeftBrace | Kind::HashLeftBrace => self.depth += 1,
+                Kind::RightBrace => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
         }
 
+        next
+    }
+
+    fn lex(&mut self) -> Option<lex::Result<Token>> {
         loop {
             self.skip_writespaces();
 
@@ -51,7 +118,14 @@ impl<'a> Lexer<'a> {
                 b'{' => Ok(Kind::LeftBrace),
                 b'}' => Ok(Kind::RightBrace),
                 b'+' => Ok(Kind::Plus),
-                b'-' => Ok(Kind::Hyphen),
+                b'-' => match self.read_byte() {
+                    Some(b'>') => Ok(Kind::Arrow),
+                    Some(_) => {
+                        self.unwind();
+                        Ok(Kind::Hyphen)
+                    }
+                    None => Ok(Kind::Hyphen),
+                },
                 b'*' => Ok(Kind::Asterisk),
                 b'/' => Ok(Kind::Slash),
                 b'"' => {
@@ -102,36 +176,52 @@ impl<'a> Lexer<'a> {
                     }
                     None => Ok(Kind::Gt),
                 },
-                b'#' => {
-                    self.skip_comment();
+                b'#' => match self.read_byte() {
+                    Some(b'{') => Ok(Kind::HashLeftBrace),
+                    Some(_) => {
+                        self.unwind();
+                        self.skip_comment(pos);
 
-                    continue;
-                }
-                b => {
+                        continue;
+                    }
+                    None => {
+                        self.comments.push(Span::new(pos, self.cursor));
+                        continue;
+                    }
+                },
+                _ => {
                     self.unwind();
 
-                    if is_atom_head(b) {
-                        let atom = self.lex_atom(pos);
-                        self.cursor += atom.len();
-
-                        if let Some(k) = to_keyword(atom) {
-                            Ok(k)
-                        } else {
-                            // identifier
-                            debug_assert!(atom.is_ascii());
+                    match self.decode_char(pos) {
+                        Ok(c) if is_atom_head(c) => {
+                            let atom = self.lex_atom(pos);
+                            self.cursor += atom.len();
 
-                            Ok(Kind::Ident)
+                            if let Some(k) = to_keyword(atom) {
+                                Ok(k)
+                            } else {
+                                Ok(Kind::Ident)
+                            }
                         }
-                    } else {
-                        break Some(Err(lex::Error {
-                            pos,
+                        Ok(c) => Err(lex::Error {
+                            span: Span::new(pos, pos + c.len_utf8()),
                             kind: lex::error::Kind::Unexpected,
-                        }));
+                        }),
+                        Err(err) => Err(err),
                     }
                 }
             };
 
-            break Some(kind.map(|kind| Token { pos, kind }));
+            // skip the invalid input, so that a parser recovering from the
+            // error can move on
+            if let Err(ref err) = kind {
+                self.cursor = self.cursor.max(err.span.end);
+            }
+
+            break Some(kind.map(|kind| Token {
+                span: Span::new(pos, self.cursor),
+                kind,
+            }));
         }
     }
 }
@@ -162,11 +252,11 @@ impl Lexer<'_> {
 impl<'a> Lexer<'a> {
     // ident or keyword
     pub(crate) fn lex_atom(&self, start_pos: usize) -> &'a str {
-        let mut end_pos = start_pos + 1;
+        let mut end_pos = start_pos;
 
-        while let Some(&b) = self.input.get(end_pos) {
-            if is_atom_tail(b) {
-                end_pos += 1;
+        while let Ok(c) = self.decode_char(end_pos) {
+            if end_pos == start_pos || is_atom_tail(c) {
+                end_pos += c.len_utf8();
             } else {
                 break;
             }
@@ -176,6 +266,25 @@ impl<'a> Lexer<'a> {
         std::str::from_utf8(bytes).unwrap()
     }
 
+    // the char starting at `pos`, an error if `input[pos..]` doesn't start
+    // with valid UTF-8
+    //
+    // `pos` must be in bounds
+    fn decode_char(&self, pos: usize) -> lex::Result<char> {
+        // a char is 4 bytes at most
+        let bytes = &self.input[pos..self.input.len().min(pos + 4)];
+
+        let valid = match std::str::from_utf8(bytes) {
+            Ok(valid) => valid,
+            Err(err) => std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap(),
+        };
+
+        valid.chars().next().ok_or_else(|| lex::Error {
+            span: Span::new(pos, pos + invalid_len(bytes)),
+            kind: lex::error::Kind::Utf8,
+        })
+    }
+
     pub(crate) fn lex_int(&self, pos: usize) -> lex::Result<(i32, usize)> {
         let mut cursor = pos;
         let mut num: i32 = 0;
@@ -185,20 +294,22 @@ impl<'a> Lexer<'a> {
                     num = num
                         .checked_mul(10)
                         .and_then(|num| num.checked_add(i32::from(b - b'0')))
-                        .ok_or(lex::Error {
-                            pos,
+                        .ok_or_else(|| lex::Error {
+                            span: Span::new(pos, self.digits_end(cursor)),
                             kind: lex::error::Kind::Overflow,
                         })?;
 
                     cursor += 1;
                 }
-                _ if is_atom_tail(b) => {
-                    return Err(lex::Error {
-                        pos: cursor,
-                        kind: lex::error::Kind::BadDigit,
-                    });
-                }
-                _ => break,
+                _ => match self.decode_char(cursor) {
+                    Ok(c) if is_atom_tail(c) => {
+                        return Err(lex::Error {
+                            span: Span::new(cursor, cursor + c.len_utf8()),
+                            kind: lex::error::Kind::BadDigit,
+                        });
+                    }
+                    _ => break,
+                },
             }
         }
 
@@ -211,32 +322,62 @@ impl<'a> Lexer<'a> {
             .iter()
             .position(|b| *b == b'"')
             .ok_or(lex::Error {
-                pos,
+                span: Span::new(pos, self.input.len()),
                 kind: lex::error::Kind::Quote,
             })?;
 
         let s = &self.input[pos + 1..][..len];
-        let s = std::str::from_utf8(s).unwrap();
+        let s = std::str::from_utf8(s).map_err(|err| {
+            let start = pos + 1 + err.valid_up_to();
+            lex::Error {
+                span: Span::new(
+                    start,
+                    start + err.error_len().unwrap_or(len - err.valid_up_to()),
+                ),
+                kind: lex::error::Kind::Utf8,
+            }
+        })?;
 
         Ok(s)
     }
 
-    fn skip_comment(&mut self) {
+    // end of the digits starting at `cursor`
+    fn digits_end(&self, cursor: usize) -> usize {
+        self.input[cursor..]
+            .iter()
+            .position(|b| !b.is_ascii_digit())
+            .map_or(self.input.len(), |i| cursor + i)
+    }
+
+    // `start` points to the `#`
+    fn skip_comment(&mut self, start: usize) {
         match self.input[self.cursor..].iter().position(|b| *b == b'\n') {
             Some(pos) => {
+                self.comments.push(Span::new(start, self.cursor + pos));
                 self.cursor += pos + 1;
             }
-            None => self.cursor = self.input.len(),
+            None => {
+                self.comments.push(Span::new(start, self.input.len()));
+                self.cursor = self.input.len();
+            }
         }
     }
 }
 
-fn is_atom_head(b: u8) -> bool {
-    b.is_ascii_alphabetic() || b == b'_'
+fn is_atom_head(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
 }
 
-fn is_atom_tail(b: u8) -> bool {
-    is_atom_head(b) || b.is_ascii_digit()
+fn is_atom_tail(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+// length of the invalid UTF-8 sequence at the start of `bytes`
+fn invalid_len(bytes: &[u8]) -> usize {
+    match std::str::from_utf8(bytes) {
+        Err(err) => err.error_len().unwrap_or(bytes.len()),
+        Ok(_) => 0,
+    }
 }
 
 fn to_keyword(atom: &str) -> Option<Kind> {
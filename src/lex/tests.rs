@@ -2,7 +2,7 @@ use super::{error, token::Kind, Lexer};
 
 macro_rules! t {
     ($input:literal, $tokens:expr) => {
-        let mut lexer = Lexer::new($input.as_bytes().into());
+        let mut lexer = Lexer::new($input.as_bytes());
         for token in $tokens {
             match lexer.next() {
                 Some(Ok(tk)) => assert_eq!(tk.kind, token),
@@ -41,6 +41,7 @@ foo
 == !=
 < >
 <= >=
+-> - >
 "#,
         vec![
             Kind::Let,
@@ -76,6 +77,9 @@ foo
             Kind::Gt,
             Kind::Le,
             Kind::Ge,
+            Kind::Arrow,
+            Kind::Hyphen,
+            Kind::Gt,
         ]
     );
 }
@@ -84,7 +88,7 @@ foo
 fn stmts() {
     t!(
         "let answer = 42;",
-        vec![
+        [
             Kind::Let,
             Kind::Ident,
             Kind::Assign,
@@ -95,7 +99,7 @@ fn stmts() {
 
     t!(
         "let is = true;",
-        vec![
+        [
             Kind::Let,
             Kind::Ident,
             Kind::Assign,
@@ -106,7 +110,7 @@ fn stmts() {
 
     t!(
         "let not = false;",
-        vec![
+        [
             Kind::Let,
             Kind::Ident,
             Kind::Assign,
@@ -208,9 +212,42 @@ fn stmts() {
     );
 }
 
+#[test]
+fn set_and_comment() {
+    t!(
+        "#{1, 2} # a comment\n#{}#",
+        [
+            Kind::HashLeftBrace,
+            Kind::Int,
+            Kind::Comma,
+            Kind::Int,
+            Kind::RightBrace,
+            Kind::HashLeftBrace,
+            Kind::RightBrace,
+        ]
+    );
+
+    // `#{` always starts a set, even where a comment was meant
+    t!(
+        "#{not a set}\n1",
+        [
+            Kind::HashLeftBrace,
+            Kind::Ident,
+            Kind::Ident,
+            Kind::Ident,
+            Kind::RightBrace,
+            Kind::Int,
+        ]
+    );
+
+    // but `#` followed by a space is still a comment, even before a `{`
+    t!("# {a comment}\n1 #{", [Kind::Int, Kind::HashLeftBrace]);
+    t!("#\t{a comment}\n# {\n1", [Kind::Int]);
+}
+
 macro_rules! t_err {
     ($input:literal, $error:expr) => {
-        let mut lexer = Lexer::new($input.as_bytes().into());
+        let mut lexer = Lexer::new($input.as_bytes());
         assert_eq!(lexer.next().unwrap().unwrap_err().kind, $error);
     };
 }
@@ -223,3 +260,39 @@ fn errors() {
     t_err!("0x", error::Kind::BadDigit);
     t_err!("$", error::Kind::Unexpected);
 }
+
+#[test]
+fn unicode() {
+    t!(
+        "let größe = \"héllo\"; 变量 _x1",
+        [
+            Kind::Let,
+            Kind::Ident,
+            Kind::Assign,
+            Kind::Str,
+            Kind::Semicolon,
+            Kind::Ident,
+            Kind::Ident,
+        ]
+    );
+
+    let mut lexer = Lexer::new("größe1".as_bytes());
+    let tk = lexer.next().unwrap().unwrap();
+    assert_eq!(lexer.lex_atom(tk.span.start), "größe1");
+
+    t_err!("①", error::Kind::Unexpected);
+    t_err!("1é", error::Kind::BadDigit);
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut lexer = Lexer::new(b"\"ab\xffc\"");
+    let err = lexer.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, error::Kind::Utf8);
+    assert_eq!((err.span.start, err.span.end), (3, 4));
+
+    let mut lexer = Lexer::new(b"\xe4\xbd");
+    let err = lexer.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, error::Kind::Utf8);
+    assert_eq!((err.span.start, err.span.end), (0, 2));
+}
@@ -1,11 +1,14 @@
+use crate::span::Span;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) struct Token {
-    pub(crate) pos: usize,
+pub struct Token {
+    pub(crate) span: Span,
     pub(crate) kind: Kind,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) enum Kind {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Kind {
     /// `let`
     Let,
 
@@ -67,6 +70,12 @@ pub(crate) enum Kind {
     /// `}`
     RightBrace,
 
+    /// `#{`
+    ///
+    /// start of a set literal, never of a comment, `#` followed by anything
+    /// else is
+    HashLeftBrace,
+
     /// `+`
     Plus,
 
@@ -102,4 +111,61 @@ pub(crate) enum Kind {
 
     /// `>=`
     Ge,
+
+    /// `->`
+    ///
+    /// before the return type of a closure
+    Arrow,
+}
+
+// as in a message, e.g. "found `;`"
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Let => "let",
+            Self::True => "true",
+            Self::False => "false",
+            Self::Fn => "fn",
+            Self::Return => "return",
+            Self::If => "if",
+            Self::Else => "else",
+            Self::Ident => return f.write_str("identifier"),
+            Self::Int => return f.write_str("integer"),
+            Self::Str => return f.write_str("string"),
+            Self::Comma => ",",
+            Self::Colon => ":",
+            Self::Semicolon => ";",
+            Self::LeftParen => "(",
+            Self::RightParen => ")",
+            Self::LeftBracket => "[",
+            Self::RightBracket => "]",
+            Self::LeftBrace => "{",
+            Self::RightBrace => "}",
+            Self::HashLeftBrace => "#{",
+            Self::Plus => "+",
+            Self::Hyphen => "-",
+            Self::Asterisk => "*",
+            Self::Slash => "/",
+            Self::Assign => "=",
+            Self::Bang => "!",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::Le => "<=",
+            Self::Ge => ">=",
+            Self::Arrow => "->",
+        };
+        write!(f, "`{}`", text)
+    }
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
 }
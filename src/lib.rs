@@ -3,9 +3,25 @@
 #[macro_use]
 mod macros;
 
+mod diagnostic;
 mod embed;
 mod eval;
+mod format;
 mod lex;
+mod lint;
+mod optimize;
 mod parse;
+mod program;
+mod span;
+mod typeck;
 
-pub use embed::{entry, Env, EnvBuilder, Error, Result, Value};
+pub mod syntax;
+
+pub use diagnostic::Diagnostic;
+pub use embed::{
+    builtins, check, check_types, entry, entry_at, Cap, Env, EnvBuilder, Error, ErrorKind,
+    InterruptHandle, Result, Value,
+};
+pub use format::format;
+pub use lint::{lint, Lint, Linter, Rule};
+pub use program::{Program, SourceMap};
@@ -0,0 +1,556 @@
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashSet, fmt, rc::Rc};
+
+use crate::{
+    embed::Error,
+    eval::{
+        env::find_closest_symbol,
+        value::{builtin, closure},
+    },
+    parse::{
+        ast::{self, Expr, Ident, Node, Stmt},
+        visit::{self, Visitor},
+    },
+    span::Span,
+};
+
+/// check `code` for common mistakes without running it, with all the
+/// [`Rule`]s, the first syntax error is returned if any
+///
+/// ```rust
+/// let lints = oris::lint(b"let x = 1;\nprint(y);").unwrap();
+///
+/// let rules: Vec<_> = lints.iter().map(|lint| lint.rule()).collect();
+/// assert_eq!(rules, [oris::Rule::Unused, oris::Rule::Undefined]);
+/// ```
+pub fn lint(code: &[u8]) -> Result<Vec<Lint>, Error> {
+    Linter::new().lint(code)
+}
+
+/// what a [`Lint`] is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    /// an identifier which is neither bound nor a builtin, e.g. a typo, which
+    /// is an error once evaluated
+    Undefined,
+
+    /// a `let` binding or a parameter which is never used, unless its name
+    /// starts with `_`
+    Unused,
+
+    /// a binding hiding another one of the same name in an outer scope, the
+    /// `let` in an `if` block overwrites the outer binding when evaluated
+    Shadowed,
+
+    /// code after `return <expr>` in the same block
+    Unreachable,
+
+    /// a call to a builtin with a wrong number of arguments
+    ArgCount,
+
+    /// an `if` condition which is a literal but not a bool, so the branch is
+    /// never taken
+    Condition,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Self::Undefined,
+        Self::Unused,
+        Self::Shadowed,
+        Self::Unreachable,
+        Self::ArgCount,
+        Self::Condition,
+    ];
+
+    /// snake_case name of the rule, e.g. `"arg_count"`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Undefined => "undefined",
+            Self::Unused => "unused",
+            Self::Shadowed => "shadowed",
+            Self::Unreachable => "unreachable",
+            Self::ArgCount => "arg_count",
+            Self::Condition => "condition",
+        }
+    }
+}
+
+/// a suspicious piece of code found by a [`Linter`]
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub(crate) rule: Rule,
+    pub(crate) span: Span,
+    pub(crate) message: String,
+    pub(crate) notes: Vec<String>,
+    // another place worth showing, e.g. the binding being shadowed
+    pub(crate) related: Option<(Span, &'static str)>,
+}
+
+impl Lint {
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// byte range of the suspicious code
+    pub fn span(&self) -> std::ops::Range<usize> {
+        self.span.into()
+    }
+
+    /// render this lint as a warning with the offending line in `code`, see
+    /// [`Diagnostic`](crate::Diagnostic)
+    ///
+    /// `code` should be the source code where this lint is found
+    pub fn diagnostic<'a>(&'a self, code: &'a [u8]) -> crate::Diagnostic<'a> {
+        crate::Diagnostic::lint(self, code)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// a linter with configurable [`Rule`]s, all of them are checked by default
+///
+/// ```rust
+/// use oris::{Linter, Rule};
+///
+/// let linter = Linter::new().allow(Rule::Unused);
+///
+/// let lints = linter.lint(b"let x = 1; if 1 { 2 }").unwrap();
+/// assert_eq!(lints.len(), 1);
+/// assert_eq!(lints[0].rule(), Rule::Condition);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Linter {
+    allowed: HashSet<Rule>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// don't report lints of `rule`
+    pub fn allow(mut self, rule: Rule) -> Self {
+        self.allowed.insert(rule);
+        self
+    }
+
+    /// parse and check `code`, the first syntax error is returned if any
+    pub fn lint(&self, code: &[u8]) -> Result<Vec<Lint>, Error> {
+        let nodes = crate::syntax::parse(code)?;
+        Ok(self.lint_nodes(&nodes))
+    }
+
+    /// check already parsed code, see [`syntax`](crate::syntax), the lints
+    /// are sorted by their positions
+    pub fn lint_nodes(&self, nodes: &[Node]) -> Vec<Lint> {
+        let mut pass = Pass {
+            linter: self,
+            scopes: vec![Vec::new()],
+            closures: Vec::new(),
+            globals: nodes
+                .iter()
+                .filter_map(|node| match node {
+                    Node::Stmt(Stmt::Let(let_)) => Some(let_.ident.sym()),
+                    _ => None,
+                })
+                .collect(),
+            used_later: HashSet::new(),
+            recursive: None,
+            lints: Vec::new(),
+        };
+
+        pass.nodes(nodes);
+        pass.pop_scope();
+
+        let mut lints = pass.lints;
+        lints.sort_by_key(|lint| (lint.span.start, lint.span.end));
+        lints
+    }
+}
+
+struct Pass<'ast, 'l> {
+    linter: &'l Linter,
+    // from the outermost to the innermost
+    scopes: Vec<Vec<Binding<'ast>>>,
+    // closures being visited, from the outermost to the innermost
+    closures: Vec<Frame<'ast>>,
+    // a closure can use a global bound after it's created but before called
+    globals: HashSet<&'ast str>,
+    // globals used by closures before they are bound
+    used_later: HashSet<&'ast str>,
+    // name of the `let` whose value is the closure to be visited, which is
+    // bound as itself in the closure
+    recursive: Option<&'ast str>,
+    lints: Vec<Lint>,
+}
+
+struct Binding<'ast> {
+    ident: &'ast Ident,
+    parameter: bool,
+    used: bool,
+}
+
+// what a closure sees when it's called, besides globals
+struct Frame<'ast> {
+    // index of the scope of the parameters
+    scope: usize,
+    captured: Vec<&'ast Ident>,
+    recursive: Option<&'ast str>,
+}
+
+impl<'ast> Pass<'ast, '_> {
+    fn report(&mut self, rule: Rule, span: Span, message: String) -> Option<&mut Lint> {
+        if self.linter.allowed.contains(&rule) {
+            return None;
+        }
+
+        self.lints.push(Lint {
+            rule,
+            span,
+            message,
+            notes: Vec::new(),
+            related: None,
+        });
+        self.lints.last_mut()
+    }
+
+    fn nodes(&mut self, nodes: &'ast [Node]) {
+        for node in nodes {
+            self.visit_node(node);
+        }
+
+        let returned = nodes.iter().position(|node| {
+            matches!(
+                node,
+                Node::Stmt(Stmt::Return(ast::Return { value: Some(_), .. }))
+            )
+        });
+
+        // `return;` without a value doesn't return
+        if let (Some(i), Some(last)) = (returned, nodes.last()) {
+            if let Some(first) = nodes.get(i + 1) {
+                let span = first.span().to(last.span());
+                if let Some(lint) = self.report(Rule::Unreachable, span, "unreachable code".into())
+                {
+                    lint.related = Some((nodes[i].span(), "returned here"));
+                }
+            }
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let bindings = self.scopes.pop().unwrap();
+
+        for binding in bindings {
+            if binding.used || binding.ident.sym().starts_with('_') {
+                continue;
+            }
+
+            let message = if binding.parameter {
+                format!("unused parameter: {}", binding.ident.sym())
+            } else {
+                format!("unused binding: {}", binding.ident.sym())
+            };
+            self.report(Rule::Unused, binding.ident.span(), message);
+        }
+    }
+
+    fn bind(&mut self, ident: &'ast Ident, parameter: bool) {
+        let outer = self.scopes.len() - 1;
+        let shadowed = self.scopes[..outer]
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|binding| binding.ident.sym() == ident.sym())
+            .map(|binding| binding.ident.span());
+
+        if let Some(shadowed) = shadowed.filter(|_| !ident.sym().starts_with('_')) {
+            let message = format!("binding shadows an outer one: {}", ident.sym());
+            if let Some(lint) = self.report(Rule::Shadowed, ident.span(), message) {
+                lint.related = Some((shadowed, "shadowed binding"));
+            }
+        }
+
+        let used = self.scopes.len() == 1 && self.used_later.remove(ident.sym());
+
+        self.scopes.last_mut().unwrap().push(Binding {
+            ident,
+            parameter,
+            used,
+        });
+    }
+
+    // mark the binding of `sym` used if it's visible from the current frame
+    fn use_local(&mut self, sym: &str) -> bool {
+        let from = self.closures.last().map_or(0, |frame| frame.scope);
+
+        let binding = self.scopes[from..]
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.ident.sym() == sym);
+
+        match binding {
+            Some(binding) => {
+                binding.used = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // resolve an identifier captured by a closure being created, as
+    // `Closure::new` and then the call of it do
+    fn capture(&mut self, ident: &'ast Ident, recursive: Option<&str>) -> bool {
+        let sym = ident.sym();
+
+        if self.use_local(sym) {
+            return true;
+        }
+
+        if let Some(frame) = self.closures.last() {
+            if frame.captured.iter().any(|captured| captured.sym() == sym)
+                || frame.recursive == Some(sym)
+            {
+                return true;
+            }
+        }
+
+        if recursive == Some(sym) {
+            return true;
+        }
+
+        if self.globals.contains(sym) {
+            match self.scopes[0]
+                .iter_mut()
+                .rev()
+                .find(|binding| binding.ident.sym() == sym)
+            {
+                Some(binding) => binding.used = true,
+                None => {
+                    self.used_later.insert(ident.sym());
+                }
+            }
+            return true;
+        }
+
+        is_builtin(sym)
+    }
+
+    fn undefined(&mut self, ident: &Ident) {
+        // the same candidates as `Env::find_similar_symbol`, those in the
+        // current frame and globals
+        let from = self.closures.last().map_or(0, |frame| frame.scope);
+        let mut candidates: Vec<Rc<str>> = self.scopes[from..]
+            .iter()
+            .chain(self.scopes.first())
+            .flatten()
+            .map(|binding| binding.ident.sym_rc_str().clone())
+            .collect();
+        if let Some(frame) = self.closures.last() {
+            candidates.extend(
+                frame
+                    .captured
+                    .iter()
+                    .map(|ident| ident.sym_rc_str().clone()),
+            );
+        }
+        candidates.extend(builtin::ALL.iter().map(|entry| Rc::from(entry.name)));
+
+        let similar = find_closest_symbol(ident.sym(), candidates.iter())
+            .map(|similar| format!("note: a similar identifier exists: {}", similar));
+
+        let message = format!("undefined identifier: {}", ident.sym());
+        if let Some(lint) = self.report(Rule::Undefined, ident.span(), message) {
+            lint.notes.extend(similar);
+        }
+    }
+
+    fn check_arg_count(&mut self, call: &ast::Call) {
+        let ident = match call.target {
+            Expr::Ident(ref ident) => ident,
+            _ => return,
+        };
+
+        // a builtin can be shadowed by any binding
+        let sym = ident.sym();
+        if !is_builtin(sym)
+            || self.globals.contains(sym)
+            || self
+                .scopes
+                .iter()
+                .flatten()
+                .any(|binding| binding.ident.sym() == sym)
+        {
+            return;
+        }
+
+        let arities = arities(sym);
+        let supplied = call.args.len();
+        if arities
+            .iter()
+            .any(|&(min, max)| min <= supplied && max.is_none_or(|max| supplied <= max))
+        {
+            return;
+        }
+
+        let mut expected = Vec::<String>::new();
+        for &(min, max) in arities.iter() {
+            let arity = match max {
+                Some(max) if max == min => min.to_string(),
+                Some(max) => format!("{} to {}", min, max),
+                None => format!("at least {}", min),
+            };
+            if !expected.contains(&arity) {
+                expected.push(arity);
+            }
+        }
+
+        let expected = expected.join(" or ");
+        let message = format!(
+            "builtin {} accepts {} {}, but got {}",
+            sym,
+            expected,
+            if expected == "1" { "arg" } else { "args" },
+            supplied
+        );
+        self.report(Rule::ArgCount, call.span, message);
+    }
+}
+
+impl<'ast> Visitor<'ast> for Pass<'ast, '_> {
+    fn visit_let(&mut self, let_: &'ast ast::Let) {
+        self.recursive = match let_.value {
+            Expr::Closure(_) => Some(let_.ident.sym()),
+            _ => None,
+        };
+
+        visit::walk_let(self, let_);
+        self.recursive = None;
+
+        self.bind(&let_.ident, false);
+    }
+
+    fn visit_ident(&mut self, ident: &'ast Ident) {
+        // an identifier not bound in a closure is captured when the closure
+        // is created, see `visit_closure`
+        if !self.use_local(ident.sym()) && self.closures.is_empty() && !is_builtin(ident.sym()) {
+            self.undefined(ident);
+        }
+    }
+
+    fn visit_closure(&mut self, closure: &'ast ast::Closure) {
+        let recursive = self.recursive.take();
+
+        let captured = closure::analyze_unbounded(closure);
+        for &ident in captured.iter() {
+            if !self.capture(ident, recursive) {
+                self.undefined(ident);
+            }
+        }
+
+        self.closures.push(Frame {
+            scope: self.scopes.len(),
+            captured,
+            recursive,
+        });
+        self.push_scope();
+
+        for parameter in closure.parameters.iter() {
+            self.bind(parameter, true);
+        }
+        visit::walk_closure(self, closure);
+
+        self.pop_scope();
+        self.closures.pop();
+    }
+
+    fn visit_call(&mut self, call: &'ast ast::Call) {
+        self.check_arg_count(call);
+        visit::walk_call(self, call);
+    }
+
+    fn visit_if(&mut self, if_: &'ast ast::If) {
+        for (condition, _) in if_.conditioned.iter() {
+            if let Expr::Int(_)
+            | Expr::Str(_)
+            | Expr::Seq(_)
+            | Expr::Set(_)
+            | Expr::Tuple(_)
+            | Expr::Map(_)
+            | Expr::Closure(_) = condition
+            {
+                let message = "condition is not a bool, the branch is never taken".to_string();
+                self.report(Rule::Condition, condition.span(), message);
+            }
+        }
+
+        visit::walk_if(self, if_);
+    }
+
+    fn visit_block(&mut self, block: &'ast ast::Block) {
+        self.push_scope();
+        self.nodes(&block.nodes);
+        self.pop_scope();
+    }
+}
+
+fn is_builtin(sym: &str) -> bool {
+    !builtin::signatures(sym).is_empty()
+}
+
+// `(min, max)` numbers of args accepted by each signature of a builtin, `T...`
+// is any number of args
+fn arities(name: &str) -> Vec<(usize, Option<usize>)> {
+    builtin::signatures(name)
+        .iter()
+        .map(|signature| {
+            let params = parameters(signature);
+            match params.last() {
+                Some(last) if last.ends_with("...") => (params.len() - 1, None),
+                _ => (params.len(), Some(params.len())),
+            }
+        })
+        .collect()
+}
+
+// `["[T]", "fn(T) -> U"]` for `fn([T], fn(T) -> U) -> [U]`
+fn parameters(signature: &str) -> Vec<&str> {
+    let inner = &signature["fn(".len()..];
+
+    let mut params = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' if depth == 0 => {
+                let last = inner[start..i].trim();
+                if !last.is_empty() {
+                    params.push(last);
+                }
+                break;
+            }
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    params
+}
@@ -0,0 +1,159 @@
+use super::{lint, Linter, Rule};
+
+// `(rule, linted code)` of each lint
+fn t(code: &str) -> Vec<(Rule, &str)> {
+    lint(code.as_bytes())
+        .unwrap()
+        .into_iter()
+        .map(|lint| (lint.rule(), &code[lint.span()]))
+        .collect()
+}
+
+#[test]
+fn undefined() {
+    assert_eq!(t("len(x)"), [(Rule::Undefined, "x")]);
+    assert_eq!(t("x; let x = 1; x"), [(Rule::Undefined, "x")]);
+    assert_eq!(
+        t("if true { let a = 1; }; a"),
+        [(Rule::Unused, "a"), (Rule::Undefined, "a")]
+    );
+
+    // globals bound before the closure is called
+    assert_eq!(t("let f = fn() { g() }; let g = fn() { f }; f()"), []);
+    assert_eq!(t("let f = fn(n) { f(n) }; f(1)"), []);
+    assert_eq!(
+        t("let f = fn() { fn(n) { n + m } }; f()"),
+        [(Rule::Undefined, "m")]
+    );
+
+    // only the captured identifiers are visible in a nested closure
+    assert_eq!(
+        t("let f = fn(a) { fn() { fn() { a } } }; f"),
+        [(Rule::Unused, "a"), (Rule::Undefined, "a")]
+    );
+    assert_eq!(t("let f = fn(a) { fn() { a; fn() { a } } }; f"), []);
+}
+
+#[test]
+fn similar() {
+    let lints = lint(b"let count = 1;\ncont + count").unwrap();
+    assert_eq!(lints.len(), 1);
+    assert_eq!(
+        lints[0]
+            .diagnostic(b"let count = 1;\ncont + count")
+            .to_string(),
+        "\
+warning: undefined identifier: cont
+ --> 2:1
+  |
+2 | cont + count
+  | ^^^^
+  |
+  = note: a similar identifier exists: count
+"
+    );
+}
+
+#[test]
+fn unused() {
+    assert_eq!(
+        t("let a = 1; let b = fn(x, _y) { 2 }; b(3, 4)"),
+        [(Rule::Unused, "a"), (Rule::Unused, "x")]
+    );
+    assert_eq!(t("let a = 1; let a = a + 1; a"), []);
+    assert_eq!(t("let a = 1; let a = 2; a"), [(Rule::Unused, "a")]);
+    assert_eq!(t("let _a = 1;"), []);
+}
+
+#[test]
+fn shadowed() {
+    let code = "let x = 1;\nif x == 1 {\n    let x = 2;\n    x\n}";
+    assert_eq!(t(code), [(Rule::Shadowed, "x")]);
+
+    let lints = lint(code.as_bytes()).unwrap();
+    assert_eq!(
+        lints[0].diagnostic(code.as_bytes()).to_string(),
+        "\
+warning: binding shadows an outer one: x
+ --> 3:9
+  |
+1 | let x = 1;
+  |     - shadowed binding
+...
+3 |     let x = 2;
+  |         ^
+"
+    );
+
+    assert_eq!(
+        t("let x = 1; let f = fn(x) { x }; f(x)"),
+        [(Rule::Shadowed, "x")]
+    );
+}
+
+#[test]
+fn unreachable() {
+    assert_eq!(
+        t("let f = fn() { return 1; 2; 3 }; f()"),
+        [(Rule::Unreachable, "2; 3")]
+    );
+    assert_eq!(t("let f = fn() { return; 2 }; f()"), []);
+}
+
+#[test]
+fn arg_count() {
+    assert_eq!(t("len(1, 2)"), [(Rule::ArgCount, "len(1, 2)")]);
+    assert_eq!(
+        t("range(); range(1); range(1, 2)"),
+        [(Rule::ArgCount, "range()")]
+    );
+    assert_eq!(t("print(); print(1, 2, 3)"), []);
+    assert_eq!(t("let len = fn() { 0 }; len()"), []);
+
+    assert_eq!(
+        lint(b"range(1, 2, 3)").unwrap()[0].to_string(),
+        "builtin range accepts 1 or 2 args, but got 3"
+    );
+    assert_eq!(
+        lint(b"map([])").unwrap()[0].to_string(),
+        "builtin map accepts 2 args, but got 1"
+    );
+    assert_eq!(
+        lint(b"len()").unwrap()[0].to_string(),
+        "builtin len accepts 1 arg, but got 0"
+    );
+}
+
+#[test]
+fn condition() {
+    assert_eq!(
+        t("if 1 { 2 } else if [] { 3 } else if true { 4 }"),
+        [(Rule::Condition, "1"), (Rule::Condition, "[]")]
+    );
+}
+
+#[test]
+fn allow() {
+    let linter = Linter::new().allow(Rule::Unused).allow(Rule::Condition);
+
+    let lints = linter.lint(b"let a = 1; if 1 { b }").unwrap();
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].rule(), Rule::Undefined);
+
+    assert!(linter.lint(b"let a = ;").is_err());
+}
+
+#[test]
+fn json() {
+    let code = b"let a = 1;";
+    let lints = lint(code).unwrap();
+
+    assert_eq!(
+        lints[0].diagnostic(code).json(true).to_string(),
+        concat!(
+            r#"{"file":null,"line":1,"column":5,"span":{"start":4,"end":5},"#,
+            r#""kind":"lint","name":"unused","message":"unused binding: a","notes":[]}"#,
+            "\n"
+        )
+    );
+}
@@ -3,20 +3,22 @@ macro_rules! propagate {
         match $eval {
             Ok($crate::eval::Eval::Continue(x)) => x,
             Ok($crate::eval::Eval::Return(b)) => return Ok($crate::eval::Eval::Return(b)),
+            Ok($crate::eval::Eval::TailCall(c)) => return Ok($crate::eval::Eval::TailCall(c)),
             Err(err) => return Err(err),
         }
     };
 }
 
 macro_rules! args {
-    ($args:ident @ $pos:ident = $($binding:ident),*) => {
+    ($args:ident @ $span:ident = $($binding:ident),*) => {
         let [$($binding),*] = match <[$crate::eval::value::Value; args!(@count $($binding),*)]>::try_from($args) {
             Ok(xs) => { xs }
             Err(err) => {
                   return Err($crate::eval::Error::ArgCount {
-                    pos: $pos,
+                    span: $span,
                     supplied: err.len(),
-                    expected: args!(@count $($binding),*),
+                    expected: $crate::eval::Arity::exactly(args!(@count $($binding),*)),
+                    defined: None,
                 });
             }
         };
@@ -1,21 +1,119 @@
+use std::io::IsTerminal;
+
+// exit codes, runtime errors are reported as a generic failure
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_SYNTAX: i32 = 3;
+
+struct Options {
+    color: bool,
+    json: bool,
+}
+
 fn main() {
-    if let Some(file) = std::env::args().nth(1) {
-        run(&file);
-    } else {
-        repl();
+    let mut color = None;
+    let mut json = false;
+    let mut fmt_check = false;
+    let mut types = false;
+    let mut linter = oris::Linter::new();
+    let mut operands = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--color=") {
+            match value {
+                "always" => color = Some(true),
+                "never" => color = Some(false),
+                "auto" => color = None,
+                other => usage(&format!("invalid value for --color: '{}'", other)),
+            }
+        } else if let Some(value) = arg.strip_prefix("--error-format=") {
+            match value {
+                "human" => json = false,
+                "json" => json = true,
+                other => usage(&format!("invalid value for --error-format: '{}'", other)),
+            }
+        } else if arg == "--check" && operands.first().is_some_and(|command| command == "fmt") {
+            fmt_check = true;
+        } else if arg == "--types" && operands.first().is_some_and(|command| command == "check") {
+            types = true;
+        } else if let Some(value) = arg
+            .strip_prefix("--allow=")
+            .filter(|_| operands.first().is_some_and(|command| command == "lint"))
+        {
+            for name in value.split(',') {
+                match oris::Rule::ALL.iter().find(|rule| rule.as_str() == name) {
+                    Some(&rule) => linter = linter.allow(rule),
+                    None => usage(&format!("unknown lint rule '{}'", name)),
+                }
+            }
+        } else if !arg.starts_with("--") {
+            operands.push(arg);
+        } else {
+            usage(&format!("unexpected argument '{}'", arg));
+        }
+    }
+
+    // colour the diagnostics only if a human is looking at them
+    let color = color.unwrap_or_else(|| {
+        std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+    });
+
+    let options = Options { color, json };
+
+    match operands.as_slice() {
+        [] => repl(&options),
+        [file] => run(file, &options),
+        [command, file] if command == "check" => check(file, types, &options),
+        [command, files @ ..] if command == "fmt" && !files.is_empty() => {
+            fmt(files, fmt_check, &options)
+        }
+        [command, files @ ..] if command == "lint" && !files.is_empty() => {
+            lint(files, &linter, &options)
+        }
+        [_, operand, ..] => usage(&format!("unexpected argument '{}'", operand)),
     }
 }
 
-fn run(file: &str) {
-    let code = match std::fs::read(file) {
+fn usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("usage: oris [options] [file]");
+    eprintln!("       oris [options] check [--types] file");
+    eprintln!("       oris [options] fmt [--check] file...");
+    eprintln!("       oris [options] lint [--allow=rule,...] file...");
+    eprintln!("options: --color=auto|always|never --error-format=human|json");
+    std::process::exit(EXIT_USAGE);
+}
+
+fn report(err: &oris::Error, code: &[u8], file: Option<&str>, options: &Options) {
+    let mut diagnostic = err
+        .diagnostic(code)
+        .color(options.color && !options.json)
+        .json(options.json);
+    if let Some(file) = file {
+        diagnostic = diagnostic.file(file);
+    }
+
+    eprint!("{}", diagnostic);
+}
+
+fn read(file: &str) -> Vec<u8> {
+    match std::fs::read(file) {
         Ok(code) => code,
         Err(err) => {
             eprintln!("can't open file '{}': {}", file, err);
-            std::process::exit(1);
+            std::process::exit(EXIT_FAILURE);
         }
-    };
+    }
+}
 
-    let mut env = oris::Env::new();
+fn run(file: &str, options: &Options) {
+    let code = read(file);
+
+    // don't run half of the file if there's a syntax error at the bottom
+    let mut env = oris::Env::builder()
+        .with_builtin()
+        .with_parse_first()
+        .build();
 
     match oris::entry(&mut env, &code) {
         Ok(result) => {
@@ -24,36 +122,153 @@ fn run(file: &str) {
             }
         }
         Err(err) => {
-            let (line, column) = err.line_column(&code);
-            eprintln!("[error] {}:{}:{}: {}", file, line + 1, column + 1, err);
-            std::process::exit(1);
+            report(&err, &code, Some(file), options);
+
+            std::process::exit(match err.kind() {
+                oris::ErrorKind::Lex | oris::ErrorKind::Parse => EXIT_SYNTAX,
+                _ => EXIT_FAILURE,
+            });
+        }
+    }
+}
+
+// report all the syntax errors, and with `--types` the type errors if the
+// syntax is valid, without running anything
+fn check(file: &str, types: bool, options: &Options) {
+    let code = read(file);
+
+    let errors = if types {
+        oris::check_types(&code)
+    } else {
+        oris::check(&code)
+    };
+    for err in errors.iter() {
+        report(err, &code, Some(file), options);
+    }
+
+    if let Some(err) = errors.first() {
+        std::process::exit(match err.kind() {
+            oris::ErrorKind::Type => EXIT_FAILURE,
+            _ => EXIT_SYNTAX,
+        });
+    }
+}
+
+// rewrite the files in the canonical style, or only list the ones which are
+// not with `--check`
+fn fmt(files: &[String], check: bool, options: &Options) {
+    let mut exit_code = 0;
+
+    for file in files {
+        let code = read(file);
+
+        let formatted = match oris::format(&code) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                report(&err, &code, Some(file), options);
+                exit_code = EXIT_SYNTAX;
+                continue;
+            }
+        };
+
+        if formatted.as_bytes() == code {
+            continue;
+        }
+
+        if check {
+            println!("{}", file);
+            exit_code = exit_code.max(EXIT_FAILURE);
+        } else if let Err(err) = std::fs::write(file, formatted) {
+            eprintln!("can't write file '{}': {}", file, err);
+            exit_code = exit_code.max(EXIT_FAILURE);
+        }
+    }
+
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+}
+
+// report suspicious code without running it
+fn lint(files: &[String], linter: &oris::Linter, options: &Options) {
+    let mut exit_code = 0;
+
+    for file in files {
+        let code = read(file);
+
+        match linter.lint(&code) {
+            Ok(lints) => {
+                for lint in lints.iter() {
+                    let diagnostic = lint
+                        .diagnostic(&code)
+                        .file(file)
+                        .color(options.color && !options.json)
+                        .json(options.json);
+                    eprint!("{}", diagnostic);
+                }
+
+                if !lints.is_empty() {
+                    exit_code = exit_code.max(EXIT_FAILURE);
+                }
+            }
+            Err(err) => {
+                report(&err, &code, Some(file), options);
+                exit_code = EXIT_SYNTAX;
+            }
         }
     }
+
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
 }
 
-fn repl() {
+fn repl(options: &Options) {
     let mut env = oris::Env::new();
 
+    // Ctrl-C aborts the line being evaluated instead of the whole REPL, and
+    // still ends it at the prompt
+    let prompt = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    #[cfg(unix)]
+    {
+        signal_hook::flag::register_conditional_default(
+            signal_hook::consts::SIGINT,
+            std::sync::Arc::clone(&prompt),
+        )
+        .unwrap();
+        signal_hook::flag::register(
+            signal_hook::consts::SIGINT,
+            std::sync::Arc::clone(env.interrupt_handle().flag()),
+        )
+        .unwrap();
+    }
+
+    // all lines so far, so that positions in closures defined by previous
+    // lines are still valid
+    let mut history = String::new();
+
     let mut stdin = std::io::stdin().lock();
     loop {
         print!(">> ");
         use std::io::Write;
         std::io::stdout().flush().unwrap();
 
-        let mut line = String::new();
+        let start = history.len();
 
         use std::io::BufRead;
-        stdin.read_line(&mut line).unwrap();
-        if line.is_empty() {
+        prompt.store(true, std::sync::atomic::Ordering::Relaxed);
+        stdin.read_line(&mut history).unwrap();
+        prompt.store(false, std::sync::atomic::Ordering::Relaxed);
+        if history.len() == start {
             break;
         }
 
-        match oris::entry(&mut env, line.as_bytes()) {
+        match oris::entry_at(&mut env, history.as_bytes(), start) {
             Ok(result) => {
                 println!("{:?}", result);
             }
             Err(err) => {
-                eprintln!("error: {}", err);
+                report(&err, history.as_bytes(), None, options);
             }
         }
     }
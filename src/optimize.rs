@@ -0,0 +1,217 @@
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    eval::{self, Value, value::closure},
+    parse::{
+        ast::{self, BinaryOp, Expr, Node, UnaryOp},
+        visit::{self, VisitorMut},
+    },
+    span::Span,
+};
+
+// fold the constant expressions of `nodes`, prune the `if` branches decided
+// statically and inline the `let`s of literal values
+//
+// the optimized nodes evaluate to the same result as the original ones, code
+// which fails, e.g. `1 + true`, or overflows is kept as is, so it's reported
+// with its original position once evaluated
+pub(crate) fn optimize(nodes: &mut [Node]) -> eval::resolve::Globals {
+    let mut folder = Folder {
+        frames: vec![HashMap::new()],
+    };
+
+    for node in nodes.iter_mut() {
+        folder.visit_node_mut(node);
+    }
+
+    // the closures capture less with the identifiers inlined
+    eval::resolve::resolve(nodes)
+}
+
+struct Folder {
+    // bindings of a literal value in each frame, an identifier missing in the
+    // frame of a closure is looked up in the globals when the closure is
+    // called, which may have changed since
+    frames: Vec<HashMap<Rc<str>, Value>>,
+}
+
+impl Folder {
+    fn frame(&mut self) -> &mut HashMap<Rc<str>, Value> {
+        self.frames.last_mut().unwrap()
+    }
+
+    // visit code which is evaluated unless `certain` is false, a binding is
+    // known afterwards only if it's the same either way
+    fn branch(&mut self, certain: bool, f: impl FnOnce(&mut Self)) {
+        if certain {
+            return f(self);
+        }
+
+        let before = self.frame().clone();
+        f(self);
+        self.frame()
+            .retain(|sym, value| before.get(sym) == Some(value));
+    }
+
+    // `Some` if the whole `if` is replaced by the expression of the branch
+    // taken
+    fn if_(&mut self, if_: &mut ast::If) -> Option<Expr> {
+        let mut conditioned = Vec::new();
+        let mut alternative = if_.alternative.take();
+
+        for (mut condition, mut block) in std::mem::take(&mut if_.conditioned).into_vec() {
+            // the conditions before are all dropped if none is kept
+            let certain = conditioned.is_empty();
+            self.branch(certain, |this| this.visit_expr_mut(&mut condition));
+
+            match constant(&condition) {
+                Some(Value::Bool(true)) => {
+                    alternative = Some(block);
+                    break;
+                }
+                // any other value is false as in `eval_if()`
+                Some(_) => {}
+                None => {
+                    self.branch(false, |this| this.visit_block_mut(&mut block));
+                    conditioned.push((condition, block));
+                }
+            }
+        }
+
+        if let Some(ref mut block) = alternative {
+            let certain = conditioned.is_empty();
+            self.branch(certain, |this| this.visit_block_mut(block));
+        }
+
+        if conditioned.is_empty() {
+            // `if` blocks have no scope of their own, so a block of a single
+            // expression is the same as the expression
+            let single = alternative.take_if(|block| matches!(*block.nodes, [Node::Expr(_)]));
+            if let Some(Node::Expr(expr)) = single.and_then(|block| block.nodes.into_vec().pop()) {
+                return Some(expr);
+            }
+        }
+
+        if_.conditioned = conditioned.into();
+        if_.alternative = alternative;
+        None
+    }
+}
+
+impl VisitorMut for Folder {
+    fn visit_let_mut(&mut self, let_: &mut ast::Let) {
+        visit::walk_let_mut(self, let_);
+
+        let sym = let_.ident.sym_rc_str().clone();
+        match constant(&let_.value) {
+            Some(value) => self.frame().insert(sym, value),
+            None => self.frame().remove(&sym),
+        };
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        let folded = match expr {
+            Expr::Ident(ident) => {
+                let span = ident.span();
+                self.frame()
+                    .get(ident.sym())
+                    .cloned()
+                    .map(|value| literal(span, value))
+            }
+            Expr::If(if_) => self.if_(if_),
+            Expr::Unary(unary) => {
+                self.visit_expr_mut(&mut unary.value);
+
+                match (unary.op, constant(&unary.value)) {
+                    // `-i32::MIN` overflows
+                    (UnaryOp::Neg, Some(Value::Int(x))) => x.checked_neg().map(Value::Int),
+                    (UnaryOp::Not, Some(Value::Bool(x))) => Some(Value::Bool(!x)),
+                    _ => None,
+                }
+                .map(|value| literal(unary.span, value))
+            }
+            Expr::Binary(binary) => {
+                self.visit_expr_mut(&mut binary.left);
+                self.visit_expr_mut(&mut binary.right);
+
+                match (constant(&binary.left), constant(&binary.right)) {
+                    (Some(left), Some(right)) => binary_(binary.span, left, binary.op, right),
+                    _ => None,
+                }
+            }
+            _ => {
+                visit::walk_expr_mut(self, expr);
+                None
+            }
+        };
+
+        if let Some(folded) = folded {
+            *expr = folded;
+        }
+    }
+
+    fn visit_closure_mut(&mut self, f: &mut ast::Closure) {
+        // the identifiers captured when the closure is created, see
+        // `Closure::new()`, which the params shadow when it's called
+        let captured = closure::analyze_unbounded(f);
+        let mut frame = HashMap::new();
+        for ident in captured {
+            if let Some(value) = self.frame().get(ident.sym()) {
+                frame.insert(ident.sym_rc_str().clone(), value.clone());
+            }
+        }
+        for ident in f.parameters.iter() {
+            frame.remove(ident.sym());
+        }
+
+        self.frames.push(frame);
+        visit::walk_closure_mut(self, f);
+        self.frames.pop();
+    }
+}
+
+fn binary_(span: Span, left: Value, op: BinaryOp, right: Value) -> Option<Expr> {
+    // the arithmetic which would panic is left to the evaluation
+    if let (Value::Int(l), Value::Int(r)) = (&left, &right) {
+        let result = match op {
+            BinaryOp::Add => l.checked_add(*r),
+            BinaryOp::Sub => l.checked_sub(*r),
+            BinaryOp::Mul => l.checked_mul(*r),
+            BinaryOp::Div => l.checked_div(*r),
+            _ => Some(0),
+        };
+        result?;
+    }
+
+    // a concatenation is left to the evaluation, which counts the string
+    // against the memory limit of the `Env`
+    if let (Value::Str(_), BinaryOp::Add) = (&left, op) {
+        return None;
+    }
+
+    eval::binary::eval(&mut Default::default(), span, left, op, right)
+        .ok()
+        .map(|value| literal(span, value))
+}
+
+// value of a literal expression
+fn constant(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Int(int) => Some(Value::Int(int.value)),
+        Expr::Bool(bool) => Some(Value::Bool(bool.value)),
+        Expr::Str(str) => Some(Value::Str(str.value_rc_str().clone())),
+        _ => None,
+    }
+}
+
+fn literal(span: Span, value: Value) -> Expr {
+    match value {
+        Value::Int(value) => Expr::Int(ast::Int { span, value }),
+        Value::Bool(value) => Expr::Bool(ast::Bool { span, value }),
+        Value::Str(value) => Expr::Str(ast::Str::from_src(span, &value)),
+        _ => unreachable!("operators on literals result in literals"),
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    eval::{self, Value},
+    parse::ast::{BinaryOp, Expr, Node},
+    span::Span,
+};
+
+fn parse(code: &str) -> (Vec<Node>, eval::resolve::Globals) {
+    let lexer = crate::lex::Lexer::new(code.as_bytes());
+    let mut nodes = crate::parse::Parser::new(lexer)
+        .collect::<crate::parse::Result<Vec<_>>>()
+        .unwrap();
+    let globals = eval::resolve::resolve(&mut nodes);
+    (nodes, globals)
+}
+
+// optimize `code` after checking it evaluates the same either way
+fn optimize(code: &str) -> Vec<Node> {
+    let (nodes, globals) = parse(code);
+    let mut optimized = nodes.clone();
+    let optimized_globals = super::optimize(&mut optimized);
+
+    let run = |nodes: &[Node], globals: &[std::rc::Rc<str>]| {
+        let mut env = crate::Env::new().env;
+        eval::entry_nodes(&mut env, nodes, globals)
+            .map_err(|error| (error.to_string(), error.span()))
+    };
+    assert_eq!(
+        run(&nodes, &globals),
+        run(&optimized, &optimized_globals),
+        "{}",
+        code
+    );
+
+    optimized
+}
+
+// value of the last node if it's folded into a literal
+fn folded(code: &str) -> Option<Value> {
+    match optimize(code).pop() {
+        Some(Node::Expr(expr)) => super::constant(&expr),
+        _ => None,
+    }
+}
+
+#[test]
+fn fold() {
+    assert_eq!(folded("60 * 60 * 24"), Some(Value::Int(86400)));
+    assert_eq!(folded("-(1 - 3) / 2"), Some(Value::Int(1)));
+    assert_eq!(folded("!(1 < 2)"), Some(Value::Bool(false)));
+    assert_eq!(folded(r#""a" == "a""#), Some(Value::Bool(true)));
+
+    // only partly constant
+    assert_eq!(folded("len([1]) + 1"), None);
+    assert_eq!(folded("[1 + 2][0]"), None);
+}
+
+#[test]
+fn failure() {
+    // still reported once evaluated, at the same position
+    assert_eq!(folded("1 + true"), None);
+    assert_eq!(folded(r#"-"a""#), None);
+    // allocated once evaluated
+    assert_eq!(folded(r#""a" + "b""#), None);
+    assert_eq!(folded(r#""a" + "b" == "ab""#), None);
+    assert_eq!(folded(r#""a" < "b""#), None);
+    assert_eq!(folded("(1 + 2) * (3 < 4)"), None);
+
+    // panics once evaluated
+    let binary =
+        |left, op, right| super::binary_(Span::default(), Value::Int(left), op, Value::Int(right));
+    assert!(binary(i32::MAX, BinaryOp::Add, 1).is_none());
+    assert!(binary(i32::MIN, BinaryOp::Div, -1).is_none());
+    assert!(binary(1, BinaryOp::Div, 0).is_none());
+
+    // never evaluated
+    assert_eq!(
+        folded("let f = fn() { 1 / (2 - 2) }; 3"),
+        Some(Value::Int(3))
+    );
+}
+
+#[test]
+fn if_() {
+    assert_eq!(folded("if true { 1 } else { f() }"), Some(Value::Int(1)));
+    assert_eq!(
+        folded("if 1 > 2 { f() } else { 2 * 3 }"),
+        Some(Value::Int(6))
+    );
+    assert_eq!(
+        folded("if false { 1 } else if true { 2 } else { 3 }"),
+        Some(Value::Int(2))
+    );
+    // not a bool, so not taken
+    assert_eq!(folded("if 1 { 1 } else { 2 }"), Some(Value::Int(2)));
+
+    match &optimize(
+        "let f = fn(x) { if x { 1 } else if false { 2 } else if true { 3 } else { 4 } }; f(false)",
+    )[0]
+    {
+        Node::Stmt(crate::parse::ast::Stmt::Let(let_)) => match &let_.value {
+            Expr::Closure(closure) => match &closure.body.nodes[..] {
+                [Node::Expr(Expr::If(if_))] => {
+                    assert_eq!(if_.conditioned.len(), 1);
+                    let alternative = &if_.alternative.as_ref().unwrap().nodes;
+                    assert!(
+                        matches!(alternative[..], [Node::Expr(Expr::Int(ref int))] if int.value == 3)
+                    );
+                }
+                other => panic!("{:?}", other),
+            },
+            other => panic!("{:?}", other),
+        },
+        other => panic!("{:?}", other),
+    }
+
+    // a block with statements is kept
+    assert_eq!(folded("if true { let a = 1; a }"), None);
+    assert_eq!(folded("if true { let a = 1; } a + 1"), Some(Value::Int(2)));
+}
+
+#[test]
+fn inline() {
+    assert_eq!(
+        folded("let day = 60 * 60 * 24; day * 7"),
+        Some(Value::Int(604800))
+    );
+    assert_eq!(
+        folded(r#"let a = "a"; let b = a; b"#),
+        Some(Value::Str("a".into()))
+    );
+    assert_eq!(folded("let a = 1; let a = [a]; a"), None);
+
+    let (mut nodes, _) = parse("let a = 2; fn() { a * 3 }");
+    super::optimize(&mut nodes);
+    match &nodes[1] {
+        Node::Expr(Expr::Closure(closure)) => assert!(
+            matches!(closure.body.nodes[..], [Node::Expr(Expr::Int(ref int))] if int.value == 6)
+        ),
+        other => panic!("{:?}", other),
+    }
+
+    // captured when the closure is created
+    assert_eq!(
+        folded("let a = 1; let f = fn() { a + 1 }; let a = 2; f()"),
+        None
+    );
+    assert_eq!(folded("let a = 1; fn() { a + 1 }()"), None);
+    assert_eq!(folded("let a = 1; let f = fn(a) { a + 1 }; f(2)"), None);
+
+    // looked up when the closure is called
+    optimize("let f = fn() { a }; let a = 1; let a = 2; f()");
+    optimize("let a = 1; let f = fn() { fn() { a } }; let a = 2; f()()");
+
+    // bound unless the branch is taken
+    assert_eq!(folded("let a = 1; if len([]) > 0 { let a = 2; } a"), None);
+    assert_eq!(
+        folded("let a = 1; if len([]) > 0 { let b = 2; } a"),
+        Some(Value::Int(1))
+    );
+    assert_eq!(
+        folded("let a = 1; if len([]) > 0 { let a = 1; } a"),
+        Some(Value::Int(1))
+    );
+    assert_eq!(
+        folded("let a = 1; let f = fn(x) { if x { let a = 2; } a }; f(true) + f(false)"),
+        None
+    );
+}
+
+#[test]
+fn parity() {
+    // result of running `code` as compiled, then optimized, with a panic of
+    // the evaluation as an outcome too
+    let run = |code: &str, memory_limit: usize| {
+        let program = crate::Program::compile(code.as_bytes()).unwrap();
+        [program.clone(), program.optimize()].map(|program| {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let mut env = crate::Env::builder()
+                    .with_builtin()
+                    .with_memory_limit(memory_limit)
+                    .build();
+                program
+                    .run(&mut env)
+                    .map(|value| format!("{:?}", value))
+                    .map_err(|error| (error.name(), error.span()))
+            }))
+            .map_err(|_| "panicked")
+        })
+    };
+
+    let mut doubled = String::from(r#"let s0 = "a";"#);
+    for i in 1..=22 {
+        doubled += &format!(" let s{} = s{} + s{};", i, i - 1, i - 1);
+    }
+    let [compiled, optimized] = run(&doubled, 1 << 16);
+    assert_eq!(compiled, optimized);
+    assert_eq!(compiled.unwrap().unwrap_err().0, "memory_limit");
+
+    let [compiled, optimized] = run(r#"let a = "ab"; len(a + a + a)"#, 8);
+    assert_eq!(compiled, optimized);
+    assert_eq!(compiled.unwrap().unwrap_err().0, "memory_limit");
+
+    for code in [
+        "1 + 2 * (3 < 4)",
+        "let a = 2147483647; a + (2 - 1)",
+        "let f = fn() { 60 / (24 - 24) }; f()",
+        "if 1 > 2 { 1 / 0 } else { 1 + true }",
+        "let a = 2; if a < 3 { a * 3 }",
+    ] {
+        let [compiled, optimized] = run(code, 1 << 20);
+        assert_eq!(compiled, optimized, "{}", code);
+    }
+}
@@ -1,4 +1,5 @@
 pub(crate) mod ast;
+pub(crate) mod visit;
 mod error;
 mod parser;
 
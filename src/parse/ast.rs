@@ -1,13 +1,18 @@
 use std::rc::Rc;
 
-#[derive(Debug)]
-pub(crate) enum Node {
+use crate::span::Span;
+
+#[derive(Clone, Debug)]
+/// a top-level item of a program or an item in a [`Block`]
+#[non_exhaustive]
+pub enum Node {
     Expr(Expr),
     Stmt(Stmt),
 }
 
-#[derive(Debug)]
-pub(crate) enum Stmt {
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Stmt {
     // let <ident> = <expr>;
     Let(Let),
 
@@ -16,35 +21,39 @@ pub(crate) enum Stmt {
     Return(Return),
 }
 
-#[derive(Debug)]
-pub(crate) struct Let {
-    // position to the let keyword
+#[derive(Clone, Debug)]
+pub struct Let {
+    // span of the whole statement
     //
     // let <ident> = <expr>;
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
 
     pub(crate) ident: Ident,
 
+    // let <ident>: <ty> = <expr>;
+    pub(crate) ty: Option<Type>,
+
     pub(crate) value: Expr,
 }
 
-#[derive(Debug)]
-pub(crate) struct Return {
-    // position to the return keyword
+#[derive(Clone, Debug)]
+pub struct Return {
+    // span of the whole statement
     //
     // return;
-    // ^
+    // ^^^^^^^
     //
     // return <expr>;
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^^
+    pub(crate) span: Span,
 
     pub(crate) value: Option<Expr>,
 }
 
-#[derive(Debug)]
-pub(crate) enum Expr {
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Expr {
     Int(Int),
 
     Bool(Bool),
@@ -57,6 +66,12 @@ pub(crate) enum Expr {
     // `{<key>: <value>, ...}`
     Map(Map),
 
+    // `#{<element>, ...}`
+    Set(Set),
+
+    // `(<element>, ...)`
+    Tuple(Tuple),
+
     Ident(Ident),
 
     // `<base>[<subscript>]`
@@ -78,89 +93,103 @@ pub(crate) enum Expr {
     If(Box<If>),
 }
 
-#[derive(Debug)]
-pub(crate) struct Int {
-    // position to first digit
-    //
+#[derive(Clone, Debug)]
+pub struct Int {
     // 123
-    // ^
-    pub(crate) pos: usize,
+    // ^^^
+    pub(crate) span: Span,
     pub(crate) value: i32,
 }
 
-#[derive(Debug)]
-pub(crate) struct Bool {
-    // position to first character
-    //
+#[derive(Clone, Debug)]
+pub struct Bool {
     // true
-    // ^
+    // ^^^^
     //
     // false
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^
+    pub(crate) span: Span,
     pub(crate) value: bool,
 }
 
-#[derive(Debug)]
-pub(crate) struct Str {
-    // position to left quotation mark
+#[derive(Clone, Debug)]
+pub struct Str {
+    // including the quotation marks
     //
     // "foobar"
-    // ^
-    pos: usize,
+    // ^^^^^^^^
+    span: Span,
     value: Rc<str>,
 }
 
-#[derive(Debug)]
-pub(crate) struct Seq {
-    // position to left bracket token
-    //
+#[derive(Clone, Debug)]
+pub struct Seq {
     // [<element>, ...]
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
     pub(crate) elements: Box<[Expr]>,
 }
 
-#[derive(Debug)]
-pub(crate) struct Map {
-    // position to left brace token
-    //
+#[derive(Clone, Debug)]
+pub struct Map {
     // { <key>: <value> ... }
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
     pub(crate) entries: Box<[(Expr, Expr)]>,
 }
 
 #[derive(Clone, Debug)]
-pub(crate) struct Ident {
-    // position to first character
-    //
+pub struct Set {
+    // #{<element>, ...}
+    // ^^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
+    pub(crate) elements: Box<[Expr]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Tuple {
+    // (<element>, ...)
+    // ^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
+    pub(crate) elements: Box<[Expr]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ident {
     // foobar
-    // ^
-    pos: usize,
+    // ^^^^^^
+    span: Span,
     sym: Rc<str>,
-}
 
-#[derive(Debug)]
-pub(crate) struct Index {
-    // position to the left bracket token
+    // index into the frame of the closure the identifier is in, if it's bound
+    // in that frame, assigned by `eval::resolve()` as the two below
     //
+    // the indices are `u32`s to keep `Expr` small
+    pub(crate) slot: Option<u32>,
+    // index into the captures of the closure the identifier is in, if it's
+    // one of them, read when the slot is not bound
+    pub(crate) upvalue: Option<u32>,
+    // index into the globals of the code, if it's at the top level or a
+    // capture which may not be bound when the closure is created
+    pub(crate) global: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Index {
     // base[subscript]
-    //     ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
 
     pub(crate) base: Expr,
 
     pub(crate) subscript: Expr,
 }
 
-#[derive(Debug)]
-pub(crate) struct Unary {
-    // position to the unary operator
-    //
+#[derive(Clone, Debug)]
+pub struct Unary {
     // <op> <expr>
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^
+    pub(crate) span: Span,
 
     pub(crate) op: UnaryOp,
 
@@ -168,7 +197,8 @@ pub(crate) struct Unary {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub(crate) enum UnaryOp {
+#[non_exhaustive]
+pub enum UnaryOp {
     /// `-`
     Neg,
 
@@ -176,13 +206,11 @@ pub(crate) enum UnaryOp {
     Not,
 }
 
-#[derive(Debug)]
-pub(crate) struct Binary {
-    // position to the binary operator
-    //
+#[derive(Clone, Debug)]
+pub struct Binary {
     // <left> <op> <right>
-    //        ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
 
     pub(crate) left: Expr,
 
@@ -192,7 +220,8 @@ pub(crate) struct Binary {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub(crate) enum BinaryOp {
+#[non_exhaustive]
+pub enum BinaryOp {
     // arithmetic
     Add,
     Sub,
@@ -208,90 +237,372 @@ pub(crate) enum BinaryOp {
     Ge,
 }
 
-#[derive(Debug)]
-pub(crate) struct Closure {
-    // position to the fn keyword
-    //
+#[derive(Clone, Debug)]
+pub struct Closure {
     // fn() { ... }
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^
+    pub(crate) span: Span,
     pub(crate) parameters: Box<[Ident]>,
+
+    // fn(<parameter>: <ty>, ...) -> <ret> { ... }
+    //                 ^^^^             ^^^^^
+    // one for each parameter
+    pub(crate) parameter_types: Box<[Option<Type>]>,
+    pub(crate) ret: Option<Type>,
+
     pub(crate) body: Block,
+
+    // assigned by `eval::resolve()`
+    pub(crate) layout: Layout,
 }
 
-#[derive(Debug)]
-pub(crate) struct Block {
-    // position to the left brace
-    //
+// the frame of a call to a closure
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Layout {
+    // name of each slot, the parameters first
+    pub(crate) slots: Box<[Rc<str>]>,
+
+    // identifiers bound when the closure is created, resolved in the frame
+    // where it's created, by upvalue
+    pub(crate) captures: Box<[Ident]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Block {
     // { ... }
-    // ^
-    pub(crate) pos: usize,
+    // ^^^^^^^
+    pub(crate) span: Span,
 
     pub(crate) nodes: Box<[Node]>,
 }
 
-#[derive(Debug)]
-pub(crate) struct Call {
-    // position to the left parenthesis
-    //
+/// type annotation of a `let` binding, a parameter or the return value of a
+/// closure, checked when the binding is bound or the closure is called
+#[derive(Clone, Debug)]
+pub struct Type {
+    // {str: [int]}
+    // ^^^^^^^^^^^^
+    pub(crate) span: Span,
+    pub(crate) kind: TypeKind,
+}
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum TypeKind {
+    // `int`
+    Int,
+
+    // `bool`
+    Bool,
+
+    // `str`
+    Str,
+
+    // `any`, a value of any type
+    Any,
+
+    // `[<element>]`
+    Seq(Box<Type>),
+
+    // `{<key>: <value>}`
+    Map(Box<Type>, Box<Type>),
+
+    // `#{<element>}`
+    Set(Box<Type>),
+
+    // `(<element>, ...)`
+    Tuple(Box<[Type]>),
+
+    // `fn(<parameter>, ...) -> <ret>`, which returns unit without `-> <ret>`
+    Fn(Box<[Type]>, Option<Box<Type>>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Call {
     // foo(<arg>...)
-    //    ^
-    pub(crate) pos: usize,
+    // ^^^^^^^^^^^^^
+    pub(crate) span: Span,
     pub(crate) target: Expr,
     pub(crate) args: Box<[Expr]>,
 }
 
-#[derive(Debug)]
-pub(crate) struct If {
-    // position to the if keyword
-    //
-    // if <expr> { ... }
-    // ^
-    pub(crate) pos: usize,
+#[derive(Clone, Debug)]
+pub struct If {
+    // if <expr> { ... } else { ... }
+    // ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+    pub(crate) span: Span,
     pub(crate) conditioned: Box<[(Expr, Block)]>,
     pub(crate) alternative: Option<Block>,
 }
 
+impl Node {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Expr(expr) => expr.span(),
+            Self::Stmt(stmt) => stmt.span(),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Let(stmt) => stmt.span,
+            Self::Return(stmt) => stmt.span,
+        }
+    }
+}
+
 impl Expr {
-    pub(crate) fn pos(&self) -> usize {
+    pub fn span(&self) -> Span {
         match self {
-            Self::Int(expr) => expr.pos,
-            Self::Bool(expr) => expr.pos,
-            Self::Str(expr) => expr.pos,
-            Self::Seq(expr) => expr.pos,
-            Self::Map(expr) => expr.pos,
-            Self::Ident(expr) => expr.pos,
-            Self::Index(expr) => expr.pos,
-            Self::Unary(expr) => expr.pos,
-            Self::Binary(expr) => expr.pos,
-            Self::Closure(expr) => expr.pos,
-            Self::Call(expr) => expr.pos,
-            Self::If(expr) => expr.pos,
+            Self::Int(expr) => expr.span,
+            Self::Bool(expr) => expr.span,
+            Self::Str(expr) => expr.span,
+            Self::Seq(expr) => expr.span,
+            Self::Map(expr) => expr.span,
+            Self::Set(expr) => expr.span,
+            Self::Tuple(expr) => expr.span,
+            Self::Ident(expr) => expr.span,
+            Self::Index(expr) => expr.span,
+            Self::Unary(expr) => expr.span,
+            Self::Binary(expr) => expr.span,
+            Self::Closure(expr) => expr.span,
+            Self::Call(expr) => expr.span,
+            Self::If(expr) => expr.span,
         }
     }
 }
 
+impl Let {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn ident(&self) -> &Ident {
+        &self.ident
+    }
+
+    /// annotated type of the binding
+    pub fn ty(&self) -> Option<&Type> {
+        self.ty.as_ref()
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+impl Return {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn value(&self) -> Option<&Expr> {
+        self.value.as_ref()
+    }
+}
+
+impl Int {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+}
+
+impl Bool {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn value(&self) -> bool {
+        self.value
+    }
+}
+
+impl Seq {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn elements(&self) -> &[Expr] {
+        &self.elements
+    }
+}
+
+impl Map {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// `(key, value)` pairs in the order of the source code
+    pub fn entries(&self) -> &[(Expr, Expr)] {
+        &self.entries
+    }
+}
+
+impl Set {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn elements(&self) -> &[Expr] {
+        &self.elements
+    }
+}
+
+impl Tuple {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn elements(&self) -> &[Expr] {
+        &self.elements
+    }
+}
+
+impl Index {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn base(&self) -> &Expr {
+        &self.base
+    }
+
+    pub fn subscript(&self) -> &Expr {
+        &self.subscript
+    }
+}
+
+impl Unary {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn op(&self) -> UnaryOp {
+        self.op
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+impl Binary {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn left(&self) -> &Expr {
+        &self.left
+    }
+
+    pub fn op(&self) -> BinaryOp {
+        self.op
+    }
+
+    pub fn right(&self) -> &Expr {
+        &self.right
+    }
+}
+
+impl Closure {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn parameters(&self) -> &[Ident] {
+        &self.parameters
+    }
+
+    /// annotated type of each parameter, as many as
+    /// [`parameters`](Self::parameters)
+    pub fn parameter_types(&self) -> &[Option<Type>] {
+        &self.parameter_types
+    }
+
+    /// annotated return type
+    pub fn ret(&self) -> Option<&Type> {
+        self.ret.as_ref()
+    }
+
+    pub fn body(&self) -> &Block {
+        &self.body
+    }
+}
+
+impl Block {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+}
+
+impl Call {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn target(&self) -> &Expr {
+        &self.target
+    }
+
+    pub fn args(&self) -> &[Expr] {
+        &self.args
+    }
+}
+
+impl If {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// `(condition, consequence)` pairs of the `if` and each `else if`
+    pub fn conditioned(&self) -> &[(Expr, Block)] {
+        &self.conditioned
+    }
+
+    /// the final `else` block
+    pub fn alternative(&self) -> Option<&Block> {
+        self.alternative.as_ref()
+    }
+}
+
 impl Ident {
-    pub(crate) fn from_str(pos: usize, sym: &str) -> Self {
+    pub(crate) fn from_str(span: Span, sym: &str) -> Self {
         Self {
-            pos,
+            span,
             sym: sym.into(),
+            slot: None,
+            upvalue: None,
+            global: None,
         }
     }
 
     #[cfg(test)]
     pub(crate) fn test(sym: &str) -> Self {
         Self {
-            pos: 0,
+            span: Span::default(),
             sym: sym.into(),
+            slot: None,
+            upvalue: None,
+            global: None,
         }
     }
 
-    pub(crate) fn pos(&self) -> usize {
-        self.pos
+    pub fn span(&self) -> Span {
+        self.span
     }
 
-    pub(crate) fn sym(&self) -> &str {
+    /// name of the identifier
+    pub fn sym(&self) -> &str {
         &self.sym
     }
 
@@ -301,14 +612,19 @@ impl Ident {
 }
 
 impl Str {
-    pub(crate) fn from_src(pos: usize, value: &str) -> Self {
+    pub(crate) fn from_src(span: Span, value: &str) -> Self {
         Self {
-            pos,
+            span,
             value: value.into(),
         }
     }
 
-    pub(crate) fn value(&self) -> &str {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// content of the string literal, without the quotation marks
+    pub fn value(&self) -> &str {
         &self.value
     }
 
@@ -317,6 +633,16 @@ impl Str {
     }
 }
 
+impl Type {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn kind(&self) -> &TypeKind {
+        &self.kind
+    }
+}
+
 impl std::fmt::Display for Ident {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.sym.fmt(f)
@@ -350,3 +676,45 @@ impl std::fmt::Display for BinaryOp {
         })
     }
 }
+
+// in the canonical style, e.g. `{str: [int]}`
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn list(f: &mut std::fmt::Formatter<'_>, types: &[Type]) -> std::fmt::Result {
+            for (i, ty) in types.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                ty.fmt(f)?;
+            }
+            Ok(())
+        }
+
+        match &self.kind {
+            TypeKind::Int => f.write_str("int"),
+            TypeKind::Bool => f.write_str("bool"),
+            TypeKind::Str => f.write_str("str"),
+            TypeKind::Any => f.write_str("any"),
+            TypeKind::Seq(elem) => write!(f, "[{}]", elem),
+            TypeKind::Map(key, value) => write!(f, "{{{}: {}}}", key, value),
+            TypeKind::Set(elem) => write!(f, "#{{{}}}", elem),
+            TypeKind::Tuple(elems) => {
+                f.write_str("(")?;
+                list(f, elems)?;
+                if elems.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            TypeKind::Fn(params, ret) => {
+                f.write_str("fn(")?;
+                list(f, params)?;
+                f.write_str(")")?;
+                match ret {
+                    Some(ret) => write!(f, " -> {}", ret),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
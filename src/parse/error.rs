@@ -1,4 +1,4 @@
-use crate::lex;
+use crate::{lex, span::Span};
 
 #[derive(Debug)]
 pub(crate) enum Error {
@@ -9,7 +9,8 @@ pub(crate) enum Error {
 
 #[derive(Debug)]
 pub(crate) struct Incomplete {
-    pub(crate) pos: usize,
+    // empty span at the end of the input
+    pub(crate) span: Span,
     pub(crate) expected: Expected,
 }
 
@@ -22,15 +23,25 @@ pub(crate) struct Mismatch {
 #[derive(Debug)]
 pub(crate) enum Expected {
     Expr,
+    // type annotation
+    Type,
     Token(lex::token::Kind),
 }
 
 impl Error {
-    pub(crate) fn pos(&self) -> usize {
+    pub(crate) fn span(&self) -> Span {
         match self {
-            Self::Lex(error) => error.pos,
-            Self::Incomplete(incomplete) => incomplete.pos,
-            Self::Mismatch(mismatch) => mismatch.left.pos,
+            Self::Lex(error) => error.span,
+            Self::Incomplete(incomplete) => incomplete.span,
+            Self::Mismatch(mismatch) => mismatch.left.span,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Lex(error) => error.kind.name(),
+            Self::Incomplete(_) => "incomplete",
+            Self::Mismatch(_) => "mismatch",
         }
     }
 }
@@ -49,7 +60,7 @@ impl std::fmt::Display for Error {
                 write!(f, "miss {}", incomplete.expected)
             }
             Error::Mismatch(error) => {
-                write!(f, "expect {}, found {:?}", error.right, error.left)
+                write!(f, "expect {}, found {}", error.right, error.left.kind)
             }
         }
     }
@@ -59,7 +70,8 @@ impl std::fmt::Display for Expected {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Expr => f.write_str("expression"),
-            Self::Token(token) => write!(f, "{:?}", token),
+            Self::Type => f.write_str("type"),
+            Self::Token(kind) => kind.fmt(f),
         }
     }
 }
@@ -1,8 +1,10 @@
 mod expr;
+mod ty;
 
 use crate::{
     lex::{self, token},
     parse::{self, ast, error::Expected},
+    span::Span,
 };
 
 pub(crate) struct Parser<'a> {
@@ -13,6 +15,11 @@ impl<'a> Parser<'a> {
     pub(crate) fn new(lexer: lex::Lexer<'a>) -> Self {
         Self { lexer }
     }
+
+    // comments in the code parsed so far
+    pub(crate) fn comments(&self) -> &[Span] {
+        self.lexer.comments()
+    }
 }
 
 impl Iterator for Parser<'_> {
@@ -30,14 +37,88 @@ impl Iterator for Parser<'_> {
     }
 }
 
+impl Parser<'_> {
+    /// parse all the nodes, instead of stopping at the first error, skip to
+    /// the next statement boundary and continue, so that all the errors are
+    /// reported at once
+    ///
+    /// a statement boundary is a `;` or `}` at the nesting level where the
+    /// error occurs, or the `let` of the next statement
+    pub(crate) fn parse_all(mut self) -> (Vec<ast::Node>, Vec<parse::Error>) {
+        let mut nodes = Vec::new();
+        let mut errors = Vec::new();
+
+        loop {
+            let depth = self.lexer.depth();
+
+            match self.next() {
+                Some(Ok(node)) => nodes.push(node),
+                Some(Err(error)) => {
+                    self.recover(&error, depth);
+                    errors.push(error);
+                }
+                None => break,
+            }
+        }
+
+        (nodes, errors)
+    }
+
+    // skip the rest of the statement which fails with `error`, `depth` is
+    // the nesting level where the statement starts
+    fn recover(&mut self, error: &parse::Error, depth: usize) {
+        // the offending token is consumed already
+        let mut last = match error {
+            parse::Error::Mismatch(mismatch) => Some(mismatch.left.clone()),
+            _ => None,
+        };
+
+        loop {
+            if self.lexer.depth() == depth {
+                match last {
+                    // it starts the next statement, e.g. `let a = let b = 1`
+                    Some(ref tk) if tk.kind == token::Kind::Let => {
+                        self.lexer.rewind(tk.span.start);
+                        return;
+                    }
+                    Some(ref tk) if tk.kind == token::Kind::Semicolon => return,
+                    Some(ref tk) if tk.kind == token::Kind::RightBrace => {
+                        self.skip_optional_semicolon();
+                        return;
+                    }
+                    _ => {}
+                }
+
+                if let Some(Ok(tk)) = self.lexer.peek() {
+                    if tk.kind == token::Kind::Let {
+                        return;
+                    }
+                }
+            }
+
+            match self.lexer.next() {
+                Some(Ok(tk)) => last = Some(tk),
+                Some(Err(_)) => last = None,
+                None => return,
+            }
+        }
+    }
+}
+
 impl<'a> Parser<'a> {
     fn parse_let_stmt(&mut self) -> parse::Result<ast::Stmt> {
-        let pos = self.expect_token(token::Kind::Let)?;
+        let start = self.expect_token(token::Kind::Let)?;
         let ident = self.expect_ident()?;
+        let ty = self.parse_optional_type(token::Kind::Colon)?;
         self.expect_token(token::Kind::Assign)?;
         let value = self.parse_expr()?;
         self.skip_optional_semicolon();
-        Ok(ast::Stmt::Let(ast::Let { pos, ident, value }))
+        Ok(ast::Stmt::Let(ast::Let {
+            span: self.span_from(start),
+            ident,
+            ty,
+            value,
+        }))
     }
 
     fn parse_return_stmt(&mut self) -> parse::Result<ast::Stmt> {
@@ -45,7 +126,7 @@ impl<'a> Parser<'a> {
         // `return <expr>`
         // `return <expr> ;`
 
-        let pos = self.expect_token(token::Kind::Return)?;
+        let start = self.expect_token(token::Kind::Return)?;
 
         let value = match self.lexer.peek() {
             Some(Ok(tk)) if tk.kind == token::Kind::Semicolon => {
@@ -59,7 +140,10 @@ impl<'a> Parser<'a> {
             }
         };
 
-        Ok(ast::Stmt::Return(ast::Return { pos, value }))
+        Ok(ast::Stmt::Return(ast::Return {
+            span: self.span_from(start),
+            value,
+        }))
     }
 
     fn parse_standalone_expr(&mut self) -> parse::Result<ast::Expr> {
@@ -81,8 +165,8 @@ impl<'a> Parser<'a> {
         match self.lexer.next() {
             Some(Ok(tk)) => match tk.kind {
                 token::Kind::Ident => {
-                    let sym = self.lexer.lex_atom(tk.pos);
-                    Ok(ast::Ident::from_str(tk.pos, sym))
+                    let sym = self.lexer.lex_atom(tk.span.start);
+                    Ok(ast::Ident::from_str(tk.span, sym))
                 }
                 _ => Err(parse::Error::Mismatch(parse::error::Mismatch {
                     left: tk,
@@ -91,19 +175,19 @@ impl<'a> Parser<'a> {
             },
             Some(Err(err)) => Err(err.into()),
             None => Err(parse::Error::Incomplete(parse::error::Incomplete {
-                pos,
+                span: Span::at(pos),
                 expected: Expected::Token(token::Kind::Ident),
             })),
         }
     }
 
-    fn expect_token(&mut self, expected: token::Kind) -> parse::Result<usize> {
+    fn expect_token(&mut self, expected: token::Kind) -> parse::Result<Span> {
         let pos = self.lexer.pos();
 
         match self.lexer.next() {
             Some(Ok(next_token)) => {
                 if next_token.kind == expected {
-                    Ok(next_token.pos)
+                    Ok(next_token.span)
                 } else {
                     Err(parse::Error::Mismatch(parse::error::Mismatch {
                         left: next_token,
@@ -113,12 +197,17 @@ impl<'a> Parser<'a> {
             }
             Some(Err(err)) => Err(err.into()),
             None => Err(parse::Error::Incomplete(parse::error::Incomplete {
-                pos,
+                span: Span::at(pos),
                 expected: Expected::Token(expected),
             })),
         }
     }
 
+    // from the start of `start` to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        Span::new(start.start, self.lexer.prev_end())
+    }
+
     fn skip_optional_semicolon(&mut self) {
         if let Some(Ok(tk)) = self.lexer.peek() {
             if tk.kind == token::Kind::Semicolon {
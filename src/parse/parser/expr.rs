@@ -1,6 +1,7 @@
 use crate::{
     lex::token::{self, Token},
     parse::{self, ast, error::Expected, Parser},
+    span::Span,
 };
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -43,7 +44,7 @@ impl<'a> Parser<'a> {
         let pos = self.lexer.pos();
         let token = self.lexer.next().ok_or_else(|| {
             parse::Error::Incomplete(parse::error::Incomplete {
-                pos,
+                span: Span::at(pos),
                 expected: Expected::Expr,
             })
         })??;
@@ -70,42 +71,67 @@ impl<'a> Parser<'a> {
     fn parse_prefix_expr(&mut self, token: Token) -> parse::Result<ast::Expr> {
         match token.kind {
             token::Kind::True => Ok(ast::Expr::Bool(ast::Bool {
-                pos: token.pos,
+                span: token.span,
                 value: true,
             })),
             token::Kind::False => Ok(ast::Expr::Bool(ast::Bool {
-                pos: token.pos,
+                span: token.span,
                 value: false,
             })),
             token::Kind::Int => Ok(ast::Expr::Int(ast::Int {
-                pos: token.pos,
+                span: token.span,
                 value: self
                     .lexer
-                    .lex_int(token.pos)
+                    .lex_int(token.span.start)
                     .map(|(num, _cursor)| num)
                     .unwrap(),
             })),
             token::Kind::Str => Ok(self
                 .lexer
-                .lex_str(token.pos)
-                .map(|s| ast::Str::from_src(token.pos, s))
+                .lex_str(token.span.start)
+                .map(|s| ast::Str::from_src(token.span, s))
                 .map(ast::Expr::Str)
                 .unwrap()),
             token::Kind::LeftParen => {
                 let expr = self.parse_expr()?;
-                self.expect_token(token::Kind::RightParen)?;
-                Ok(expr)
+
+                match self.lexer.peek() {
+                    Some(Ok(tk)) if tk.kind == token::Kind::Comma => {
+                        // tuple
+                        //
+                        // (<element>, ...)
+                        // (<element>,)
+
+                        let _comma = self.lexer.next();
+
+                        let mut elements = vec![expr];
+                        elements.extend(self.parse_separated_with(
+                            token::Kind::Comma,
+                            token::Kind::RightParen,
+                            |parser| parser.parse_expr(),
+                        )?);
+
+                        Ok(ast::Expr::Tuple(ast::Tuple {
+                            span: self.span_from(token.span),
+                            elements: elements.into_boxed_slice(),
+                        }))
+                    }
+                    _ => {
+                        self.expect_token(token::Kind::RightParen)?;
+                        Ok(expr)
+                    }
+                }
             }
             token::Kind::Fn => self
-                .parse_function_expression(token.pos)
+                .parse_function_expression(token.span)
                 .map(std::rc::Rc::new)
                 .map(ast::Expr::Closure),
             token::Kind::Ident => Ok(ast::Expr::Ident(ast::Ident::from_str(
-                token.pos,
-                self.lexer.lex_atom(token.pos),
+                token.span,
+                self.lexer.lex_atom(token.span.start),
             ))),
             token::Kind::If => self
-                .parse_if_expr(token.pos)
+                .parse_if_expr(token.span)
                 .map(Box::new)
                 .map(ast::Expr::If),
             token::Kind::LeftBracket => self
@@ -115,20 +141,20 @@ impl<'a> Parser<'a> {
                 .map(Vec::into_boxed_slice)
                 .map(|elements| {
                     ast::Expr::Seq(ast::Seq {
-                        pos: token.pos,
+                        span: self.span_from(token.span),
                         elements,
                     })
                 }),
             token::Kind::Hyphen => self.parse_expr().map(|expr| {
                 ast::Expr::Unary(Box::new(ast::Unary {
-                    pos: token.pos,
+                    span: token.span.to(expr.span()),
                     op: ast::UnaryOp::Neg,
                     value: expr,
                 }))
             }),
             token::Kind::Bang => self.parse_expr().map(|expr| {
                 ast::Expr::Unary(Box::new(ast::Unary {
-                    pos: token.pos,
+                    span: token.span.to(expr.span()),
                     op: ast::UnaryOp::Not,
                     value: expr,
                 }))
@@ -147,11 +173,22 @@ impl<'a> Parser<'a> {
                 .map(Vec::into_boxed_slice)
                 .map(|entries| {
                     ast::Expr::Map(ast::Map {
-                        pos: token.pos,
+                        span: self.span_from(token.span),
                         entries,
                     })
                 })
             }
+            token::Kind::HashLeftBrace => self
+                .parse_separated_with(token::Kind::Comma, token::Kind::RightBrace, |parser| {
+                    parser.parse_expr()
+                })
+                .map(Vec::into_boxed_slice)
+                .map(|elements| {
+                    ast::Expr::Set(ast::Set {
+                        span: self.span_from(token.span),
+                        elements,
+                    })
+                }),
             _ => Err(parse::Error::Mismatch(parse::error::Mismatch {
                 left: token,
                 right: Expected::Expr,
@@ -162,7 +199,6 @@ impl<'a> Parser<'a> {
     fn parse_infix_expr(&mut self, left: ast::Expr) -> parse::Result<ast::Expr> {
         fn binary_expr(
             self_: &mut Parser<'_>,
-            pos: usize,
             left: ast::Expr,
             op: ast::BinaryOp,
             precedence: Precedence,
@@ -170,7 +206,7 @@ impl<'a> Parser<'a> {
             let right = self_.parse_expr_again(precedence)?;
 
             Ok(ast::Expr::Binary(Box::new(ast::Binary {
-                pos,
+                span: left.span().to(right.span()),
                 left,
                 op,
                 right,
@@ -181,41 +217,23 @@ impl<'a> Parser<'a> {
         let tk = self.lexer.next().unwrap().unwrap();
 
         match tk.kind {
-            token::Kind::Plus => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Add, Precedence::AddSub)
-            }
-            token::Kind::Hyphen => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Sub, Precedence::AddSub)
-            }
+            token::Kind::Plus => binary_expr(self, left, ast::BinaryOp::Add, Precedence::AddSub),
+            token::Kind::Hyphen => binary_expr(self, left, ast::BinaryOp::Sub, Precedence::AddSub),
             token::Kind::Asterisk => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Mul, Precedence::MulDiv)
-            }
-            token::Kind::Slash => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Div, Precedence::MulDiv)
-            }
-            token::Kind::Eq => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Eq, Precedence::Compare)
-            }
-            token::Kind::Ne => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Ne, Precedence::Compare)
-            }
-            token::Kind::Lt => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Lt, Precedence::Compare)
-            }
-            token::Kind::Le => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Le, Precedence::Compare)
-            }
-            token::Kind::Gt => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Gt, Precedence::Compare)
-            }
-            token::Kind::Ge => {
-                binary_expr(self, tk.pos, left, ast::BinaryOp::Ge, Precedence::Compare)
+                binary_expr(self, left, ast::BinaryOp::Mul, Precedence::MulDiv)
             }
+            token::Kind::Slash => binary_expr(self, left, ast::BinaryOp::Div, Precedence::MulDiv),
+            token::Kind::Eq => binary_expr(self, left, ast::BinaryOp::Eq, Precedence::Compare),
+            token::Kind::Ne => binary_expr(self, left, ast::BinaryOp::Ne, Precedence::Compare),
+            token::Kind::Lt => binary_expr(self, left, ast::BinaryOp::Lt, Precedence::Compare),
+            token::Kind::Le => binary_expr(self, left, ast::BinaryOp::Le, Precedence::Compare),
+            token::Kind::Gt => binary_expr(self, left, ast::BinaryOp::Gt, Precedence::Compare),
+            token::Kind::Ge => binary_expr(self, left, ast::BinaryOp::Ge, Precedence::Compare),
             token::Kind::LeftParen => {
                 // call expr
                 //
                 // <f>(<arg>...)
-                self.parse_call_expr(tk.pos, left)
+                self.parse_call_expr(left)
             }
             token::Kind::LeftBracket => {
                 // index expr
@@ -225,7 +243,7 @@ impl<'a> Parser<'a> {
                 let subscript = self.parse_expr()?;
                 self.expect_token(token::Kind::RightBracket)?;
                 Ok(ast::Expr::Index(Box::new(ast::Index {
-                    pos: tk.pos,
+                    span: self.span_from(left.span()),
                     base: left,
                     subscript,
                 })))
@@ -240,27 +258,37 @@ impl<'a> Parser<'a> {
 
 impl<'a> Parser<'a> {
     // with token `fn` skipped
-    fn parse_function_expression(&mut self, pos: usize) -> parse::Result<ast::Closure> {
+    fn parse_function_expression(&mut self, start: Span) -> parse::Result<ast::Closure> {
         // fn(<parameter...>) { <statement...> }
+        // fn(<parameter>: <ty>, ...) -> <ty> { <statement...> }
 
         self.expect_token(token::Kind::LeftParen)?;
 
-        let parameters =
-            self.parse_separated_with(token::Kind::Comma, token::Kind::RightParen, |parser| {
-                parser.expect_ident()
-            })?;
+        let (parameters, parameter_types): (Vec<_>, Vec<_>) = self
+            .parse_separated_with(token::Kind::Comma, token::Kind::RightParen, |parser| {
+                let ident = parser.expect_ident()?;
+                let ty = parser.parse_optional_type(token::Kind::Colon)?;
+                Ok((ident, ty))
+            })?
+            .into_iter()
+            .unzip();
+
+        let ret = self.parse_optional_type(token::Kind::Arrow)?;
 
         let body = self.parse_block()?;
 
         Ok(ast::Closure {
-            pos,
+            span: self.span_from(start),
             parameters: parameters.into_boxed_slice(),
+            parameter_types: parameter_types.into_boxed_slice(),
+            ret,
             body,
+            layout: Default::default(),
         })
     }
 
     // with token `(` skipped
-    fn parse_call_expr(&mut self, pos: usize, f: ast::Expr) -> parse::Result<ast::Expr> {
+    fn parse_call_expr(&mut self, f: ast::Expr) -> parse::Result<ast::Expr> {
         let args = self
             .parse_separated_with(token::Kind::Comma, token::Kind::RightParen, |parser| {
                 parser.parse_expr()
@@ -268,14 +296,14 @@ impl<'a> Parser<'a> {
             .map(Vec::into_boxed_slice)?;
 
         Ok(ast::Expr::Call(Box::new(ast::Call {
-            pos,
+            span: self.span_from(f.span()),
             target: f,
             args,
         })))
     }
 
     // with token `if` skipped
-    fn parse_if_expr(&mut self, pos: usize) -> parse::Result<ast::If> {
+    fn parse_if_expr(&mut self, start: Span) -> parse::Result<ast::If> {
         let mut conditioned = Vec::new();
 
         let alternative = loop {
@@ -300,7 +328,7 @@ impl<'a> Parser<'a> {
         };
 
         Ok(ast::If {
-            pos,
+            span: self.span_from(start),
             conditioned: conditioned.into_boxed_slice(),
             alternative,
         })
@@ -308,7 +336,7 @@ impl<'a> Parser<'a> {
 }
 
 impl<'a> Parser<'a> {
-    fn parse_separated_with<F, T>(
+    pub(super) fn parse_separated_with<F, T>(
         &mut self,
         separater: token::Kind,
         end: token::Kind,
@@ -349,7 +377,7 @@ impl<'a> Parser<'a> {
                 Some(Err(err)) => return Err(err.into()),
                 None => {
                     return Err(parse::Error::Incomplete(parse::error::Incomplete {
-                        pos,
+                        span: Span::at(pos),
                         expected: Expected::Token(end),
                     }));
                 }
@@ -358,7 +386,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_block(&mut self) -> parse::Result<ast::Block> {
-        self.expect_token(token::Kind::LeftBrace)?;
+        let start = self.expect_token(token::Kind::LeftBrace)?;
 
         let mut nodes = Vec::new();
 
@@ -366,7 +394,7 @@ impl<'a> Parser<'a> {
             let pos = self.lexer.pos();
             match self.lexer.peek().ok_or_else(|| {
                 parse::Error::Incomplete(parse::error::Incomplete {
-                    pos,
+                    span: Span::at(pos),
                     expected: Expected::Token(token::Kind::RightBrace),
                 })
             })? {
@@ -375,7 +403,7 @@ impl<'a> Parser<'a> {
                     self.lexer.next().unwrap().unwrap(); // skip `}`
 
                     return Ok(ast::Block {
-                        pos,
+                        span: self.span_from(start),
                         nodes: nodes.into_boxed_slice(),
                     });
                 }
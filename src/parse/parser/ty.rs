@@ -0,0 +1,110 @@
+use crate::{
+    lex::token,
+    parse::{self, ast, error::Expected, Parser},
+    span::Span,
+};
+
+impl<'a> Parser<'a> {
+    // int | bool | str | any
+    // [<ty>] | {<ty>: <ty>} | #{<ty>} | (<ty>, ...)
+    // fn(<ty>, ...) -> <ty>
+    pub(super) fn parse_type(&mut self) -> parse::Result<ast::Type> {
+        let pos = self.lexer.pos();
+        let token = self.lexer.next().ok_or_else(|| {
+            parse::Error::Incomplete(parse::error::Incomplete {
+                span: Span::at(pos),
+                expected: Expected::Type,
+            })
+        })??;
+
+        let kind = match token.kind {
+            token::Kind::Ident => match self.lexer.lex_atom(token.span.start) {
+                "int" => ast::TypeKind::Int,
+                "bool" => ast::TypeKind::Bool,
+                "str" => ast::TypeKind::Str,
+                "any" => ast::TypeKind::Any,
+                _ => {
+                    return Err(parse::Error::Mismatch(parse::error::Mismatch {
+                        left: token,
+                        right: Expected::Type,
+                    }));
+                }
+            },
+            token::Kind::LeftBracket => {
+                let elem = self.parse_type()?;
+                self.expect_token(token::Kind::RightBracket)?;
+                ast::TypeKind::Seq(Box::new(elem))
+            }
+            token::Kind::LeftBrace => {
+                let key = self.parse_type()?;
+                self.expect_token(token::Kind::Colon)?;
+                let value = self.parse_type()?;
+                self.expect_token(token::Kind::RightBrace)?;
+                ast::TypeKind::Map(Box::new(key), Box::new(value))
+            }
+            token::Kind::HashLeftBrace => {
+                let elem = self.parse_type()?;
+                self.expect_token(token::Kind::RightBrace)?;
+                ast::TypeKind::Set(Box::new(elem))
+            }
+            token::Kind::LeftParen => {
+                let first = self.parse_type()?;
+
+                match self.lexer.peek() {
+                    Some(Ok(tk)) if tk.kind == token::Kind::Comma => {
+                        let _comma = self.lexer.next();
+
+                        let mut elements = vec![first];
+                        elements.extend(self.parse_separated_with(
+                            token::Kind::Comma,
+                            token::Kind::RightParen,
+                            |parser| parser.parse_type(),
+                        )?);
+                        ast::TypeKind::Tuple(elements.into_boxed_slice())
+                    }
+                    // grouped as `(<expr>)`
+                    _ => {
+                        self.expect_token(token::Kind::RightParen)?;
+                        first.kind
+                    }
+                }
+            }
+            token::Kind::Fn => {
+                self.expect_token(token::Kind::LeftParen)?;
+                let params = self.parse_separated_with(
+                    token::Kind::Comma,
+                    token::Kind::RightParen,
+                    |parser| parser.parse_type(),
+                )?;
+                let ret = self.parse_optional_type(token::Kind::Arrow)?;
+                ast::TypeKind::Fn(params.into_boxed_slice(), ret.map(Box::new))
+            }
+            _ => {
+                return Err(parse::Error::Mismatch(parse::error::Mismatch {
+                    left: token,
+                    right: Expected::Type,
+                }));
+            }
+        };
+
+        Ok(ast::Type {
+            span: self.span_from(token.span),
+            kind,
+        })
+    }
+
+    // `<prefix> <ty>` if the next token is `prefix`, e.g. `: int` after a
+    // parameter
+    pub(super) fn parse_optional_type(
+        &mut self,
+        prefix: token::Kind,
+    ) -> parse::Result<Option<ast::Type>> {
+        match self.lexer.peek() {
+            Some(Ok(tk)) if tk.kind == prefix => {
+                let _prefix = self.lexer.next();
+                self.parse_type().map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
@@ -2,29 +2,36 @@ mod expr;
 mod stmt;
 
 use super::ast::*;
+use crate::span::Span;
 
 fn bool(value: bool) -> Expr {
-    Expr::Bool(Bool { pos: 0, value })
+    Expr::Bool(Bool {
+        span: Span::default(),
+        value,
+    })
 }
 
 fn int(value: i32) -> Expr {
-    Expr::Int(Int { pos: 0, value })
+    Expr::Int(Int {
+        span: Span::default(),
+        value,
+    })
 }
 
 fn str(value: &str) -> Expr {
-    Expr::Str(Str::from_src(0, value))
+    Expr::Str(Str::from_src(Span::default(), value))
 }
 
 fn seq(elements: Vec<Expr>) -> Expr {
     Expr::Seq(Seq {
-        pos: 0,
+        span: Span::default(),
         elements: elements.into_boxed_slice(),
     })
 }
 
 fn binary(left: Expr, op: BinaryOp, right: Expr) -> Expr {
     Expr::Binary(Box::new(Binary {
-        pos: 0,
+        span: Span::default(),
         left,
         op,
         right,
@@ -36,7 +43,7 @@ where
     Node: From<N>,
 {
     Block {
-        pos: 0,
+        span: Span::default(),
         nodes: nodes
             .into_iter()
             .map(Node::from)
@@ -58,7 +65,7 @@ impl From<Expr> for Node {
 }
 
 fn parse(input: &'static str) -> crate::parse::Result<Vec<Node>> {
-    let lexer = crate::lex::Lexer::new(input.as_bytes().into());
+    let lexer = crate::lex::Lexer::new(input.as_bytes());
     let parser = crate::parse::Parser::new(lexer);
     parser.collect()
 }
@@ -74,7 +81,9 @@ fn pic_eq_node(left: &Node, right: &Node) -> bool {
 fn pic_eq_stmt(left: &Stmt, right: &Stmt) -> bool {
     match (left, right) {
         (Stmt::Let(left), Stmt::Let(right)) => {
-            left.ident.sym() == right.ident.sym() && pic_eq_expr(&left.value, &right.value)
+            left.ident.sym() == right.ident.sym()
+                && pic_eq_opt(left.ty.as_ref(), right.ty.as_ref(), pic_eq_type)
+                && pic_eq_expr(&left.value, &right.value)
         }
         (Stmt::Return(left), Stmt::Return(right)) => {
             pic_eq_opt(left.value.as_ref(), right.value.as_ref(), pic_eq_expr)
@@ -98,6 +107,12 @@ fn pic_eq_expr(left: &Expr, right: &Expr) -> bool {
                 pic_eq_expr(left_key, right_key) && pic_eq_expr(left_value, right_value)
             },
         ),
+        (Expr::Set(left), Expr::Set(right)) => {
+            pic_eq_slice(&left.elements, &right.elements, pic_eq_expr)
+        }
+        (Expr::Tuple(left), Expr::Tuple(right)) => {
+            pic_eq_slice(&left.elements, &right.elements, pic_eq_expr)
+        }
         (Expr::Ident(left), Expr::Ident(right)) => left.sym() == right.sym(),
         (Expr::Index(left), Expr::Index(right)) => {
             pic_eq_expr(&left.base, &right.base) && pic_eq_expr(&left.subscript, &right.subscript)
@@ -111,9 +126,12 @@ fn pic_eq_expr(left: &Expr, right: &Expr) -> bool {
                 && pic_eq_expr(&left.right, &right.right)
         }
         (Expr::Closure(left), Expr::Closure(right)) => {
-            pic_eq_slice(&*left.parameters, &*right.parameters, |left, right| {
+            pic_eq_slice(&left.parameters, &right.parameters, |left, right| {
                 left.sym() == right.sym()
-            }) && pic_eq_block(&left.body, &right.body)
+            }) && pic_eq_slice(&left.parameter_types, &right.parameter_types, |left, right| {
+                pic_eq_opt(left.as_ref(), right.as_ref(), pic_eq_type)
+            }) && pic_eq_opt(left.ret.as_ref(), right.ret.as_ref(), pic_eq_type)
+                && pic_eq_block(&left.body, &right.body)
         }
         (Expr::Call(left), Expr::Call(right)) => {
             pic_eq_expr(&left.target, &right.target)
@@ -137,6 +155,11 @@ fn pic_eq_expr(left: &Expr, right: &Expr) -> bool {
     }
 }
 
+// types are printed in the canonical style without spans
+fn pic_eq_type(left: &Type, right: &Type) -> bool {
+    left.to_string() == right.to_string()
+}
+
 fn pic_eq_block(left: &Block, right: &Block) -> bool {
     pic_eq_slice(&left.nodes, &right.nodes, pic_eq_node)
 }
@@ -161,3 +184,25 @@ where
         (None, None) => true,
     }
 }
+
+#[test]
+fn visit_mut() {
+    struct Scale;
+
+    impl crate::parse::visit::VisitorMut for Scale {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            match expr {
+                Expr::Int(int) => int.value *= 10,
+                _ => crate::parse::visit::walk_expr_mut(self, expr),
+            }
+        }
+    }
+
+    let mut nodes = parse("let f = fn(x) { [1, x + 2] }; f(3)").unwrap();
+    for node in nodes.iter_mut() {
+        crate::parse::visit::VisitorMut::visit_node_mut(&mut Scale, node);
+    }
+
+    let expected = parse("let f = fn(x) { [10, x + 20] }; f(30)").unwrap();
+    assert!(pic_eq_slice(&nodes, &expected, pic_eq_node));
+}
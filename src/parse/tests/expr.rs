@@ -5,7 +5,11 @@ fn ident(name: &str) -> Expr {
 }
 
 fn unary(op: UnaryOp, value: Expr) -> Expr {
-    Expr::Unary(Box::new(Unary { pos: 0, op, value }))
+    Expr::Unary(Box::new(Unary {
+        span: Span::default(),
+        op,
+        value,
+    }))
 }
 
 fn pic_eq(left: &Node, right: &Expr) -> bool {
@@ -68,7 +72,7 @@ fn map_() {
     t!(
         "{1: a, true: \"b\", \"three\": 3}",
         Expr::Map(Map {
-            pos: 0,
+            span: Span::default(),
             entries: vec![
                 (int(1), ident("a")),
                 (bool(true), str("b")),
@@ -79,6 +83,46 @@ fn map_() {
     );
 }
 
+#[test]
+fn set_() {
+    t!(
+        "#{}",
+        Expr::Set(Set {
+            span: Span::default(),
+            elements: [].into()
+        })
+    );
+    t!(
+        "#{1, a}",
+        Expr::Set(Set {
+            span: Span::default(),
+            elements: [int(1), ident("a")].into()
+        })
+    );
+}
+
+#[test]
+fn tuple_() {
+    t!(
+        "(1, a)",
+        Expr::Tuple(Tuple {
+            span: Span::default(),
+            elements: [int(1), ident("a")].into()
+        })
+    );
+    t!(
+        "(1,)",
+        Expr::Tuple(Tuple {
+            span: Span::default(),
+            elements: [int(1)].into()
+        })
+    );
+    t!("(1)", int(1));
+
+    assert!(parse("()").is_err());
+    assert!(parse("(1, 2").is_err());
+}
+
 #[test]
 fn ident_() {
     t!("a", ident("a"));
@@ -145,7 +189,7 @@ fn if_() {
     t!(
         "if a { 1 } else { 2 }",
         Expr::If(Box::new(If {
-            pos: 0,
+            span: Span::default(),
             conditioned: [(ident("a"), block(vec![int(1)]))].into(),
             alternative: Some(block(vec![int(2)]))
         }))
@@ -153,7 +197,7 @@ fn if_() {
     t!(
         "if a { 1 } else if b { 2 } else if c { 3 } else { 4 }",
         Expr::If(Box::new(If {
-            pos: 0,
+            span: Span::default(),
             conditioned: [
                 (ident("a"), block(vec![int(1)])),
                 (ident("b"), block(vec![int(2)])),
@@ -164,3 +208,25 @@ fn if_() {
         }))
     );
 }
+
+#[test]
+fn span() {
+    let code = "(-x) + foo(1, [2])[0]";
+
+    let nodes = parse(code).unwrap();
+    let expr = match &nodes[..] {
+        [Node::Expr(Expr::Binary(binary))] => binary,
+        other => panic!("{:?}", other),
+    };
+
+    let text = |span: Span| &code[span.start..span.end];
+
+    assert_eq!(text(expr.span), &code[1..]);
+    assert_eq!(text(expr.left.span()), "-x");
+    assert_eq!(text(expr.right.span()), "foo(1, [2])[0]");
+
+    match &expr.right {
+        Expr::Index(index) => assert_eq!(text(index.base.span()), "foo(1, [2])"),
+        other => panic!("{:?}", other),
+    }
+}
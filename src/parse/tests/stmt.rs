@@ -29,32 +29,36 @@ fn let_() {
     t!(
         "let a = 42;",
         Stmt::Let(Let {
-            pos: 0,
+            span: Span::default(),
             ident: ident("a"),
+            ty: None,
             value: int(42)
         })
     );
     t!(
         "let b = true;",
         Stmt::Let(Let {
-            pos: 0,
+            span: Span::default(),
             ident: ident("b"),
+            ty: None,
             value: bool(true)
         })
     );
     t!(
         "let c = false;",
         Stmt::Let(Let {
-            pos: 0,
+            span: Span::default(),
             ident: ident("c"),
+            ty: None,
             value: bool(false)
         })
     );
     t!(
         "let math = 1 + 4 * 3 - 6 / 2;",
         Stmt::Let(Let {
-            pos: 0,
+            span: Span::default(),
             ident: ident("math"),
+            ty: None,
             value: binary(
                 binary(int(1), BinaryOp::Add, binary(int(4), BinaryOp::Mul, int(3))),
                 BinaryOp::Sub,
@@ -65,13 +69,17 @@ fn let_() {
     t!(
         "let always_five = fn() { 5 };",
         Stmt::Let(Let {
-            pos: 0,
+            span: Span::default(),
             ident: ident("always_five"),
+            ty: None,
             value: Expr::Closure(
                 Closure {
-                    pos: 0,
+                    span: Span::default(),
                     parameters: Box::new([]),
+                    parameter_types: Box::new([]),
+                    ret: None,
                     body: block(vec![Node::Expr(int(5))]),
+                    layout: Default::default(),
                 }
                 .into()
             )
@@ -80,17 +88,21 @@ fn let_() {
     t!(
         "let add = fn(x, y) { x + y };",
         Stmt::Let(Let {
-            pos: 0,
+            span: Span::default(),
             ident: ident("add"),
+            ty: None,
             value: Expr::Closure(
                 Closure {
-                    pos: 0,
+                    span: Span::default(),
                     parameters: [ident("x"), ident("y")].into(),
+                    parameter_types: [None, None].into(),
+                    ret: None,
                     body: block(vec![binary(
                         Expr::Ident(ident("x")),
                         BinaryOp::Add,
                         Expr::Ident(ident("y")),
                     )]),
+                    layout: Default::default(),
                 }
                 .into()
             )
@@ -98,19 +110,82 @@ fn let_() {
     );
 }
 
+#[test]
+fn annotation() {
+    fn ty(kind: TypeKind) -> Type {
+        Type {
+            span: Span::default(),
+            kind,
+        }
+    }
+
+    let seq_of = |kind| ty(TypeKind::Seq(Box::new(ty(kind))));
+
+    t!(
+        "let f: fn(int, any) -> [int] = fn(x: int, y) -> [int] { [x] };",
+        Stmt::Let(Let {
+            span: Span::default(),
+            ident: ident("f"),
+            ty: Some(ty(TypeKind::Fn(
+                [ty(TypeKind::Int), ty(TypeKind::Any)].into(),
+                Some(Box::new(seq_of(TypeKind::Int)))
+            ))),
+            value: Expr::Closure(
+                Closure {
+                    span: Span::default(),
+                    parameters: [ident("x"), ident("y")].into(),
+                    parameter_types: [Some(ty(TypeKind::Int)), None].into(),
+                    ret: Some(seq_of(TypeKind::Int)),
+                    body: block(vec![seq(vec![Expr::Ident(ident("x"))])]),
+                    layout: Default::default(),
+                }
+                .into()
+            )
+        })
+    );
+    t!(
+        r#"let m: {str: (int, #{bool})} = {};"#,
+        Stmt::Let(Let {
+            span: Span::default(),
+            ident: ident("m"),
+            ty: Some(ty(TypeKind::Map(
+                Box::new(ty(TypeKind::Str)),
+                Box::new(ty(TypeKind::Tuple(
+                    [
+                        ty(TypeKind::Int),
+                        ty(TypeKind::Set(Box::new(ty(TypeKind::Bool))))
+                    ]
+                    .into()
+                )))
+            ))),
+            value: Expr::Map(Map {
+                span: Span::default(),
+                entries: Box::new([]),
+            })
+        })
+    );
+
+    assert_eq!(
+        parse("let a: float = 1;").unwrap_err().to_string(),
+        "expect type, found identifier"
+    );
+    assert!(parse("let a: [int = [];").is_err());
+    assert!(parse("fn(x:) { x }").is_err());
+}
+
 #[test]
 fn return_() {
     t!(
         "return;",
         Stmt::Return(Return {
-            pos: 0,
+            span: Span::default(),
             value: None
         })
     );
     t!(
         "return 1;",
         Stmt::Return(Return {
-            pos: 0,
+            span: Span::default(),
             value: Some(int(1))
         })
     );
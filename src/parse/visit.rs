@@ -0,0 +1,344 @@
+use std::rc::Rc;
+
+use crate::parse::ast::*;
+
+/// walk the AST by reference
+///
+/// each method visits the children of its node by default, override it to
+/// do something before or after that by calling the `walk_*` function of the
+/// same name, or to skip the children by not calling it
+///
+/// ```rust
+/// use oris::syntax::{self, Visitor};
+///
+/// #[derive(Default)]
+/// struct Calls(usize);
+///
+/// impl<'ast> Visitor<'ast> for Calls {
+///     fn visit_call(&mut self, call: &'ast syntax::Call) {
+///         self.0 += 1;
+///         syntax::walk_call(self, call);
+///     }
+/// }
+///
+/// let nodes = syntax::parse(b"f(g(1), 2); h()").unwrap();
+///
+/// let mut calls = Calls::default();
+/// for node in nodes.iter() {
+///     calls.visit_node(node);
+/// }
+/// assert_eq!(calls.0, 3);
+/// ```
+pub trait Visitor<'ast> {
+    fn visit_node(&mut self, node: &'ast Node) {
+        walk_node(self, node)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    /// the ident being bound is not visited by default, see
+    /// [`visit_ident`](Self::visit_ident)
+    fn visit_let(&mut self, let_: &'ast Let) {
+        walk_let(self, let_)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr)
+    }
+
+    /// an identifier used as an expression, identifiers being bound by a
+    /// `let` or as parameters of a closure are not visited
+    fn visit_ident(&mut self, ident: &'ast Ident) {
+        let _ = ident;
+    }
+
+    fn visit_closure(&mut self, closure: &'ast Closure) {
+        walk_closure(self, closure)
+    }
+
+    fn visit_call(&mut self, call: &'ast Call) {
+        walk_call(self, call)
+    }
+
+    fn visit_if(&mut self, if_: &'ast If) {
+        walk_if(self, if_)
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block)
+    }
+}
+
+pub fn walk_node<'ast, V>(visitor: &mut V, node: &'ast Node)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    match node {
+        Node::Expr(expr) => visitor.visit_expr(expr),
+        Node::Stmt(stmt) => visitor.visit_stmt(stmt),
+    }
+}
+
+pub fn walk_stmt<'ast, V>(visitor: &mut V, stmt: &'ast Stmt)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    match stmt {
+        Stmt::Let(let_) => visitor.visit_let(let_),
+        Stmt::Return(return_) => {
+            if let Some(ref expr) = return_.value {
+                visitor.visit_expr(expr);
+            }
+        }
+    }
+}
+
+pub fn walk_let<'ast, V>(visitor: &mut V, let_: &'ast Let)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    visitor.visit_expr(&let_.value);
+}
+
+pub fn walk_expr<'ast, V>(visitor: &mut V, expr: &'ast Expr)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    match expr {
+        Expr::Int(_) | Expr::Bool(_) | Expr::Str(_) => {}
+        Expr::Seq(seq) => {
+            for expr in seq.elements.iter() {
+                visitor.visit_expr(expr);
+            }
+        }
+        Expr::Set(set) => {
+            for expr in set.elements.iter() {
+                visitor.visit_expr(expr);
+            }
+        }
+        Expr::Tuple(tuple) => {
+            for expr in tuple.elements.iter() {
+                visitor.visit_expr(expr);
+            }
+        }
+        Expr::Map(map) => {
+            for (k, v) in map.entries.iter() {
+                visitor.visit_expr(k);
+                visitor.visit_expr(v);
+            }
+        }
+        Expr::Ident(ident) => visitor.visit_ident(ident),
+        Expr::Index(index) => {
+            visitor.visit_expr(&index.base);
+            visitor.visit_expr(&index.subscript);
+        }
+        Expr::Unary(expr) => visitor.visit_expr(&expr.value),
+        Expr::Binary(expr) => {
+            visitor.visit_expr(&expr.left);
+            visitor.visit_expr(&expr.right);
+        }
+        Expr::Closure(closure) => visitor.visit_closure(closure),
+        Expr::Call(call) => visitor.visit_call(call),
+        Expr::If(if_) => visitor.visit_if(if_),
+    }
+}
+
+pub fn walk_closure<'ast, V>(visitor: &mut V, closure: &'ast Closure)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    visitor.visit_block(&closure.body);
+}
+
+pub fn walk_call<'ast, V>(visitor: &mut V, call: &'ast Call)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    visitor.visit_expr(&call.target);
+
+    for arg in call.args.iter() {
+        visitor.visit_expr(arg);
+    }
+}
+
+pub fn walk_if<'ast, V>(visitor: &mut V, if_: &'ast If)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    for (condition, consequence) in if_.conditioned.iter() {
+        visitor.visit_expr(condition);
+        visitor.visit_block(consequence);
+    }
+
+    if let Some(ref alternative) = if_.alternative {
+        visitor.visit_block(alternative);
+    }
+}
+
+pub fn walk_block<'ast, V>(visitor: &mut V, block: &'ast Block)
+where
+    V: Visitor<'ast> + ?Sized,
+{
+    for node in block.nodes.iter() {
+        visitor.visit_node(node);
+    }
+}
+
+/// like [`Visitor`], but walk the AST by mutable reference, e.g. for an
+/// optimisation pass rewriting expressions in place
+///
+/// a closure shared with a running program is cloned before it's visited
+pub(crate) trait VisitorMut {
+    fn visit_node_mut(&mut self, node: &mut Node) {
+        walk_node_mut(self, node)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_let_mut(&mut self, let_: &mut Let) {
+        walk_let_mut(self, let_)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_ident_mut(&mut self, ident: &mut Ident) {
+        let _ = ident;
+    }
+
+    fn visit_closure_mut(&mut self, closure: &mut Closure) {
+        walk_closure_mut(self, closure)
+    }
+
+    fn visit_call_mut(&mut self, call: &mut Call) {
+        walk_call_mut(self, call)
+    }
+
+    fn visit_if_mut(&mut self, if_: &mut If) {
+        walk_if_mut(self, if_)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+}
+
+pub(crate) fn walk_node_mut<V>(visitor: &mut V, node: &mut Node)
+where
+    V: VisitorMut + ?Sized,
+{
+    match node {
+        Node::Expr(expr) => visitor.visit_expr_mut(expr),
+        Node::Stmt(stmt) => visitor.visit_stmt_mut(stmt),
+    }
+}
+
+pub(crate) fn walk_stmt_mut<V>(visitor: &mut V, stmt: &mut Stmt)
+where
+    V: VisitorMut + ?Sized,
+{
+    match stmt {
+        Stmt::Let(let_) => visitor.visit_let_mut(let_),
+        Stmt::Return(return_) => {
+            if let Some(ref mut expr) = return_.value {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+    }
+}
+
+pub(crate) fn walk_let_mut<V>(visitor: &mut V, let_: &mut Let)
+where
+    V: VisitorMut + ?Sized,
+{
+    visitor.visit_expr_mut(&mut let_.value);
+}
+
+pub(crate) fn walk_expr_mut<V>(visitor: &mut V, expr: &mut Expr)
+where
+    V: VisitorMut + ?Sized,
+{
+    match expr {
+        Expr::Int(_) | Expr::Bool(_) | Expr::Str(_) => {}
+        Expr::Seq(seq) => {
+            for expr in seq.elements.iter_mut() {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        Expr::Set(set) => {
+            for expr in set.elements.iter_mut() {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        Expr::Tuple(tuple) => {
+            for expr in tuple.elements.iter_mut() {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        Expr::Map(map) => {
+            for (k, v) in map.entries.iter_mut() {
+                visitor.visit_expr_mut(k);
+                visitor.visit_expr_mut(v);
+            }
+        }
+        Expr::Ident(ident) => visitor.visit_ident_mut(ident),
+        Expr::Index(index) => {
+            visitor.visit_expr_mut(&mut index.base);
+            visitor.visit_expr_mut(&mut index.subscript);
+        }
+        Expr::Unary(expr) => visitor.visit_expr_mut(&mut expr.value),
+        Expr::Binary(expr) => {
+            visitor.visit_expr_mut(&mut expr.left);
+            visitor.visit_expr_mut(&mut expr.right);
+        }
+        Expr::Closure(closure) => visitor.visit_closure_mut(Rc::make_mut(closure)),
+        Expr::Call(call) => visitor.visit_call_mut(call),
+        Expr::If(if_) => visitor.visit_if_mut(if_),
+    }
+}
+
+pub(crate) fn walk_closure_mut<V>(visitor: &mut V, closure: &mut Closure)
+where
+    V: VisitorMut + ?Sized,
+{
+    visitor.visit_block_mut(&mut closure.body);
+}
+
+pub(crate) fn walk_call_mut<V>(visitor: &mut V, call: &mut Call)
+where
+    V: VisitorMut + ?Sized,
+{
+    visitor.visit_expr_mut(&mut call.target);
+
+    for arg in call.args.iter_mut() {
+        visitor.visit_expr_mut(arg);
+    }
+}
+
+pub(crate) fn walk_if_mut<V>(visitor: &mut V, if_: &mut If)
+where
+    V: VisitorMut + ?Sized,
+{
+    for (condition, consequence) in if_.conditioned.iter_mut() {
+        visitor.visit_expr_mut(condition);
+        visitor.visit_block_mut(consequence);
+    }
+
+    if let Some(ref mut alternative) = if_.alternative {
+        visitor.visit_block_mut(alternative);
+    }
+}
+
+pub(crate) fn walk_block_mut<V>(visitor: &mut V, block: &mut Block)
+where
+    V: VisitorMut + ?Sized,
+{
+    for node in block.nodes.iter_mut() {
+        visitor.visit_node_mut(node);
+    }
+}
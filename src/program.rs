@@ -0,0 +1,118 @@
+use std::rc::Rc;
+
+use crate::{embed, eval, parse::ast};
+
+/// parsed code which can be run many times, in one or more [`Env`](crate::Env)s, without
+/// lexing and parsing it again
+///
+/// ```rust
+/// let program = oris::Program::compile(b"x * 2").unwrap();
+///
+/// for x in 0..3 {
+///     let mut env = oris::Env::builder().with_int("x", x).build();
+///     assert_eq!(program.run(&mut env).unwrap().as_int(), Some(x * 2));
+/// }
+/// ```
+///
+/// cloning a `Program` is cheap, the code and the parsed nodes are shared
+#[derive(Clone)]
+pub struct Program {
+    code: Rc<[u8]>,
+    nodes: Rc<[ast::Node]>,
+    globals: eval::resolve::Globals,
+}
+
+impl Program {
+    /// parse the whole `code`, the first syntax error is returned if any, see
+    /// [`check`](crate::check) to get all of them
+    pub fn compile(code: &[u8]) -> Result<Self, embed::Error> {
+        let lexer = crate::lex::Lexer::with_cursor(code, 0);
+        let mut nodes = crate::parse::Parser::new(lexer)
+            .collect::<crate::parse::Result<Vec<_>>>()
+            .map_err(eval::Error::from)?;
+        let globals = eval::resolve::resolve(&mut nodes);
+
+        Ok(Self {
+            code: Rc::from(code),
+            nodes: nodes.into(),
+            globals,
+        })
+    }
+
+    /// fold the constant expressions, e.g. `60 * 60 * 24`, prune the `if`
+    /// branches decided statically and inline the `let`s of literal values
+    ///
+    /// [`run`](Self::run) returns the same result, or the same error at the same
+    /// position, as without optimizing, but the type check skips the pruned
+    /// branches
+    ///
+    /// ```rust
+    /// let program = oris::Program::compile(b"let day = 60 * 60 * 24; if true { day * 7 }")
+    ///     .unwrap()
+    ///     .optimize();
+    ///
+    /// let mut env = oris::Env::new();
+    /// assert_eq!(program.run(&mut env).unwrap().as_int(), Some(604800));
+    /// ```
+    pub fn optimize(self) -> Self {
+        let mut nodes = self.nodes.to_vec();
+        let globals = crate::optimize::optimize(&mut nodes);
+
+        Self {
+            code: self.code,
+            nodes: nodes.into(),
+            globals,
+        }
+    }
+
+    /// evaluate the program in `env`, global `let`s are kept in `env`
+    /// afterwards as [`entry`](crate::entry) does
+    ///
+    /// positions in the error are relative to the code of this program, use
+    /// [`source_map`](Self::source_map) to translate them
+    pub fn run(&self, env: &mut embed::Env) -> embed::Result {
+        if env.type_check {
+            if let Some(error) = crate::typeck::check(&self.nodes, &env.env).into_iter().next() {
+                return Err(eval::Error::Type(error).into());
+            }
+        }
+
+        env.env.begin();
+        let value = eval::entry_nodes(&mut env.env, &self.nodes, &self.globals);
+        env.env.end();
+
+        Ok(embed::Value { value: value? })
+    }
+
+    pub fn source_map(&self) -> SourceMap<'_> {
+        SourceMap { code: &self.code }
+    }
+}
+
+/// translate positions in an [`Error`](crate::Error) returned by
+/// [`Program::run`] into the code of the program
+pub struct SourceMap<'a> {
+    code: &'a [u8],
+}
+
+impl<'a> SourceMap<'a> {
+    /// the code which the program is compiled from
+    pub fn code(&self) -> &'a [u8] {
+        self.code
+    }
+
+    /// see [`Error::line_column`](crate::Error::line_column)
+    pub fn line_column(&self, error: &embed::Error) -> (usize, usize) {
+        error.line_column(self.code)
+    }
+
+    /// see [`Error::line_column_utf16`](crate::Error::line_column_utf16)
+    pub fn line_column_utf16(&self, error: &embed::Error) -> (usize, usize) {
+        error.line_column_utf16(self.code)
+    }
+
+    /// see [`Error::diagnostic`](crate::Error::diagnostic)
+    pub fn diagnostic(&self, error: &'a embed::Error) -> crate::Diagnostic<'a> {
+        error.diagnostic(self.code)
+    }
+}
@@ -0,0 +1,40 @@
+use std::ops::Range;
+
+/// byte range `start..end` in the source code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl Span {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub(crate) fn new(start: usize, end: usize) -> Self {
+        debug_assert!(start <= end);
+
+        Self { start, end }
+    }
+
+    // empty span at `pos`, e.g. where a missing token should be
+    pub(crate) fn at(pos: usize) -> Self {
+        Self::new(pos, pos)
+    }
+
+    // from the start of `self` to the end of `other`
+    pub(crate) fn to(self, other: Span) -> Self {
+        Self::new(self.start, other.end)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
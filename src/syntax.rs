@@ -0,0 +1,94 @@
+//! the parser of oris, for tools like linters, code generators or migration
+//! scripts
+//!
+//! ```rust
+//! use oris::syntax::{self, Expr, Node};
+//!
+//! let nodes = syntax::parse(b"let answer = 42;\nanswer").unwrap();
+//! assert_eq!(nodes.len(), 2);
+//!
+//! match &nodes[1] {
+//!     Node::Expr(Expr::Ident(ident)) => {
+//!         assert_eq!(ident.sym(), "answer");
+//!         assert_eq!(ident.span().start(), 17);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+//!
+//! all the positions are byte offsets into the parsed code
+
+use crate::{embed::Error, eval};
+
+pub use crate::{
+    lex::token::{Kind as TokenKind, Token},
+    parse::{ast::*, visit::*},
+    span::Span,
+};
+
+/// parse the whole `code`, the first syntax error is returned if any, see
+/// [`check`](crate::check) to get all of them
+pub fn parse(code: &[u8]) -> Result<Vec<Node>, Error> {
+    let lexer = crate::lex::Lexer::with_cursor(code, 0);
+    crate::parse::Parser::new(lexer)
+        .collect::<crate::parse::Result<_>>()
+        .map_err(|error| Error::from(eval::Error::from(error)))
+}
+
+/// like [`parse`], but skip to the next statement after a syntax error and
+/// continue, so that the nodes around broken code and all the errors are
+/// returned, e.g. for an editor
+pub fn parse_with_recovery(code: &[u8]) -> (Vec<Node>, Vec<Error>) {
+    let lexer = crate::lex::Lexer::with_cursor(code, 0);
+    let (nodes, errors) = crate::parse::Parser::new(lexer).parse_all();
+
+    let errors = errors
+        .into_iter()
+        .map(|error| Error::from(eval::Error::from(error)))
+        .collect();
+
+    (nodes, errors)
+}
+
+/// split `code` into tokens, comments and whitespaces are skipped
+///
+/// lexing continues after an invalid token, which is yielded as an error
+///
+/// ```rust
+/// use oris::syntax::{self, TokenKind};
+///
+/// let kinds = syntax::tokenize(b"let a = 1; # comment")
+///     .map(|token| token.unwrap().kind())
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     kinds,
+///     [
+///         TokenKind::Let,
+///         TokenKind::Ident,
+///         TokenKind::Assign,
+///         TokenKind::Int,
+///         TokenKind::Semicolon,
+///     ]
+/// );
+/// ```
+pub fn tokenize(code: &[u8]) -> Tokens<'_> {
+    Tokens {
+        lexer: crate::lex::Lexer::with_cursor(code, 0),
+    }
+}
+
+/// iterator returned by [`tokenize`]
+pub struct Tokens<'a> {
+    lexer: crate::lex::Lexer<'a>,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lexer.next().map(|token| {
+            token.map_err(|error| Error::from(eval::Error::from(crate::parse::Error::from(error))))
+        })
+    }
+}
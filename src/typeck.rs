@@ -0,0 +1,968 @@
+mod error;
+mod ty;
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    eval::{self, value::builtin, Value},
+    parse::ast::{self, BinaryOp, Expr, Node, Stmt, UnaryOp},
+    span::Span,
+};
+
+use ty::{Signature, Type};
+
+pub(crate) type Error = error::Error;
+
+// infer the types of `nodes` evaluated in `env` and report the errors sorted
+// by position
+//
+// the checker only rejects code which fails for sure once evaluated, an
+// expression of a type unknown statically, e.g. an element of a seq mixing
+// ints and strs or an undefined identifier, is compatible with anything
+pub(crate) fn check(nodes: &[Node], env: &eval::Env) -> Vec<Error> {
+    let mut checker = Checker::new(env, nodes);
+    checker.nodes(nodes);
+    checker.finish()
+}
+
+struct Checker {
+    vars: Vec<Var>,
+
+    // nesting depth of `let`s, a variable created deeper than the current
+    // depth and still unbound is generalized
+    level: usize,
+
+    scopes: Vec<HashMap<Rc<str>, Binding>>,
+
+    // return type of each enclosing closure
+    returns: Vec<Type>,
+
+    // names of the top-level `let`s, which a closure may use before they are
+    // bound
+    globals: HashSet<Rc<str>>,
+
+    // types of the globals used before they are bound
+    forward: HashMap<Rc<str>, Type>,
+
+    // checks on types unknown when reached, done after all the code is seen
+    deferred: Vec<Deferred>,
+
+    errors: Vec<Error>,
+}
+
+#[derive(Clone)]
+struct Var {
+    bound: Option<Type>,
+    level: usize,
+}
+
+#[derive(Clone)]
+enum Binding {
+    Scheme(Scheme),
+    // typed by its signatures on each call
+    Builtin(&'static str),
+}
+
+// a type with `generics` type parameters, e.g. `fn(T) -> T`
+#[derive(Clone)]
+struct Scheme {
+    generics: usize,
+    ty: Type,
+}
+
+enum Deferred {
+    Binary {
+        span: Span,
+        op: BinaryOp,
+        // of both operands
+        operand: Type,
+    },
+    Index {
+        span: Span,
+        base: Type,
+        subscript: Type,
+    },
+}
+
+impl Checker {
+    fn new(env: &eval::Env, nodes: &[Node]) -> Self {
+        let global = env
+            .globals()
+            .map(|(sym, value)| {
+                let builtin = match value {
+                    Value::Builtin(_) => builtin::ALL
+                        .iter()
+                        .map(|entry| entry.name)
+                        .find(|name| **name == **sym),
+                    _ => None,
+                };
+
+                let binding = match builtin {
+                    Some(name) => Binding::Builtin(name),
+                    None => Binding::Scheme(Scheme {
+                        generics: 0,
+                        ty: value_type(value),
+                    }),
+                };
+
+                (sym.clone(), binding)
+            })
+            .collect();
+
+        Self {
+            vars: Vec::new(),
+            level: 0,
+            scopes: vec![global],
+            returns: Vec::new(),
+            globals: nodes
+                .iter()
+                .filter_map(|node| match node {
+                    Node::Stmt(Stmt::Let(let_)) => Some(let_.ident.sym_rc_str().clone()),
+                    _ => None,
+                })
+                .collect(),
+            forward: HashMap::new(),
+            deferred: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> Vec<Error> {
+        for deferred in std::mem::take(&mut self.deferred) {
+            match deferred {
+                Deferred::Binary { span, op, operand } => {
+                    if supports(op, &self.resolve(&operand)) == Some(false) {
+                        self.binary_error(span, op, &operand, &operand);
+                    }
+                }
+                Deferred::Index {
+                    span,
+                    base,
+                    subscript,
+                } => match self.resolve(&base) {
+                    Type::Seq(_) | Type::Map(..) | Type::Tuple(_) | Type::Dyn | Type::Var(_) => {}
+                    _ => self.index_error(span, &base, &subscript),
+                },
+            }
+        }
+
+        self.errors.sort_by_key(|error| error.span().start);
+        self.errors
+    }
+
+    // type of the last node
+    fn nodes(&mut self, nodes: &[Node]) -> Type {
+        let mut ty = Type::Unit;
+
+        for node in nodes {
+            ty = match node {
+                Node::Expr(expr) => self.expr(expr),
+                Node::Stmt(Stmt::Let(let_)) => {
+                    self.let_(let_);
+                    Type::Unit
+                }
+                Node::Stmt(Stmt::Return(return_)) => match return_.value {
+                    // `return;` doesn't return
+                    None => Type::Unit,
+                    Some(ref value) => {
+                        let ty = self.expr(value);
+                        if let Some(ret) = self.returns.last().cloned() {
+                            self.expect(value.span(), &ret, &ty);
+                        }
+                        // never produces a value where it is
+                        self.fresh()
+                    }
+                },
+            };
+        }
+
+        ty
+    }
+
+    fn block(&mut self, block: &ast::Block) -> Type {
+        self.scopes.push(HashMap::new());
+        let ty = self.nodes(&block.nodes);
+        self.scopes.pop();
+        ty
+    }
+
+    fn let_(&mut self, let_: &ast::Let) {
+        let sym = let_.ident.sym_rc_str();
+
+        let annotated = let_.ty.as_ref().map(annotation);
+
+        self.level += 1;
+        let ty = match let_.value {
+            // the closure refers to itself unless the name is already bound
+            Expr::Closure(ref closure) if self.lookup(sym).is_none() => {
+                let itself = annotated.clone().unwrap_or_else(|| self.fresh());
+                let ty = self.closure(closure, Some((sym, &itself)), annotated.as_ref());
+                self.unify(&itself, &ty);
+                ty
+            }
+            Expr::Closure(ref closure) => self.closure(closure, None, annotated.as_ref()),
+            ref value => self.expr(value),
+        };
+        self.level -= 1;
+
+        // the binding is of the annotated type even if `any`
+        let ty = match annotated {
+            Some(annotated) => {
+                self.expect(let_.value.span(), &annotated, &ty);
+                annotated
+            }
+            None => ty,
+        };
+
+        let scheme = self.generalize(&ty);
+
+        if self.scopes.len() == 1 {
+            if let Some(used) = self.forward.remove(sym) {
+                let ty = self.instantiate(&scheme);
+                if !self.unify(&used, &ty) {
+                    let [used, ty] = self.render([&used, &ty]);
+                    self.errors.push(Error::Mismatch {
+                        span: let_.value.span(),
+                        expected: used,
+                        found: ty,
+                    });
+                }
+            }
+        }
+
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(sym.clone(), Binding::Scheme(scheme));
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Int(_) => Type::Int,
+            Expr::Bool(_) => Type::Bool,
+            Expr::Str(_) => Type::Str,
+            Expr::Seq(seq) => {
+                let elements = seq.elements.iter().map(|expr| self.expr(expr)).collect();
+                Type::Seq(Box::new(self.join(elements)))
+            }
+            Expr::Set(set) => {
+                let elements = set.elements.iter().map(|expr| self.expr(expr)).collect();
+                Type::Set(Box::new(self.join(elements)))
+            }
+            Expr::Map(map) => {
+                let (keys, values) = map
+                    .entries
+                    .iter()
+                    .map(|(key, value)| (self.expr(key), self.expr(value)))
+                    .unzip();
+                Type::Map(Box::new(self.join(keys)), Box::new(self.join(values)))
+            }
+            Expr::Tuple(tuple) => {
+                Type::Tuple(tuple.elements.iter().map(|expr| self.expr(expr)).collect())
+            }
+            Expr::Ident(ident) => self.ident(ident),
+            Expr::Index(index) => self.index(index),
+            Expr::Unary(unary) => self.unary(unary),
+            Expr::Binary(binary) => self.binary(binary),
+            Expr::Closure(closure) => self.closure(closure, None, None),
+            Expr::Call(call) => self.call(call),
+            Expr::If(if_) => self.if_(if_),
+        }
+    }
+
+    fn ident(&mut self, ident: &ast::Ident) -> Type {
+        match self.lookup(ident.sym()).cloned() {
+            Some(Binding::Scheme(scheme)) => self.instantiate(&scheme),
+            Some(Binding::Builtin(name)) => match builtin::signatures(name) {
+                [signature] => {
+                    let signature = Signature::parse(signature);
+                    match signature.variadic {
+                        Some(_) => Type::Dyn,
+                        None => {
+                            let args = self.fresh_n(signature.generics);
+                            let params = signature
+                                .params
+                                .iter()
+                                .map(|param| param.instantiate(&args))
+                                .collect();
+                            Type::Fn(params, Box::new(signature.ret.instantiate(&args)))
+                        }
+                    }
+                }
+                // overloaded
+                _ => Type::Dyn,
+            },
+            None if !self.returns.is_empty() && self.globals.contains(ident.sym()) => {
+                let var = self.fresh_at(0);
+                self.forward
+                    .entry(ident.sym_rc_str().clone())
+                    .or_insert(var)
+                    .clone()
+            }
+            None => Type::Dyn,
+        }
+    }
+
+    fn index(&mut self, index: &ast::Index) -> Type {
+        let base = self.expr(&index.base);
+        let subscript = self.expr(&index.subscript);
+
+        match self.resolve(&base) {
+            Type::Seq(elem) => {
+                self.expect(index.subscript.span(), &Type::Int, &subscript);
+                *elem
+            }
+            Type::Map(key, value) => {
+                self.expect(index.subscript.span(), &key, &subscript);
+                *value
+            }
+            Type::Tuple(elems) => {
+                self.expect(index.subscript.span(), &Type::Int, &subscript);
+                match index.subscript {
+                    Expr::Int(ref int) => usize::try_from(int.value)
+                        .ok()
+                        .and_then(|i| elems.get(i).cloned())
+                        .unwrap_or(Type::Dyn),
+                    _ => Type::Dyn,
+                }
+            }
+            Type::Dyn => Type::Dyn,
+            Type::Var(_) => {
+                self.deferred.push(Deferred::Index {
+                    span: index.span,
+                    base,
+                    subscript,
+                });
+                Type::Dyn
+            }
+            _ => {
+                self.index_error(index.span, &base, &subscript);
+                Type::Dyn
+            }
+        }
+    }
+
+    fn unary(&mut self, unary: &ast::Unary) -> Type {
+        let operand = self.expr(&unary.value);
+        let ty = match unary.op {
+            UnaryOp::Neg => Type::Int,
+            UnaryOp::Not => Type::Bool,
+        };
+
+        if !self.unify(&ty, &operand) {
+            let [operand] = self.render([&operand]);
+            self.errors.push(Error::Unary {
+                span: unary.span,
+                op: unary.op,
+                operand,
+            });
+        }
+
+        ty
+    }
+
+    fn binary(&mut self, binary: &ast::Binary) -> Type {
+        let left = self.expr(&binary.left);
+        let right = self.expr(&binary.right);
+
+        match binary.op {
+            BinaryOp::Div | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                if !(self.unify(&Type::Int, &left) && self.unify(&Type::Int, &right)) {
+                    self.binary_error(binary.span, binary.op, &left, &right);
+                }
+
+                match binary.op {
+                    BinaryOp::Div => Type::Int,
+                    _ => Type::Bool,
+                }
+            }
+            op @ (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Eq | BinaryOp::Ne) => {
+                if !self.unify(&left, &right) {
+                    self.binary_error(binary.span, op, &left, &right);
+                    return Type::Dyn;
+                }
+
+                match supports(op, &self.resolve(&left)) {
+                    Some(true) => {}
+                    Some(false) => self.binary_error(binary.span, op, &left, &right),
+                    None => self.deferred.push(Deferred::Binary {
+                        span: binary.span,
+                        op,
+                        operand: left.clone(),
+                    }),
+                }
+
+                match op {
+                    BinaryOp::Eq | BinaryOp::Ne => Type::Bool,
+                    _ => left,
+                }
+            }
+        }
+    }
+
+    // `itself` is the name and the type of the closure if it refers to itself,
+    // `expected` is the type of the closure known in advance
+    fn closure(
+        &mut self,
+        closure: &ast::Closure,
+        itself: Option<(&Rc<str>, &Type)>,
+        expected: Option<&Type>,
+    ) -> Type {
+        let params = self.fresh_n(closure.parameters.len());
+        let ret = self.fresh();
+
+        for (param, ty) in params.iter().zip(closure.parameter_types.iter()) {
+            if let Some(ty) = ty {
+                self.unify(param, &annotation(ty));
+            }
+        }
+        if let Some(ty) = &closure.ret {
+            self.unify(&ret, &annotation(ty));
+        }
+
+        if let Some(Type::Fn(expected, _)) = expected.map(|ty| self.resolve(ty)) {
+            if expected.len() == params.len() {
+                for (param, expected) in params.iter().zip(&expected) {
+                    self.unify(param, expected);
+                }
+            }
+        }
+
+        let mut scope = HashMap::new();
+        if let Some((sym, ty)) = itself {
+            scope.insert(sym.clone(), Binding::Scheme(mono(ty.clone())));
+        }
+        for (ident, ty) in closure.parameters.iter().zip(&params) {
+            scope.insert(
+                ident.sym_rc_str().clone(),
+                Binding::Scheme(mono(ty.clone())),
+            );
+        }
+
+        self.scopes.push(scope);
+        self.returns.push(ret.clone());
+        let body = self.block(&closure.body);
+        self.returns.pop();
+        self.scopes.pop();
+
+        let span = closure
+            .body
+            .nodes
+            .last()
+            .map_or(closure.body.span, Node::span);
+        self.expect(span, &ret, &body);
+
+        Type::Fn(params, Box::new(ret))
+    }
+
+    fn call(&mut self, call: &ast::Call) -> Type {
+        if let Expr::Ident(ref ident) = call.target {
+            if let Some(&Binding::Builtin(name)) = self.lookup(ident.sym()) {
+                return self.call_builtin(call, name);
+            }
+        }
+
+        let target = self.expr(&call.target);
+
+        match self.resolve(&target) {
+            Type::Fn(params, ret) => {
+                if params.len() != call.args.len() {
+                    for arg in call.args.iter() {
+                        self.expr(arg);
+                    }
+                    self.errors.push(Error::ArgCount {
+                        span: call.span,
+                        supplied: call.args.len(),
+                        expected: params.len(),
+                    });
+                } else {
+                    for (param, arg) in params.iter().zip(call.args.iter()) {
+                        let ty = self.arg(arg, param);
+                        self.expect(arg.span(), param, &ty);
+                    }
+                }
+
+                *ret
+            }
+            Type::Var(_) => {
+                let args = call.args.iter().map(|arg| self.expr(arg)).collect();
+                let ret = self.fresh();
+                self.unify(&target, &Type::Fn(args, Box::new(ret.clone())));
+                ret
+            }
+            ty => {
+                for arg in call.args.iter() {
+                    self.expr(arg);
+                }
+
+                if ty != Type::Dyn {
+                    let [target] = self.render([&target]);
+                    self.errors.push(Error::Call {
+                        span: call.span,
+                        target,
+                    });
+                }
+                Type::Dyn
+            }
+        }
+    }
+
+    // an overloaded builtin is typed by the only signature accepting the args,
+    // the args are left as is if more than one does
+    fn call_builtin(&mut self, call: &ast::Call, name: &'static str) -> Type {
+        let signatures: Vec<_> = builtin::signatures(name)
+            .iter()
+            .map(|signature| Signature::parse(signature))
+            .filter(|signature| signature.accepts(call.args.len()))
+            .collect();
+
+        if let [signature] = &signatures[..] {
+            let (params, ret) = self.params(signature, call.args.len());
+            for (param, arg) in params.iter().zip(call.args.iter()) {
+                let ty = self.arg(arg, param);
+                self.expect(arg.span(), param, &ty);
+            }
+            return ret;
+        }
+
+        let args: Vec<_> = call.args.iter().map(|arg| self.expr(arg)).collect();
+
+        let vars = self.vars.clone();
+        let mut accepted = Vec::new();
+        for signature in &signatures {
+            let (params, ret) = self.params(signature, args.len());
+            if params
+                .iter()
+                .zip(&args)
+                .all(|(param, arg)| self.unify(param, arg))
+            {
+                accepted.push((signature, self.zonk(&ret)));
+            }
+            self.vars.clone_from(&vars);
+        }
+
+        match &accepted[..] {
+            [] => {
+                let args = self.render_list(&args);
+                self.errors.push(Error::Builtin {
+                    span: call.span,
+                    name,
+                    args,
+                });
+                Type::Dyn
+            }
+            [(signature, _)] => {
+                let (params, ret) = self.params(signature, args.len());
+                for (param, arg) in params.iter().zip(&args) {
+                    self.unify(param, arg);
+                }
+                ret
+            }
+            [(_, ret), rest @ ..] => {
+                if rest.iter().all(|(_, other)| other == ret) && !has_var(ret) {
+                    ret.clone()
+                } else {
+                    Type::Dyn
+                }
+            }
+        }
+    }
+
+    // types of the params and the return type of a call to `signature` with
+    // `args` args
+    fn params(&mut self, signature: &Signature, args: usize) -> (Vec<Type>, Type) {
+        let generics = self.fresh_n(signature.generics);
+
+        let mut params: Vec<_> = signature
+            .params
+            .iter()
+            .map(|param| param.instantiate(&generics))
+            .collect();
+
+        if let Some(ref variadic) = signature.variadic {
+            for _ in params.len()..args {
+                // each variadic arg has its own type parameters
+                let generics = (0..signature.generics)
+                    .map(|i| match signature.is_shared(i) {
+                        true => generics[i].clone(),
+                        false => self.fresh(),
+                    })
+                    .collect::<Vec<_>>();
+                params.push(variadic.instantiate(&generics));
+            }
+        }
+
+        (params, signature.ret.instantiate(&generics))
+    }
+
+    // a closure passed as an arg takes the types of the params of `expected`,
+    // so that an error inside it is reported where it is
+    fn arg(&mut self, arg: &Expr, expected: &Type) -> Type {
+        match arg {
+            Expr::Closure(closure) => self.closure(closure, None, Some(expected)),
+            arg => self.expr(arg),
+        }
+    }
+
+    fn if_(&mut self, if_: &ast::If) -> Type {
+        let mut branches = Vec::new();
+
+        // a condition which is not a bool is valid, the branch is just not
+        // taken, see the `condition` lint
+        for (condition, block) in if_.conditioned.iter() {
+            self.expr(condition);
+            branches.push(self.block(block));
+        }
+
+        branches.push(match if_.alternative {
+            Some(ref block) => self.block(block),
+            None => Type::Unit,
+        });
+
+        self.join(branches)
+    }
+
+    // the common type of `types`, unknown if they differ
+    fn join(&mut self, types: Vec<Type>) -> Type {
+        let mut types = types.into_iter();
+
+        let first = match types.next() {
+            Some(first) => first,
+            None => return self.fresh(),
+        };
+
+        for ty in types {
+            if !self.unify(&first, &ty) {
+                return Type::Dyn;
+            }
+        }
+
+        first
+    }
+
+    fn lookup(&self, sym: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(sym))
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.fresh_at(self.level)
+    }
+
+    fn fresh_at(&mut self, level: usize) -> Type {
+        self.vars.push(Var { bound: None, level });
+        Type::Var(self.vars.len() - 1)
+    }
+
+    fn fresh_n(&mut self, n: usize) -> Vec<Type> {
+        (0..n).map(|_| self.fresh()).collect()
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let args = self.fresh_n(scheme.generics);
+        scheme.ty.instantiate(&args)
+    }
+
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let mut generics = Vec::new();
+        let ty = self.generalize_in(ty, &mut generics);
+
+        Scheme {
+            generics: generics.len(),
+            ty,
+        }
+    }
+
+    // `generics` are the variables replaced so far
+    fn generalize_in(&self, ty: &Type, generics: &mut Vec<usize>) -> Type {
+        match self.resolve(ty) {
+            Type::Var(var) if self.vars[var].level > self.level => {
+                match generics.iter().position(|v| *v == var) {
+                    Some(i) => Type::Gen(i),
+                    None => {
+                        generics.push(var);
+                        Type::Gen(generics.len() - 1)
+                    }
+                }
+            }
+            ty => ty.map(|ty| self.generalize_in(ty, generics)),
+        }
+    }
+
+    // follow the bound variables at the top of `ty`
+    fn resolve(&self, ty: &Type) -> Type {
+        let mut ty = ty;
+        while let Type::Var(var) = ty {
+            match self.vars[*var].bound {
+                Some(ref bound) => ty = bound,
+                None => break,
+            }
+        }
+        ty.clone()
+    }
+
+    // follow the bound variables anywhere in `ty`
+    fn zonk(&self, ty: &Type) -> Type {
+        match self.resolve(ty) {
+            ty @ Type::Var(_) => ty,
+            ty => ty.map(|ty| self.zonk(ty)),
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Var(a), Type::Var(b)) if a == b => true,
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => {
+                self.bind(var, ty);
+                true
+            }
+            (Type::Dyn, _) | (_, Type::Dyn) => true,
+            (Type::Unit, Type::Unit)
+            | (Type::Int, Type::Int)
+            | (Type::Bool, Type::Bool)
+            | (Type::Str, Type::Str) => true,
+            (Type::Seq(a), Type::Seq(b)) | (Type::Set(a), Type::Set(b)) => self.unify(&a, &b),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => self.unify(&ak, &bk) && self.unify(&av, &bv),
+            (Type::Tuple(a), Type::Tuple(b)) if a.is_empty() || b.is_empty() => true,
+            (Type::Tuple(a), Type::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| self.unify(a, b))
+            }
+            (Type::Fn(ap, ar), Type::Fn(bp, br)) => {
+                ap.len() == bp.len()
+                    && ap.iter().zip(&bp).all(|(a, b)| self.unify(a, b))
+                    && self.unify(&ar, &br)
+            }
+            _ => false,
+        }
+    }
+
+    fn bind(&mut self, var: usize, ty: Type) {
+        let level = self.vars[var].level;
+
+        // a recursive type, e.g. of `fn(f) { f(f) }`, is beyond the checker
+        let ty = match self.occurs(var, level, &ty) {
+            true => Type::Dyn,
+            false => ty,
+        };
+
+        self.vars[var].bound = Some(ty);
+    }
+
+    // whether `var` occurs in `ty`, the variables in `ty` are lowered to
+    // `level` meanwhile, so that they are generalized no earlier than `var`
+    fn occurs(&mut self, var: usize, level: usize, ty: &Type) -> bool {
+        match self.resolve(ty) {
+            Type::Var(other) if other == var => true,
+            Type::Var(other) => {
+                let other = &mut self.vars[other];
+                other.level = other.level.min(level);
+                false
+            }
+            Type::Seq(elem) | Type::Set(elem) => self.occurs(var, level, &elem),
+            Type::Map(key, value) => {
+                self.occurs(var, level, &key) || self.occurs(var, level, &value)
+            }
+            Type::Tuple(elems) => elems.iter().any(|ty| self.occurs(var, level, ty)),
+            Type::Fn(params, ret) => {
+                params.iter().any(|ty| self.occurs(var, level, ty)) || self.occurs(var, level, &ret)
+            }
+            _ => false,
+        }
+    }
+
+    // report the mismatch unless `found` unifies with `expected`
+    fn expect(&mut self, span: Span, expected: &Type, found: &Type) -> bool {
+        if self.unify(expected, found) {
+            return true;
+        }
+
+        let [expected, found] = self.render([expected, found]);
+        self.errors.push(Error::Mismatch {
+            span,
+            expected,
+            found,
+        });
+        false
+    }
+
+    fn binary_error(&mut self, span: Span, op: BinaryOp, left: &Type, right: &Type) {
+        let [left, right] = self.render([left, right]);
+        self.errors.push(Error::Binary {
+            span,
+            left,
+            op,
+            right,
+        });
+    }
+
+    fn index_error(&mut self, span: Span, base: &Type, subscript: &Type) {
+        let [base, subscript] = self.render([base, subscript]);
+        self.errors.push(Error::Index {
+            span,
+            base,
+            subscript,
+        });
+    }
+
+    // variables are named `T`, `U`, ... in the order of appearance across
+    // all the `types`
+    fn render<const N: usize>(&self, types: [&Type; N]) -> [String; N] {
+        let mut names = Vec::new();
+        types.map(|ty| {
+            let mut out = String::new();
+            self.write(&mut out, ty, &mut names);
+            out
+        })
+    }
+
+    fn render_list(&self, types: &[Type]) -> String {
+        let mut names = Vec::new();
+        let mut out = String::new();
+        self.write_list(&mut out, types, &mut names);
+        out
+    }
+
+    fn write_list(&self, out: &mut String, types: &[Type], names: &mut Vec<usize>) {
+        for (i, ty) in types.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.write(out, ty, names);
+        }
+    }
+
+    fn write(&self, out: &mut String, ty: &Type, names: &mut Vec<usize>) {
+        match self.resolve(ty) {
+            Type::Unit => out.push_str("unit"),
+            Type::Int => out.push_str("int"),
+            Type::Bool => out.push_str("bool"),
+            Type::Str => out.push_str("str"),
+            Type::Seq(elem) => {
+                out.push('[');
+                self.write(out, &elem, names);
+                out.push(']');
+            }
+            Type::Map(key, value) => {
+                out.push('{');
+                self.write(out, &key, names);
+                out.push_str(": ");
+                self.write(out, &value, names);
+                out.push('}');
+            }
+            Type::Set(elem) => {
+                out.push_str("#{");
+                self.write(out, &elem, names);
+                out.push('}');
+            }
+            Type::Tuple(elems) if elems.is_empty() => out.push_str("tuple"),
+            Type::Tuple(elems) => {
+                out.push('(');
+                self.write_list(out, &elems, names);
+                if elems.len() == 1 {
+                    out.push(',');
+                }
+                out.push(')');
+            }
+            Type::Fn(params, ret) => {
+                out.push_str("fn(");
+                self.write_list(out, &params, names);
+                out.push(')');
+                if self.resolve(&ret) != Type::Unit {
+                    out.push_str(" -> ");
+                    self.write(out, &ret, names);
+                }
+            }
+            Type::Var(var) => {
+                let i = match names.iter().position(|v| *v == var) {
+                    Some(i) => i,
+                    None => {
+                        names.push(var);
+                        names.len() - 1
+                    }
+                };
+                match "TUVWXYZ".chars().nth(i) {
+                    Some(name) => out.push(name),
+                    None => out.push_str(&format!("T{}", i)),
+                }
+            }
+            Type::Gen(_) => unreachable!("type parameter outside of a scheme"),
+            Type::Dyn => out.push_str("any"),
+        }
+    }
+}
+
+// type written as an annotation, `any` is compatible with anything
+fn annotation(ty: &ast::Type) -> Type {
+    match ty.kind() {
+        ast::TypeKind::Int => Type::Int,
+        ast::TypeKind::Bool => Type::Bool,
+        ast::TypeKind::Str => Type::Str,
+        ast::TypeKind::Any => Type::Dyn,
+        ast::TypeKind::Seq(elem) => Type::Seq(Box::new(annotation(elem))),
+        ast::TypeKind::Map(key, value) => {
+            Type::Map(Box::new(annotation(key)), Box::new(annotation(value)))
+        }
+        ast::TypeKind::Set(elem) => Type::Set(Box::new(annotation(elem))),
+        ast::TypeKind::Tuple(elems) => Type::Tuple(elems.iter().map(annotation).collect()),
+        ast::TypeKind::Fn(params, ret) => Type::Fn(
+            params.iter().map(annotation).collect(),
+            Box::new(ret.as_deref().map_or(Type::Unit, annotation)),
+        ),
+    }
+}
+
+fn mono(ty: Type) -> Scheme {
+    Scheme { generics: 0, ty }
+}
+
+// type of a global defined by the host or a previous `entry()`
+fn value_type(value: &Value) -> Type {
+    match value {
+        Value::Unit => Type::Unit,
+        Value::Int(_) => Type::Int,
+        Value::Bool(_) => Type::Bool,
+        Value::Str(_) => Type::Str,
+        Value::Seq(_) => Type::Seq(Box::new(Type::Dyn)),
+        Value::Map(_) => Type::Map(Box::new(Type::Dyn), Box::new(Type::Dyn)),
+        Value::Set(_) => Type::Set(Box::new(Type::Dyn)),
+        Value::Tuple(values) => Type::Tuple(values.iter().map(value_type).collect()),
+        Value::Builtin(_) | Value::Closure(_) => Type::Dyn,
+    }
+}
+
+// whether the operands of type `ty` support `op`, `None` if `ty` is unknown
+// yet
+fn supports(op: BinaryOp, ty: &Type) -> Option<bool> {
+    let ops: &[BinaryOp] = match ty {
+        Type::Var(_) => return None,
+        Type::Int | Type::Dyn => return Some(true),
+        Type::Bool | Type::Tuple(_) => &[BinaryOp::Eq, BinaryOp::Ne],
+        Type::Str | Type::Seq(_) | Type::Map(..) => &[BinaryOp::Add, BinaryOp::Eq, BinaryOp::Ne],
+        Type::Set(_) => &[
+            BinaryOp::Add,
+            BinaryOp::Sub,
+            BinaryOp::Mul,
+            BinaryOp::Eq,
+            BinaryOp::Ne,
+        ],
+        Type::Unit | Type::Fn(..) | Type::Gen(_) => &[],
+    };
+
+    Some(ops.contains(&op))
+}
+
+fn has_var(ty: &Type) -> bool {
+    match ty {
+        Type::Var(_) => true,
+        Type::Seq(elem) | Type::Set(elem) => has_var(elem),
+        Type::Map(key, value) => has_var(key) || has_var(value),
+        Type::Tuple(elems) => elems.iter().any(has_var),
+        Type::Fn(params, ret) => params.iter().any(has_var) || has_var(ret),
+        _ => false,
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{parse::ast, span::Span};
+
+// types are rendered in advance, since they refer to the variables of the
+// checker
+#[derive(Debug)]
+pub(crate) enum Error {
+    Mismatch {
+        span: Span,
+        expected: String,
+        found: String,
+    },
+    Unary {
+        span: Span,
+        op: ast::UnaryOp,
+        operand: String,
+    },
+    Binary {
+        span: Span,
+        left: String,
+        op: ast::BinaryOp,
+        right: String,
+    },
+    Index {
+        span: Span,
+        base: String,
+        subscript: String,
+    },
+    Call {
+        span: Span,
+        target: String,
+    },
+    ArgCount {
+        span: Span,
+        supplied: usize,
+        expected: usize,
+    },
+    Builtin {
+        span: Span,
+        name: &'static str,
+        args: String,
+    },
+}
+
+impl Error {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Self::Mismatch { span, .. } => *span,
+            Self::Unary { span, .. } => *span,
+            Self::Binary { span, .. } => *span,
+            Self::Index { span, .. } => *span,
+            Self::Call { span, .. } => *span,
+            Self::ArgCount { span, .. } => *span,
+            Self::Builtin { span, .. } => *span,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Mismatch { .. } => "mismatch",
+            Self::Unary { .. } => "unary",
+            Self::Binary { .. } => "binary",
+            Self::Index { .. } => "index",
+            Self::Call { .. } => "call",
+            Self::ArgCount { .. } => "arg_count",
+            Self::Builtin { .. } => "builtin",
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch {
+                span: _,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "mismatched types: expected {}, found {}",
+                    expected, found
+                )
+            }
+            Self::Unary {
+                span: _,
+                op,
+                operand,
+            } => {
+                write!(f, "invalid unary operator {} for {}", op, operand)
+            }
+            Self::Binary {
+                span: _,
+                left,
+                op,
+                right,
+            } => {
+                write!(
+                    f,
+                    "invalid binary operator {} between {} and {}",
+                    op, left, right
+                )
+            }
+            Self::Index {
+                span: _,
+                base,
+                subscript,
+            } => {
+                write!(f, "index {} with {}", base, subscript)
+            }
+            Self::Call { span: _, target } => {
+                write!(f, "{} is not callable", target)
+            }
+            Self::ArgCount {
+                supplied, expected, ..
+            } => {
+                write!(f, "accept arg x {}, but got {}", expected, supplied)
+            }
+            Self::Builtin {
+                span: _,
+                name,
+                args,
+            } => {
+                write!(f, "no signature of builtin {} accepts ({})", name, args)
+            }
+        }
+    }
+}
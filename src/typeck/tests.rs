@@ -0,0 +1,219 @@
+use super::{ty::Signature, Checker};
+
+fn env() -> crate::eval::Env {
+    crate::Env::new().env
+}
+
+fn parse(code: &str) -> Vec<crate::parse::ast::Node> {
+    let (nodes, errors) = crate::syntax::parse_with_recovery(code.as_bytes());
+    assert!(errors.is_empty(), "{:?}", errors);
+    nodes
+}
+
+// inferred type of `code`
+fn t(code: &str) -> String {
+    let env = env();
+    let nodes = parse(code);
+    let mut checker = Checker::new(&env, &nodes);
+    let ty = checker.nodes(&nodes);
+    assert!(checker.errors.is_empty(), "{:?}", checker.errors);
+    let [ty] = checker.render([&ty]);
+    ty
+}
+
+// `(name, checked code)` of each error
+fn e(code: &str) -> Vec<(&'static str, &str)> {
+    super::check(&parse(code), &env())
+        .iter()
+        .map(|error| (error.name(), &code[std::ops::Range::from(error.span())]))
+        .collect()
+}
+
+fn message(code: &str) -> String {
+    super::check(&parse(code), &env())[0].to_string()
+}
+
+#[test]
+fn signatures() {
+    for (name, signatures) in crate::builtins() {
+        for signature in signatures {
+            let signature = Signature::parse(signature);
+            assert!(signature.accepts(signature.params.len()), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn literal() {
+    assert_eq!(t("1"), "int");
+    assert_eq!(t("[true]"), "[bool]");
+    assert_eq!(t("[]"), "[T]");
+    assert_eq!(t(r#"{"a": [1]}"#), "{str: [int]}");
+    assert_eq!(t(r#"#{"a"}"#), "#{str}");
+    assert_eq!(t(r#"(1, "a")"#), "(int, str)");
+    assert_eq!(t("(1,)"), "(int,)");
+    assert_eq!(t(r#"[1, "a"]"#), "[any]");
+    assert_eq!(t("let a = 1;"), "unit");
+}
+
+#[test]
+fn closure() {
+    assert_eq!(t("fn(x) { x + 1 }"), "fn(int) -> int");
+    assert_eq!(t("fn(x) { x }"), "fn(T) -> T");
+    assert_eq!(t("fn(f, x) { f(f(x)) }"), "fn(fn(T) -> T, T) -> T");
+    assert_eq!(t("fn(x) { print(x) }"), "fn(T)");
+    assert_eq!(
+        t("fn(n) { if n > 0 { return \"a\"; } \"b\" }"),
+        "fn(int) -> str"
+    );
+
+    // generalized by `let`
+    assert_eq!(t(r#"let id = fn(x) { x }; (id(1), id("a"))"#), "(int, str)");
+
+    // recursive
+    assert_eq!(
+        t("let f = fn(n) { if n == 0 { 1 } else { n * f(n - 1) } }; f"),
+        "fn(int) -> int"
+    );
+
+    // globals bound after the closure
+    assert_eq!(
+        t("let f = fn() { g() + 1 }; let g = fn() { 2 }; f"),
+        "fn() -> int"
+    );
+}
+
+#[test]
+fn builtin() {
+    assert_eq!(t("map([1, 2], fn(x) { x > 1 })"), "[bool]");
+    assert_eq!(t("reduce([1], \"\", fn(acc, x) { acc })"), "str");
+    assert_eq!(t("head"), "fn([T]) -> T");
+    assert_eq!(t("append([1], 2, 3)"), "[int]");
+    assert_eq!(t("print(1, \"a\")"), "unit");
+    assert_eq!(t("keys({1: true})"), "[int]");
+
+    // overloaded
+    assert_eq!(t("len"), "any");
+    assert_eq!(t("len([])"), "int");
+    assert_eq!(t("len((1, true))"), "int");
+    assert_eq!(t("min([\"a\"])"), "str");
+    assert_eq!(t("sort_by([\"a\"], fn(s) { len(s) })"), "[str]");
+    assert_eq!(t("fn(xs) { max(xs) }"), "fn(T) -> any");
+}
+
+#[test]
+fn lenient() {
+    assert_eq!(e("undefined + 1"), []);
+    assert_eq!(e(r#"let xs = [1, "a"]; xs[0] + 1; xs[1] + "b""#), []);
+    assert_eq!(e("if true { 1 } else { \"a\" }"), []);
+    // not taken when evaluated
+    assert_eq!(e("if 1 { 2 }"), []);
+    assert_eq!(
+        e("let f = fn(x) { if x { 1 } else { 2 } }; f(1) + f(true)"),
+        []
+    );
+    assert_eq!(e("let f = fn(g) { g(g) }; f"), []);
+}
+
+#[test]
+fn mismatch() {
+    assert_eq!(
+        e(r#"let double = fn(x) { x * 2 }; double("a")"#),
+        [("mismatch", r#""a""#)]
+    );
+    assert_eq!(e("[1][true]"), [("mismatch", "true")]);
+    assert_eq!(
+        e(r#"let f = fn(n) { if n { return 1; } "a" }; f"#),
+        [("mismatch", r#""a""#)]
+    );
+    assert_eq!(
+        e(r#"let f = fn() { g() + 1 }; let g = fn() { "a" };"#),
+        [("mismatch", r#"fn() { "a" }"#)]
+    );
+
+    assert_eq!(
+        message("fn(f) { f(1) + f(true) }"),
+        "mismatched types: expected int, found bool"
+    );
+    assert_eq!(
+        message(r#"let apply = fn(f) { f(1) + 1 }; apply(fn(x) { "a" })"#),
+        "mismatched types: expected fn(int) -> int, found fn(int) -> str"
+    );
+
+    // the params of a closure passed as an arg are known in advance
+    assert_eq!(
+        e("let apply = fn(f) { f(1) }; apply(fn(s) { len(s) })"),
+        [("builtin", "len(s)")]
+    );
+}
+
+#[test]
+fn operator() {
+    assert_eq!(e(r#"1 + "a""#), [("binary", r#"1 + "a""#)]);
+    assert_eq!(e(r#""a" < "b""#), [("binary", r#""a" < "b""#)]);
+    assert_eq!(e("true + false"), [("binary", "true + false")]);
+    assert_eq!(e("#{1} - #{2}; [1] + [2]"), []);
+    assert_eq!(e("-true; !1"), [("unary", "-true"), ("unary", "!1")]);
+
+    // checked once the type of `x` is known
+    assert_eq!(e(r#"fn(x) { x - x; x == "a" }"#), [("binary", "x - x")]);
+
+    assert_eq!(
+        message("fn(x) { x * x } + 1"),
+        "invalid binary operator + between fn(T) -> T and int"
+    );
+}
+
+#[test]
+fn call() {
+    assert_eq!(e("1(2)"), [("call", "1(2)")]);
+    assert_eq!(e("fn(x) { x }(1, 2)"), [("arg_count", "fn(x) { x }(1, 2)")]);
+    assert_eq!(e("true[0]"), [("index", "true[0]")]);
+    assert_eq!(e("let f = fn(x) { x[0] }; f(1)"), []);
+    assert_eq!(e("fn(x) { x[0]; x + 1 }"), [("index", "x[0]")]);
+
+    assert_eq!(e("len(1)"), [("builtin", "len(1)")]);
+    assert_eq!(e("range(1, 2, 3)"), [("builtin", "range(1, 2, 3)")]);
+    assert_eq!(
+        e(r#"map([1], fn(x) { x + "1" })"#),
+        [("binary", r#"x + "1""#)]
+    );
+    assert_eq!(
+        message(r#"has({1: 2}, "a")"#),
+        "no signature of builtin has accepts ({int: int}, str)"
+    );
+
+    // a builtin shadowed by a `let`
+    assert_eq!(e("let len = fn() { 0 }; len()"), []);
+}
+
+#[test]
+fn sorted() {
+    assert_eq!(
+        e(r#"let f = fn(x) { x - x; x == "a" }; 1 + true"#),
+        [("binary", "x - x"), ("binary", "1 + true")]
+    );
+}
+
+#[test]
+fn annotation() {
+    assert_eq!(t("fn(x: str) { x }"), "fn(str) -> str");
+    assert_eq!(t("fn(xs) -> [int] { xs }"), "fn([int]) -> [int]");
+    assert_eq!(t("let x: any = 1; x"), "any");
+    assert_eq!(
+        t("let f: fn(int) -> int = fn(x) { x }; f"),
+        "fn(int) -> int"
+    );
+    assert_eq!(
+        t("let f = fn(n) -> int { if n == 0 { return 1; } f(n - 1) }; f"),
+        "fn(int) -> int"
+    );
+
+    assert_eq!(e(r#"let x: int = "a";"#), [("mismatch", r#""a""#)]);
+    assert_eq!(e(r#"fn(x: int) { x + "a" }"#), [("binary", r#"x + "a""#)]);
+    assert_eq!(e(r#"fn() -> str { 1 }"#), [("mismatch", "1")]);
+    assert_eq!(
+        message(r#"let f: fn(str) = fn(x: int) { x };"#),
+        "mismatched types: expected fn(str), found fn(int) -> int"
+    );
+}
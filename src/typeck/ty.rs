@@ -0,0 +1,216 @@
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Type {
+    Unit,
+    Int,
+    Bool,
+    Str,
+    Seq(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Set(Box<Type>),
+    // a tuple of any size in a signature if empty, which no code can build
+    Tuple(Vec<Type>),
+    Fn(Vec<Type>, Box<Type>),
+    // to be inferred, an index into the variables of the checker
+    Var(usize),
+    // the `i`th type parameter of a generic type, e.g. `T` of a builtin
+    Gen(usize),
+    // unknown statically, e.g. an element of a seq mixing ints and strs,
+    // which is compatible with any type
+    Dyn,
+}
+
+impl Type {
+    // apply `f` to each type directly inside this one
+    pub(crate) fn map(&self, mut f: impl FnMut(&Type) -> Type) -> Type {
+        match self {
+            Self::Seq(elem) => Self::Seq(Box::new(f(elem))),
+            Self::Map(key, value) => Self::Map(Box::new(f(key)), Box::new(f(value))),
+            Self::Set(elem) => Self::Set(Box::new(f(elem))),
+            Self::Tuple(elems) => Self::Tuple(elems.iter().map(f).collect()),
+            Self::Fn(params, ret) => {
+                let params = params.iter().map(&mut f).collect();
+                Self::Fn(params, Box::new(f(ret)))
+            }
+            other => other.clone(),
+        }
+    }
+
+    // replace the type parameters with `args`
+    pub(crate) fn instantiate(&self, args: &[Type]) -> Type {
+        match self {
+            Self::Gen(i) => args[*i].clone(),
+            other => other.map(|ty| ty.instantiate(args)),
+        }
+    }
+
+    fn has_gen(&self, i: usize) -> bool {
+        match self {
+            Self::Gen(j) => *j == i,
+            Self::Seq(elem) | Self::Set(elem) => elem.has_gen(i),
+            Self::Map(key, value) => key.has_gen(i) || value.has_gen(i),
+            Self::Tuple(elems) => elems.iter().any(|ty| ty.has_gen(i)),
+            Self::Fn(params, ret) => params.iter().any(|ty| ty.has_gen(i)) || ret.has_gen(i),
+            _ => false,
+        }
+    }
+}
+
+// a signature of a builtin as written in `builtin::ALL`, e.g.
+// `fn([T], fn(T) -> U) -> [U]`
+pub(crate) struct Signature {
+    pub(crate) params: Vec<Type>,
+    // type of each trailing arg, e.g. `T...`
+    pub(crate) variadic: Option<Type>,
+    pub(crate) ret: Type,
+    pub(crate) generics: usize,
+}
+
+impl Signature {
+    // `seq`, `map` and `set` are of any element, `tuple` is of any size, `T`
+    // in `fn(T...)` is a type parameter of each arg unless it appears
+    // elsewhere in the signature
+    pub(crate) fn parse(signature: &str) -> Self {
+        let mut parser = Parser {
+            rest: signature,
+            names: Vec::new(),
+        };
+
+        parser.eat("fn(");
+
+        let mut params = Vec::new();
+        let mut variadic = None;
+        while !parser.eat(")") {
+            let param = parser.ty();
+            if parser.eat("...") {
+                variadic = Some(param);
+            } else {
+                params.push(param);
+            }
+            parser.eat(",");
+        }
+
+        let ret = if parser.eat("->") {
+            parser.ty()
+        } else {
+            Type::Unit
+        };
+
+        assert!(parser.rest.is_empty(), "invalid signature: {}", signature);
+
+        Self {
+            params,
+            variadic,
+            ret,
+            generics: parser.names.len(),
+        }
+    }
+
+    pub(crate) fn accepts(&self, args: usize) -> bool {
+        match self.variadic {
+            Some(_) => args >= self.params.len(),
+            None => args == self.params.len(),
+        }
+    }
+
+    // whether the type parameter `i` of the variadic args is also used by the
+    // other params or the return type
+    pub(crate) fn is_shared(&self, i: usize) -> bool {
+        self.params.iter().any(|param| param.has_gen(i)) || self.ret.has_gen(i)
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    // name of each type parameter
+    names: Vec<Option<&'a str>>,
+}
+
+impl<'a> Parser<'a> {
+    fn eat(&mut self, token: &str) -> bool {
+        self.rest = self.rest.trim_start();
+
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // a new type parameter, named if it is written as e.g. `T`
+    fn gen(&mut self) -> Type {
+        self.names.push(None);
+        Type::Gen(self.names.len() - 1)
+    }
+
+    fn ty(&mut self) -> Type {
+        if self.eat("fn(") {
+            let mut params = Vec::new();
+            while !self.eat(")") {
+                params.push(self.ty());
+                self.eat(",");
+            }
+            let ret = if self.eat("->") {
+                self.ty()
+            } else {
+                Type::Unit
+            };
+            Type::Fn(params, Box::new(ret))
+        } else if self.eat("[") {
+            let elem = self.ty();
+            if self.eat("]") {
+                Type::Seq(Box::new(elem))
+            } else {
+                // `[T, U]`, a seq of a fixed size with elements of different
+                // types
+                while !self.eat("]") {
+                    self.eat(",");
+                    self.ty();
+                }
+                Type::Seq(Box::new(Type::Dyn))
+            }
+        } else if self.eat("#{") {
+            let elem = self.ty();
+            self.eat("}");
+            Type::Set(Box::new(elem))
+        } else if self.eat("{") {
+            let key = self.ty();
+            self.eat(":");
+            let value = self.ty();
+            self.eat("}");
+            Type::Map(Box::new(key), Box::new(value))
+        } else {
+            let end = self
+                .rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(self.rest.len());
+            let (name, rest) = self.rest.split_at(end);
+            self.rest = rest;
+
+            match name {
+                "int" => Type::Int,
+                "bool" => Type::Bool,
+                "str" => Type::Str,
+                "seq" => Type::Seq(Box::new(self.gen())),
+                "map" => Type::Map(Box::new(self.gen()), Box::new(self.gen())),
+                "set" => Type::Set(Box::new(self.gen())),
+                "tuple" => Type::Tuple(Vec::new()),
+                name => {
+                    assert!(
+                        name.starts_with(|c: char| c.is_ascii_uppercase()),
+                        "invalid type: {:?}",
+                        name
+                    );
+                    match self.names.iter().position(|n| *n == Some(name)) {
+                        Some(i) => Type::Gen(i),
+                        None => {
+                            self.names.push(Some(name));
+                            Type::Gen(self.names.len() - 1)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
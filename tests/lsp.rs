@@ -0,0 +1,286 @@
+// drive `oris-lsp` over stdio as an editor would
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_oris-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let mut client = Self {
+            child,
+            stdin,
+            stdout,
+        };
+
+        let response = client.request(0, "initialize", r#"{"capabilities":{}}"#);
+        assert!(
+            response.contains(r#""definitionProvider":true"#),
+            "{}",
+            response
+        );
+        client.notify("initialized", "{}");
+
+        client
+    }
+
+    fn send(&mut self, content: &str) {
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn request(&mut self, id: usize, method: &str, params: &str) -> String {
+        self.send(&format!(
+            r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{}}}"#,
+            id, method, params
+        ));
+
+        self.receive()
+    }
+
+    fn notify(&mut self, method: &str, params: &str) {
+        self.send(&format!(
+            r#"{{"jsonrpc":"2.0","method":"{}","params":{}}}"#,
+            method, params
+        ));
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> String {
+        self.notify(
+            "textDocument/didOpen",
+            &format!(
+                r#"{{"textDocument":{{"uri":"{}","languageId":"oris","version":1,"text":{:?}}}}}"#,
+                uri, text
+            ),
+        );
+
+        // the diagnostics
+        self.receive()
+    }
+
+    fn receive(&mut self) -> String {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+
+            match header.trim_end() {
+                "" => break,
+                header => {
+                    if let Some(value) = header.strip_prefix("Content-Length: ") {
+                        length = value.parse().unwrap();
+                    }
+                }
+            }
+        }
+
+        let mut content = vec![0; length];
+        self.stdout.read_exact(&mut content).unwrap();
+        String::from_utf8(content).unwrap()
+    }
+
+    fn exit(mut self) {
+        assert_eq!(
+            self.request(99, "shutdown", "null"),
+            r#"{"jsonrpc":"2.0","id":99,"result":null}"#
+        );
+        self.notify("exit", "null");
+
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn position(uri: &str, line: usize, character: usize) -> String {
+    format!(
+        r#"{{"textDocument":{{"uri":"{}"}},"position":{{"line":{},"character":{}}}}}"#,
+        uri, line, character
+    )
+}
+
+#[test]
+fn diagnostics() {
+    let mut client = Client::start();
+
+    let published = client.open("file:///syntax.oris", "let a = 1 +;\nlet b = $;\n");
+    assert!(
+        published.contains(r#""uri":"file:///syntax.oris""#),
+        "{}",
+        published
+    );
+    assert!(
+        published.contains(
+            r#""range":{"start":{"line":0,"character":11},"end":{"line":0,"character":12}}"#
+        ),
+        "{}",
+        published
+    );
+    assert!(
+        published.contains(
+            r#""range":{"start":{"line":1,"character":8},"end":{"line":1,"character":9}}"#
+        ),
+        "{}",
+        published
+    );
+
+    let published = client.open("file:///runtime.oris", "print(1);\nlen(1)\n");
+    assert!(
+        published.contains(r#""range":{"start":{"line":1,"character":0},"end":{"line":1,"character":6}},"severity":1,"code":"arg_type""#),
+        "{}",
+        published
+    );
+
+    client.notify(
+        "textDocument/didChange",
+        r#"{"textDocument":{"uri":"file:///runtime.oris","version":2},"contentChanges":[{"text":"len(\"\")"}]}"#,
+    );
+    assert!(client.receive().contains(r#""diagnostics":[]"#));
+
+    client.exit();
+}
+
+#[test]
+fn runaway() {
+    let mut client = Client::start();
+
+    // too deep for any stack
+    let published = client.open("file:///deep.oris", "let f = fn(n) { 1 + f(n) }; f(0)");
+    assert!(published.contains(r#""code":"depth""#), "{}", published);
+
+    // stopped after a while, without a diagnostic
+    let published = client.open("file:///loop.oris", "let f = fn() { f() }; f()");
+    assert!(published.contains(r#""diagnostics":[]"#), "{}", published);
+
+    let published = client.open("file:///after.oris", "1 + true");
+    assert!(published.contains(r#""code":"binary""#), "{}", published);
+
+    // an edit interrupts the evaluation of the previous text, whose
+    // diagnostics are never published
+    client.notify(
+        "textDocument/didChange",
+        r#"{"textDocument":{"uri":"file:///loop.oris","version":2},"contentChanges":[{"text":"let f = fn() { f() }; f()"}]}"#,
+    );
+    client.notify(
+        "textDocument/didChange",
+        r#"{"textDocument":{"uri":"file:///loop.oris","version":3},"contentChanges":[{"text":"len(1)"}]}"#,
+    );
+    let published = client.receive();
+    assert!(published.contains(r#""code":"arg_type""#), "{}", published);
+
+    // a burst of edits, only the latest text is evaluated
+    for version in 4..100 {
+        client.notify(
+            "textDocument/didChange",
+            &format!(
+                r#"{{"textDocument":{{"uri":"file:///loop.oris","version":{}}},"contentChanges":[{{"text":"let f = fn() {{ f() }}; f()"}}]}}"#,
+                version
+            ),
+        );
+    }
+    client.notify(
+        "textDocument/didChange",
+        r#"{"textDocument":{"uri":"file:///loop.oris","version":100},"contentChanges":[{"text":"len(1)"}]}"#,
+    );
+    let published = client.receive();
+    assert!(published.contains(r#""code":"arg_type""#), "{}", published);
+
+    client.exit();
+}
+
+#[test]
+fn navigation() {
+    let mut client = Client::start();
+
+    let code = "let double = fn(x) { x * 2 };\nlet first = fn(xs) { xs[0] };\ndouble(first([1]))\n";
+    let published = client.open("file:///main.oris", code);
+    assert!(published.contains(r#""diagnostics":[]"#), "{}", published);
+
+    // `double` on the last line
+    let response = client.request(
+        1,
+        "textDocument/definition",
+        &position("file:///main.oris", 2, 3),
+    );
+    assert!(
+        response.contains(
+            r#""range":{"start":{"line":0,"character":4},"end":{"line":0,"character":10}}"#
+        ),
+        "{}",
+        response
+    );
+
+    // `x` in the body is the parameter
+    let response = client.request(
+        2,
+        "textDocument/definition",
+        &position("file:///main.oris", 0, 21),
+    );
+    assert!(
+        response.contains(
+            r#""range":{"start":{"line":0,"character":16},"end":{"line":0,"character":17}}"#
+        ),
+        "{}",
+        response
+    );
+
+    let response = client.request(
+        3,
+        "textDocument/hover",
+        &position("file:///main.oris", 2, 0),
+    );
+    assert!(response.contains(r#""result":null"#), "{}", response);
+
+    client.open("file:///hover.oris", "len([1, 2])");
+    let response = client.request(
+        4,
+        "textDocument/hover",
+        &position("file:///hover.oris", 0, 1),
+    );
+    assert!(response.contains("len: fn(str) -> int"), "{}", response);
+
+    let response = client.request(
+        5,
+        "textDocument/completion",
+        &position("file:///main.oris", 2, 0),
+    );
+    assert!(
+        response.contains(r#"{"label":"double","kind":6}"#),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains(r#"{"label":"first","kind":6}"#),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains(r#""label":"len","kind":3"#),
+        "{}",
+        response
+    );
+
+    let response = client.request(6, "textDocument/formatting", "{}");
+    assert!(response.contains(r#""code":-32601"#), "{}", response);
+
+    client.exit();
+}
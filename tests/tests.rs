@@ -21,7 +21,7 @@ fn main() {
 
         let path = entry.path();
 
-        if path.extension().map_or(false, |ext| ext == "oris") {
+        if path.extension().is_some_and(|ext| ext == "oris") {
             eprintln!("test {}", path.display());
 
             let ok = test_fail(&path);
@@ -44,10 +44,32 @@ fn test_pass(path: &std::path::Path) -> bool {
     let code = std::fs::read(path).unwrap();
 
     let mut env = oris::Env::new();
-    match oris::entry(&mut env, &code) {
+    if let Err(error) = oris::entry(&mut env, &code) {
+        eprintln!("failed, error: {}", error);
+        return false;
+    }
+
+    // code which runs must type check
+    if let Some(error) = oris::check_types(&code).first() {
+        eprintln!("type check failed, error: {}", error);
+        return false;
+    }
+
+    // neither must optimizing
+    let program = oris::Program::compile(&code).unwrap().optimize();
+    if let Err(error) = program.run(&mut oris::Env::new()) {
+        eprintln!("optimized code failed, error: {}", error);
+        return false;
+    }
+
+    // formatting must not change what the code does
+    let formatted = oris::format(&code).unwrap();
+    let mut env = oris::Env::new();
+    match oris::entry(&mut env, formatted.as_bytes()) {
         Ok(_) => true,
         Err(error) => {
-            eprintln!("failed, error: {}", error);
+            eprintln!("formatted code failed, error: {}", error);
+            eprintln!("{}", formatted);
             false
         }
     }
@@ -67,15 +89,29 @@ fn test_fail(path: &std::path::Path) -> bool {
             let expected_error_file_path = path.with_extension("oris.error");
             match std::fs::read_to_string(&expected_error_file_path) {
                 Ok(mut expected_error_string) => {
-                    if expected_error_string.chars().next_back() == Some('\n') {
+                    if expected_error_string.ends_with('\n') {
                         expected_error_string.pop();
                     }
 
                     let (line, column) = error.line_column(&code);
-                    let found_error_string =
-                        format!("{}:{}\n{}", line + 1, column + 1, error.to_string());
+                    let found_error_string = format!("{}:{}\n{}", line + 1, column + 1, error);
+
+                    // optimizing must not change the error or its position
+                    let optimized = oris::Program::compile(&code)
+                        .and_then(|program| program.optimize().run(&mut oris::Env::new()));
+                    let same = match optimized {
+                        Ok(_) => false,
+                        Err(optimized) => {
+                            optimized.to_string() == error.to_string()
+                                && optimized.span() == error.span()
+                        }
+                    };
+
+                    if !same {
+                        eprintln!(" optimized code does not fail with the same error");
 
-                    if found_error_string == expected_error_string {
+                        false
+                    } else if found_error_string == expected_error_string {
                         true
                     } else {
                         eprintln!(" failed with an unexpected error:");